@@ -1,67 +1,204 @@
-use std::ops::RangeBounds;
+use std::{collections::HashMap, fmt};
 
 use bevy::prelude::*;
-use bevy_turborand::GlobalRng;
-use enum_map::{enum_map, EnumMap};
+use bevy_turborand::RngComponent;
+use serde::{
+    de::{MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 
 use twilight_model::id::{marker::UserMarker, Id};
 
-use crate::{components::*, game_helpers::Difficulty};
+use crate::{components::*, enemies::EnemyDefinition, game_helpers::Difficulty};
 
-#[derive(Clone, Copy, Component, Debug, Eq, Hash, PartialEq)]
-pub struct BygoneParts(pub EnumMap<BygonePart, Vitality>);
+/// Health every part of the enemies gains with each wave a survival battle clears
+const WAVE_HEALTH: usize = 1;
+/// The enemies of a survival battle hit one harder every this many waves
+const WAVES_PER_DAMAGE: usize = 2;
 
-#[derive(Bundle, Clone, Debug)]
-pub struct Bygone03Bundle {
+/// Every part of an enemy in the order its definition lists them.
+/// Stored as a map by part id, which is also how saves from before the enemy definitions look.
+#[derive(Clone, Component, Debug, Default, Eq, Hash, PartialEq)]
+pub struct BygoneParts(pub Vec<(BygonePart, Vitality)>);
+
+impl BygoneParts {
+    pub fn get(&self, part: &BygonePart) -> Option<&Vitality> {
+        self.0
+            .iter()
+            .find(|(known, _)| known == part)
+            .map(|(_, vitality)| vitality)
+    }
+
+    pub fn get_mut(&mut self, part: &BygonePart) -> Option<&mut Vitality> {
+        self.0
+            .iter_mut()
+            .find(|(known, _)| known == part)
+            .map(|(_, vitality)| vitality)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Vitality> {
+        self.0.iter_mut().map(|(_, vitality)| vitality)
+    }
+}
+
+impl Serialize for BygoneParts {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(part, vitality)| (part, vitality)))
+    }
+}
+
+impl<'de> Deserialize<'de> for BygoneParts {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PartsVisitor;
+
+        impl<'de> Visitor<'de> for PartsVisitor {
+            type Value = BygoneParts;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map from part ids to their vitality")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut parts = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    parts.push(entry);
+                }
+                Ok(BygoneParts(parts))
+            }
+        }
+
+        deserializer.deserialize_map(PartsVisitor)
+    }
+}
+
+#[derive(Bundle, Debug)]
+pub struct BygoneBundle {
     game_id: GameId,
+    kind: EnemyKind,
+    slot: EnemySlot,
     parts: BygoneParts,
     attack: Attack,
-    stage: Bygone03Stage,
+    stage: BygoneStage,
+    effects: StatusEffects,
+    targeting: TargetingPolicy,
+    specials: SpecialMoves,
+    rng: RngComponent,
     _enemy: Enemy,
     _active: Active,
 }
 
-impl Bygone03Bundle {
+impl BygoneBundle {
     pub fn new(
-        parts_health_range: impl RangeBounds<usize> + Clone,
-        attack_range: impl RangeBounds<usize> + Clone,
+        kind: EnemyKind,
+        slot: EnemySlot,
+        definition: &EnemyDefinition,
+        difficulty: Difficulty,
         game_id: GameId,
-        rng: &mut GlobalRng,
+        mut rng: RngComponent,
     ) -> Self {
-        let wings_hp = rng.usize(parts_health_range.clone());
-        let parts = BygoneParts(enum_map! {
-            BygonePart::Core => Vitality::new(rng.usize(parts_health_range.clone()), 70),
-            BygonePart::Sensor => Vitality::new(rng.usize(parts_health_range.clone()), 80),
-            BygonePart::Gun => Vitality::new(rng.usize(parts_health_range.clone()), 50),
-            BygonePart::LeftWing => Vitality::new(wings_hp, 30),
-            BygonePart::RightWing => Vitality::new(wings_hp, 30),
-        });
-        let attack = Attack::new(rng.usize(attack_range), 100);
+        let scaling = definition.scaling(difficulty);
+        // Shared health is rolled before the rest, in the order the groups first show up
+        let mut group_health = HashMap::new();
+        for part_definition in definition.parts.iter() {
+            if let Some(group) = &part_definition.health_group {
+                group_health
+                    .entry(group)
+                    .or_insert_with(|| rng.usize(scaling.health.range()));
+            }
+        }
+        let parts = BygoneParts(
+            definition
+                .parts
+                .iter()
+                .map(|part_definition| {
+                    let health = match &part_definition.health_group {
+                        Some(group) => group_health[group],
+                        None => rng.usize(scaling.health.range()),
+                    };
+                    (
+                        part_definition.id.clone(),
+                        Vitality::new(health, part_definition.dodge),
+                    )
+                })
+                .collect(),
+        );
+        let attack = Attack::new(rng.usize(scaling.damage.range()), definition.accuracy)
+            .with_spread(definition.damage_spread)
+            .with_crit(definition.crit_chance, CRIT_MULTIPLIER);
 
         Self {
             game_id,
+            kind,
+            slot,
             parts,
             attack,
-            stage: Bygone03Stage::Armored,
+            stage: definition.first_stage(),
+            effects: StatusEffects::default(),
+            targeting: definition.targeting(difficulty),
+            specials: SpecialMoves::default(),
+            rng,
             _enemy: Enemy,
             _active: Active,
         }
     }
 
-    pub fn with_difficulty(game_id: GameId, difficulty: Difficulty, rng: &mut GlobalRng) -> Self {
-        let parts_health_range = match difficulty {
-            Difficulty::Easy => 1..=1,
-            Difficulty::Medium => 1..=2,
-            Difficulty::Hard => 1..=3,
-            Difficulty::RealBullets => 1..=3,
-        };
-        let attack_range = match difficulty {
-            Difficulty::Easy => 1..=1,
-            Difficulty::Medium => 1..=2,
-            Difficulty::Hard => 1..=3,
-            Difficulty::RealBullets => 6..=6,
-        };
-        Self::new(parts_health_range, attack_range, game_id, rng)
+    /// Makes the enemy stronger for a later wave of a survival battle, the first wave stays as it is
+    pub fn in_wave(mut self, wave: usize) -> Self {
+        for (_, vitality) in self.parts.0.iter_mut() {
+            *vitality = Vitality::new(
+                vitality.health().max() + wave * WAVE_HEALTH,
+                vitality.dodge(),
+            );
+        }
+        self.attack.add_damage(wave / WAVES_PER_DAMAGE);
+        self
+    }
+
+    pub fn with_difficulty(
+        kind: EnemyKind,
+        slot: EnemySlot,
+        definition: &EnemyDefinition,
+        game_id: GameId,
+        difficulty: Difficulty,
+        seed: u64,
+    ) -> Self {
+        Self::new(
+            kind,
+            slot,
+            definition,
+            difficulty,
+            game_id,
+            RngComponent::new(Some(seed)),
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn restore(
+        game_id: GameId,
+        kind: EnemyKind,
+        slot: EnemySlot,
+        parts: BygoneParts,
+        attack: Attack,
+        stage: BygoneStage,
+        effects: StatusEffects,
+        targeting: TargetingPolicy,
+        specials: SpecialMoves,
+        rng: RngComponent,
+    ) -> Self {
+        Self {
+            game_id,
+            kind,
+            slot,
+            parts,
+            attack,
+            stage,
+            effects,
+            targeting,
+            specials,
+            rng,
+            _enemy: Enemy,
+            _active: Active,
+        }
     }
 }
 
@@ -70,24 +207,57 @@ pub struct PlayerBundle {
     user_id: UserIdComponent,
     name: PlayerName,
     game_id: GameId,
+    class: PlayerClass,
     vitality: Vitality,
     attack: Attack,
+    cooldowns: Cooldowns,
+    effects: StatusEffects,
+    threat: Threat,
     _player: Player,
     _active: Active,
     _ready: Ready,
 }
 
 impl PlayerBundle {
-    pub fn new(user_id: Id<UserMarker>, name: PlayerName, game_id: GameId) -> Self {
+    pub fn new(
+        user_id: Id<UserMarker>,
+        name: PlayerName,
+        game_id: GameId,
+        class: PlayerClass,
+    ) -> Self {
         Self {
             user_id: UserIdComponent(user_id),
             name,
             game_id,
-            vitality: Vitality::new(6, 100),
-            attack: Attack::new(1, 0),
+            class,
+            vitality: class.vitality(),
+            attack: class.attack(),
+            cooldowns: Cooldowns::default(),
+            effects: StatusEffects::default(),
+            threat: Threat::default(),
             _player: Player,
             _active: Active,
             _ready: Ready,
         }
     }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn restore(
+        user_id: Id<UserMarker>,
+        name: PlayerName,
+        game_id: GameId,
+        class: PlayerClass,
+        vitality: Vitality,
+        attack: Attack,
+        cooldowns: Cooldowns,
+        effects: StatusEffects,
+    ) -> Self {
+        Self {
+            vitality,
+            attack,
+            cooldowns,
+            effects,
+            ..Self::new(user_id, name, game_id, class)
+        }
+    }
 }
@@ -1,16 +1,105 @@
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+
+use crate::{
+    components::PlayerClass,
+    enemies::DEFAULT_ENEMY,
+    game_helpers::{Difficulty, GameScope},
+    simulation::Strategy,
+    timings::TimingOverrides,
+};
 
 /// Discord bot to fight enemies from Uof7
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 pub struct Cli {
-    /// Saved games file
-    // #[clap(short, long, value_parser, value_name = "FILE")]
-    // pub games_path: PathBuf,
-    // /// Scoreboard file
-    // #[clap(short, long, value_parser, value_name = "FILE")]
-    // pub scoreboard_path: PathBuf,\
+    /// Saved games file, battles are restored from it on startup
+    #[clap(short, long, value_parser, value_name = "FILE")]
+    pub games_path: Option<PathBuf>,
+    /// Saved game messages file, lets restored battles keep updating their messages
+    #[clap(short, long, value_parser, value_name = "FILE")]
+    pub messages_path: Option<PathBuf>,
+    /// Scoreboard file
+    #[clap(short, long, value_parser, value_name = "FILE")]
+    pub scoreboard_path: Option<PathBuf>,
+    /// Guild settings file, the guilds change their settings with /config
+    #[clap(short, long, value_parser, value_name = "FILE")]
+    pub config_path: Option<PathBuf>,
+    /// Replay log file, every battle input is appended to it so the battle can be replayed later
+    #[clap(short, long, value_parser, value_name = "FILE")]
+    pub replay_path: Option<PathBuf>,
+    /// Enemy definitions file, the built-in enemies are used without it
+    #[clap(short, long, global = true, value_parser, value_name = "FILE")]
+    pub enemies_path: Option<PathBuf>,
+    /// Timings file with the common timings and the ones of particular guilds
+    #[clap(short, long, value_parser, value_name = "FILE")]
+    pub timings_path: Option<PathBuf>,
+    /// The common timings, they take precedence over the timings file
+    #[clap(flatten)]
+    pub timings: TimingOverrides,
     /// Update bot commands
     #[clap(short, long, action)]
     pub update_commands: bool,
+    /// Play in the terminal instead of Discord
+    #[clap(short, long, action)]
+    pub local: bool,
+    /// Where a battle runs: one per channel or one per whole guild
+    #[clap(long, value_parser, value_name = "SCOPE", default_value_t = GameScope::Channel)]
+    pub game_scope: GameScope,
+    /// Number of fake players available in local mode
+    #[clap(long, value_parser, value_name = "COUNT", default_value_t = 3)]
+    pub local_players: u64,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run headless battles with scripted players and report the balance stats
+    Simulate(SimulateArgs),
+    /// Re-run logged battles from a replay log and check them against the recorded battle log
+    Replay(ReplayArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct SimulateArgs {
+    /// Battles to run for every difficulty and player count
+    #[clap(short, long, value_parser, default_value_t = 1000)]
+    pub battles: usize,
+    /// Player counts to try
+    #[clap(short, long, value_parser, value_delimiter = ',', default_values_t = vec![1, 2, 3, 4])]
+    pub players: Vec<usize>,
+    /// Difficulties to try, all of them by default
+    #[clap(short, long, value_parser, value_delimiter = ',')]
+    pub difficulties: Vec<Difficulty>,
+    /// Id of the enemy to fight
+    #[clap(long, value_parser, default_value_t = DEFAULT_ENEMY.to_string())]
+    pub enemy: String,
+    /// Class every scripted player fights as
+    #[clap(long, value_parser, default_value_t = PlayerClass::default())]
+    pub class: PlayerClass,
+    /// How the scripted players pick their targets
+    #[clap(short, long, value_enum, default_value_t = Strategy::Random)]
+    pub strategy: Strategy,
+    /// Turns after which a battle counts as expired, by default as many as a live battle lasts with
+    /// the common timings
+    #[clap(short, long, value_parser)]
+    pub max_turns: Option<usize>,
+    /// Seed for reproducible runs
+    #[clap(long, value_parser)]
+    pub seed: Option<u64>,
+    /// Print the report as JSON instead of a table
+    #[clap(short, long, action)]
+    pub json: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ReplayArgs {
+    /// Replay log written with --replay-path
+    #[clap(value_parser, value_name = "FILE")]
+    pub path: PathBuf,
+    /// Only replay the game with this id
+    #[clap(short, long, value_parser)]
+    pub game: Option<u128>,
 }
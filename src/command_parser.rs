@@ -1,48 +1,168 @@
 use std::str::FromStr;
 
-use phf::phf_ordered_map;
 use twilight_model::application::interaction::application_command::{
     CommandData, CommandOptionValue,
 };
 
-use crate::{components::BygonePart, game_helpers::Difficulty, localization::Language};
+use crate::{
+    components::PlayerClass,
+    enemies::DEFAULT_ENEMY,
+    game_helpers::{Difficulty, GameMode, TurnMode},
+    guild_config::{GameEnders, GuildConfigUpdate},
+    localization::Language,
+};
 
 pub const BATTLE_COMMAND: &str = "battle";
+pub const LEADERBOARD_COMMAND: &str = "leaderboard";
+pub const CONFIG_COMMAND: &str = "config";
+pub const END_COMMAND: &str = "end_battle";
 pub const LANGUAGE_COMMAND_OPTION: &str = "language";
 pub const DIFFICULTY_COMMAND_OPTION: &str = "difficulty";
+pub const ENEMY_COMMAND_OPTION: &str = "enemy";
+pub const CLASS_COMMAND_OPTION: &str = "class";
+pub const MODE_COMMAND_OPTION: &str = "mode";
+pub const TURNS_COMMAND_OPTION: &str = "turns";
+pub const COOLDOWN_COMMAND_OPTION: &str = "cooldown";
+pub const ALLOW_CHANNEL_COMMAND_OPTION: &str = "allow_channel";
+pub const DISALLOW_CHANNEL_COMMAND_OPTION: &str = "disallow_channel";
+pub const ENDERS_COMMAND_OPTION: &str = "who_can_end";
 
-pub fn is_game_starting(command: &CommandData) -> Option<(Language, Difficulty)> {
+/// The enemy is passed on as is, unknown ones are replaced when the game starts.
+/// The language and the difficulty are left to the guild's settings when they are not given
+#[allow(clippy::type_complexity)]
+pub fn is_game_starting(
+    command: &CommandData,
+) -> Option<(
+    Option<Language>,
+    Option<Difficulty>,
+    String,
+    PlayerClass,
+    GameMode,
+    TurnMode,
+)> {
     if command.name != BATTLE_COMMAND {
         return None;
     }
-    let mut language = Language::En;
-    let mut difficulty = Difficulty::Medium;
+    let mut language = None;
+    let mut difficulty = None;
+    let mut enemy = DEFAULT_ENEMY.to_string();
+    let mut class = PlayerClass::default();
+    let mut mode = GameMode::default();
+    let mut turns = TurnMode::default();
     for option in &command.options {
         if option.name == LANGUAGE_COMMAND_OPTION {
             if let CommandOptionValue::String(lang_name) = &option.value {
                 if let Ok(lang) = Language::from_str(lang_name) {
-                    language = lang;
+                    language = Some(lang);
                 }
             }
         }
         if option.name == DIFFICULTY_COMMAND_OPTION {
             if let CommandOptionValue::String(level) = &option.value {
                 if let Ok(level) = Difficulty::from_str(level) {
-                    difficulty = level;
+                    difficulty = Some(level);
+                }
+            }
+        }
+        if option.name == ENEMY_COMMAND_OPTION {
+            if let CommandOptionValue::String(id) = &option.value {
+                enemy = id.clone();
+            }
+        }
+        if option.name == CLASS_COMMAND_OPTION {
+            if let CommandOptionValue::String(class_name) = &option.value {
+                if let Ok(chosen) = PlayerClass::from_str(class_name) {
+                    class = chosen;
+                }
+            }
+        }
+        if option.name == MODE_COMMAND_OPTION {
+            if let CommandOptionValue::String(mode_name) = &option.value {
+                if let Ok(chosen) = GameMode::from_str(mode_name) {
+                    mode = chosen;
+                }
+            }
+        }
+        if option.name == TURNS_COMMAND_OPTION {
+            if let CommandOptionValue::String(turns_name) = &option.value {
+                if let Ok(chosen) = TurnMode::from_str(turns_name) {
+                    turns = chosen;
                 }
             }
         }
     }
-    Some((language, difficulty))
+    Some((language, difficulty, enemy, class, mode, turns))
 }
 
-pub const BYGONE_PARTS_FROM_EMOJI_NAME: phf::OrderedMap<&str, BygonePart> = phf_ordered_map! {
-    "🇨" => BygonePart::Core,
-    "🇸" => BygonePart::Sensor,
-    "🇱" => BygonePart::LeftWing,
-    "🇷" => BygonePart::RightWing,
-    "🇬" => BygonePart::Gun,
-};
+/// The language is left to the guild's settings and the locales when it is not given
+pub fn is_leaderboard_requested(command: &CommandData) -> Option<Option<Language>> {
+    if command.name != LEADERBOARD_COMMAND {
+        return None;
+    }
+    let mut language = None;
+    for option in &command.options {
+        if option.name == LANGUAGE_COMMAND_OPTION {
+            if let CommandOptionValue::String(lang_name) = &option.value {
+                if let Ok(lang) = Language::from_str(lang_name) {
+                    language = Some(lang);
+                }
+            }
+        }
+    }
+    Some(language)
+}
+
+pub fn is_config_requested(command: &CommandData) -> Option<GuildConfigUpdate> {
+    if command.name != CONFIG_COMMAND {
+        return None;
+    }
+    let mut update = GuildConfigUpdate::default();
+    for option in &command.options {
+        if option.name == LANGUAGE_COMMAND_OPTION {
+            if let CommandOptionValue::String(lang_name) = &option.value {
+                if let Ok(lang) = Language::from_str(lang_name) {
+                    update.language = Some(lang);
+                }
+            }
+        }
+        if option.name == DIFFICULTY_COMMAND_OPTION {
+            if let CommandOptionValue::String(level) = &option.value {
+                if let Ok(level) = Difficulty::from_str(level) {
+                    update.difficulty = Some(level);
+                }
+            }
+        }
+        if option.name == COOLDOWN_COMMAND_OPTION {
+            if let CommandOptionValue::Integer(secs) = option.value {
+                if let Ok(secs) = u64::try_from(secs) {
+                    update.cooldown_secs = Some(secs);
+                }
+            }
+        }
+        if option.name == ALLOW_CHANNEL_COMMAND_OPTION {
+            if let CommandOptionValue::Channel(channel_id) = option.value {
+                update.allow_channel = Some(channel_id);
+            }
+        }
+        if option.name == DISALLOW_CHANNEL_COMMAND_OPTION {
+            if let CommandOptionValue::Channel(channel_id) = option.value {
+                update.disallow_channel = Some(channel_id);
+            }
+        }
+        if option.name == ENDERS_COMMAND_OPTION {
+            if let CommandOptionValue::String(enders_name) = &option.value {
+                if let Ok(enders) = GameEnders::from_str(enders_name) {
+                    update.enders = Some(enders);
+                }
+            }
+        }
+    }
+    Some(update)
+}
+
+pub fn is_game_ending(command: &CommandData) -> bool {
+    command.name == END_COMMAND
+}
 
 // Maybe use Ъ with a very small chance
 // pub const AUXILIARY_EMOJIS: [&str; 5] = [
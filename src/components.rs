@@ -1,18 +1,23 @@
-use std::time::SystemTime;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    ops::RangeInclusive,
+    time::SystemTime,
+};
 
 use bevy::prelude::Component;
 
 use derive_new::new;
-use enum_map::Enum;
 use serde::{Deserialize, Serialize};
+use strum::{Display, EnumIter, EnumString, IntoStaticStr};
 use twilight_model::id::{marker::UserMarker, Id};
 
 use crate::{
     localization::{Localization, RenderText},
-    logging::format_time,
+    logging::{battle_traces, format_time},
 };
 
-#[derive(Clone, Copy, Component, Debug, Default, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Component, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct Health {
     current: usize,
     max: usize,
@@ -35,6 +40,14 @@ impl Health {
         self.current > 0
     }
 
+    pub fn restore(&mut self, amount: usize) {
+        self.current = (self.current + amount).min(self.max)
+    }
+
+    pub fn wounded(&self) -> bool {
+        self.current < self.max
+    }
+
     pub fn current(&self) -> usize {
         self.current
     }
@@ -57,7 +70,7 @@ impl RenderText for Health {
     }
 }
 
-#[derive(Clone, Copy, Component, Debug, Default, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Component, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct Vitality {
     health: Health,
     dodge: isize,
@@ -83,16 +96,27 @@ impl Vitality {
         self.dodge += modifier
     }
 
+    pub fn heal(&mut self, amount: usize) {
+        self.health.restore(amount)
+    }
+
+    /// Damage that can't be dodged, like burns
+    pub fn suffer(&mut self, damage: usize) {
+        self.health.reduce(damage)
+    }
+
     pub fn take_attack(&mut self, damage: usize, accuracy: isize) -> bool {
         if accuracy >= self.dodge {
             self.health.reduce(damage);
-            println!(
-                "{} - components - Taking damage: {}, accuracy: {}, dodge: {}",
-                format_time(),
-                damage,
-                accuracy,
-                self.dodge
-            );
+            if battle_traces() {
+                println!(
+                    "{} - components - Taking damage: {}, accuracy: {}, dodge: {}",
+                    format_time(),
+                    damage,
+                    accuracy,
+                    self.dodge
+                );
+            }
             return true;
         }
         false
@@ -113,13 +137,64 @@ impl RenderText for Vitality {
     }
 }
 
-#[derive(Clone, Copy, Component, Debug, Default, Eq, Hash, new, Ord, PartialEq, PartialOrd)]
+#[derive(
+    Clone,
+    Copy,
+    Component,
+    Debug,
+    Default,
+    Deserialize,
+    Eq,
+    Hash,
+    new,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
 pub struct Attack {
     damage: usize,
     accuracy: isize,
+    /// Damage is rolled between `damage` and `damage + spread`
+    #[serde(default)]
+    #[new(default)]
+    spread: usize,
+    /// Hits with an accuracy roll this close to the top of the d100 are critical
+    #[serde(default)]
+    #[new(default)]
+    crit_chance: isize,
+    #[serde(default = "default_crit_multiplier")]
+    #[new(value = "CRIT_MULTIPLIER")]
+    crit_multiplier: usize,
+}
+
+pub const CRIT_MULTIPLIER: usize = 2;
+/// Saves from before crits
+fn default_crit_multiplier() -> usize {
+    CRIT_MULTIPLIER
+}
+
+/// How an attack landed
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AttackOutcome {
+    Miss,
+    Hit,
+    Crit,
 }
 
 impl Attack {
+    pub fn with_spread(self, spread: usize) -> Self {
+        Self { spread, ..self }
+    }
+
+    pub fn with_crit(self, crit_chance: isize, crit_multiplier: usize) -> Self {
+        Self {
+            crit_chance,
+            crit_multiplier,
+            ..self
+        }
+    }
+
     pub fn accuracy(&self) -> isize {
         self.accuracy
     }
@@ -128,77 +203,203 @@ impl Attack {
         self.accuracy += modifier
     }
 
-    pub fn attack(&self, target: &mut Vitality, acc_roll: isize) -> bool {
-        target.take_attack(self.damage, self.accuracy + acc_roll)
+    pub fn add_damage(&mut self, damage: usize) {
+        self.damage += damage
+    }
+
+    pub fn damage_range(&self) -> RangeInclusive<usize> {
+        self.damage..=self.damage + self.spread
+    }
+
+    /// `damage` is the rolled one, `multiplier` is the percentage of it the target takes.
+    /// Whatever gets through the multiplier is rounded up, so a hit never does less than 1.
+    pub fn attack(
+        &self,
+        target: &mut Vitality,
+        acc_roll: isize,
+        damage: usize,
+        multiplier: usize,
+    ) -> AttackOutcome {
+        let crit = self.crit_chance > 0 && acc_roll >= 100 - self.crit_chance;
+        let damage = if crit {
+            damage * self.crit_multiplier
+        } else {
+            damage
+        };
+        let damage = (damage * multiplier).div_ceil(100);
+        match target.take_attack(damage, self.accuracy + acc_roll) {
+            true if crit => AttackOutcome::Crit,
+            true => AttackOutcome::Hit,
+            false => AttackOutcome::Miss,
+        }
     }
 }
 
 impl RenderText for Attack {
     fn render_text(&self, localization: &Localization) -> String {
-        format!(
-            "{} {}, {}%",
-            localization.attack, self.damage, self.accuracy
-        )
+        let damage = if self.spread > 0 {
+            format!("{}-{}", self.damage, self.damage + self.spread)
+        } else {
+            self.damage.to_string()
+        };
+        format!("{} {}, {}%", localization.attack, damage, self.accuracy)
     }
 }
 
-#[derive(Clone, Copy, Component, Debug, Enum, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub enum BygonePart {
-    Core,
-    Sensor,
-    Gun,
-    LeftWing,
-    RightWing,
+/// Lasts for a number of turns, players and enemies can both have them
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    Display,
+    EnumIter,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
+pub enum StatusEffect {
+    /// Takes damage at the end of every turn, enemies take it on the part that moves their stage
+    Burning,
+    /// Sits out: players are not ready for the next turn, enemies don't attack
+    Stunned,
+    Shielded,
+    Suppressed,
 }
 
-impl RenderText for BygonePart {
-    fn render_text(&self, localization: &Localization) -> String {
+impl StatusEffect {
+    /// Added to the dodge while the effect lasts, for enemies to the dodge of every part
+    pub fn dodge(&self) -> isize {
+        match self {
+            StatusEffect::Shielded => 30,
+            _ => 0,
+        }
+    }
+
+    /// Added to the accuracy while the effect lasts
+    pub fn accuracy(&self) -> isize {
         match self {
-            BygonePart::Core => &localization.core.0,
-            BygonePart::Sensor => &localization.sensor.0,
-            BygonePart::Gun => &localization.gun.0,
-            BygonePart::LeftWing => &localization.left_wing.0,
-            BygonePart::RightWing => &localization.right_wing.0,
+            StatusEffect::Suppressed => -30,
+            _ => 0,
+        }
+    }
+
+    /// Dealt at the end of every turn
+    pub fn damage(&self) -> usize {
+        match self {
+            StatusEffect::Burning => 1,
+            _ => 0,
         }
-        .clone()
     }
 }
 
-#[derive(Clone, Copy, Component, Debug, Enum, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub enum Bygone03Stage {
-    Armored,
-    Exposed,
-    Burning,
-    Defeated,
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, new, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct TimedEffect {
+    pub effect: StatusEffect,
+    pub turns: usize,
 }
 
-impl Bygone03Stage {
-    pub fn next(self) -> Self {
-        match self {
-            Self::Armored => Self::Exposed,
-            Self::Exposed => Self::Burning,
-            Self::Burning => Self::Defeated,
-            Self::Defeated => Self::Defeated,
+/// Effects on a player or an enemy with the turns they have left
+#[derive(Clone, Component, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct StatusEffects(BTreeMap<StatusEffect, usize>);
+
+impl StatusEffects {
+    pub fn has(&self, effect: StatusEffect) -> bool {
+        self.0.contains_key(&effect)
+    }
+
+    /// Tells whether the effect is new, an effect that is already there only gets its turns renewed
+    pub fn apply(&mut self, effect: TimedEffect) -> bool {
+        match self.0.get_mut(&effect.effect) {
+            Some(turns) => {
+                *turns = (*turns).max(effect.turns);
+                false
+            }
+            None => {
+                self.0.insert(effect.effect, effect.turns);
+                true
+            }
         }
     }
 
-    pub fn terminal(&self) -> bool {
-        *self == Self::Defeated
+    /// Takes a turn off every effect and gives back the ones that wore off
+    pub fn tick(&mut self) -> Vec<StatusEffect> {
+        self.0
+            .values_mut()
+            .for_each(|turns| *turns = turns.saturating_sub(1));
+        let expired: Vec<_> = self
+            .0
+            .iter()
+            .filter(|(_, turns)| **turns == 0)
+            .map(|(effect, _)| *effect)
+            .collect();
+        self.0.retain(|_, turns| *turns > 0);
+        expired
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (StatusEffect, usize)> + '_ {
+        self.0.iter().map(|(effect, turns)| (*effect, *turns))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
     }
 }
 
-impl RenderText for Bygone03Stage {
-    fn render_text(&self, localization: &Localization) -> String {
-        match *self {
-            Self::Armored => &localization.core_armored.0,
-            Self::Exposed => &localization.core_exposed.0,
-            Self::Burning => &localization.core_burning.0,
-            Self::Defeated => &localization.core_destroyed.0,
-        }
-        .clone()
+/// Id of an enemy part, each enemy definition has its own set of them
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(transparent)]
+pub struct BygonePart(pub String);
+
+impl From<&str> for BygonePart {
+    fn from(id: &str) -> Self {
+        Self(id.to_owned())
+    }
+}
+
+impl Display for BygonePart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
     }
 }
 
+/// Id of the stage an enemy is in, the enemy definition says which one comes next
+#[derive(Clone, Component, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(transparent)]
+pub struct BygoneStage(pub String);
+
+impl From<&str> for BygoneStage {
+    fn from(id: &str) -> Self {
+        Self(id.to_owned())
+    }
+}
+
+/// Which enemy definition a bygone was spawned from
+#[derive(Clone, Component, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct EnemyKind(pub String);
+
+/// Place of an enemy in its encounter, the leading enemy is 0. Players pick their target by it.
+#[derive(
+    Clone,
+    Copy,
+    Component,
+    Debug,
+    Default,
+    Deserialize,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
+#[serde(transparent)]
+pub struct EnemySlot(pub usize);
+
 #[derive(Clone, Copy, Component, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Enemy;
 
@@ -211,9 +412,210 @@ pub struct Active;
 #[derive(Clone, Copy, Component, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Ready;
 
-#[derive(Clone, Component, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+/// How an enemy picks the player to attack
+#[derive(
+    Clone,
+    Copy,
+    Component,
+    Debug,
+    Default,
+    Deserialize,
+    Display,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
+pub enum TargetingPolicy {
+    #[default]
+    Random,
+    /// Finishes off whoever has the least health left
+    LowestHealth,
+    /// Goes after whoever dealt the most damage since the enemies last attacked
+    Threat,
+    /// Spreads its damage by going after whoever has the most health left
+    Spread,
+    /// Goes for medics first, anyone when there are none
+    Healers,
+}
+
+/// A move an enemy makes instead of its usual shot, announced an attack ahead
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum SpecialMove {
+    /// Shoots at every player at once
+    Barrage,
+    /// One shot with its damage multiplied, hitting the part before it comes calls it off
+    ChargedShot {
+        multiplier: usize,
+        interrupted_by: BygonePart,
+    },
+    /// The part takes no damage until the enemy attacks again
+    Shield(BygonePart),
+}
+
+impl SpecialMove {
+    /// The part the move shields or that calls it off
+    pub fn part(&self) -> Option<&BygonePart> {
+        match self {
+            SpecialMove::Barrage => None,
+            SpecialMove::ChargedShot { interrupted_by, .. } => Some(interrupted_by),
+            SpecialMove::Shield(part) => Some(part),
+        }
+    }
+}
+
+/// Where an enemy is with its special moves
+#[derive(Clone, Component, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SpecialMoves {
+    /// Counts the attacks, specials come every few of them
+    pub attacks: usize,
+    /// Announced, comes instead of the next attack
+    pub telegraph: Option<SpecialMove>,
+    pub shielded: Option<BygonePart>,
+}
+
+/// Damage the player dealt to the enemies since they last attacked
+#[derive(Clone, Copy, Component, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Threat(pub usize);
+
+/// Set by the defend ability, the player's dodge is raised until the enemies attack next
+#[derive(Clone, Copy, Component, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Defending;
+
+/// What a player can do instead of attacking, every ability takes the player's turn
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    EnumIter,
+    EnumString,
+    Eq,
+    Hash,
+    IntoStaticStr,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum Ability {
+    /// Patches up the most wounded player of the battle
+    Heal,
+    Defend,
+    /// An attack that is harder to dodge
+    FocusedShot,
+    /// Brings a knocked out player back into the battle
+    Revive,
+}
+
+impl Ability {
+    /// Turns that have to end before the ability can be used again
+    pub fn cooldown(&self) -> usize {
+        match self {
+            Ability::Heal => 2,
+            Ability::Defend => 0,
+            Ability::FocusedShot => 3,
+            Ability::Revive => 0,
+        }
+    }
+}
+
+/// Turns left until the abilities a player used are available again
+#[derive(Clone, Component, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Cooldowns(BTreeMap<Ability, usize>);
+
+impl Cooldowns {
+    pub fn available(&self, ability: Ability) -> bool {
+        !self.0.contains_key(&ability)
+    }
+
+    pub fn start(&mut self, ability: Ability) {
+        if ability.cooldown() > 0 {
+            self.0.insert(ability, ability.cooldown());
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.0.values_mut().for_each(|turns| *turns -= 1);
+        self.0.retain(|_, turns| *turns > 0);
+    }
+}
+
+#[derive(Clone, Component, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct PlayerName(pub String);
 
+/// Picked when joining a battle, players who join by attacking right away fight as fighters
+#[derive(
+    Clone,
+    Copy,
+    Component,
+    Debug,
+    Default,
+    Deserialize,
+    Display,
+    EnumIter,
+    EnumString,
+    Eq,
+    Hash,
+    IntoStaticStr,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum PlayerClass {
+    #[default]
+    Fighter,
+    /// Hard to hit, but fragile
+    Scout,
+    /// More health and damage, but clumsy
+    Heavy,
+    /// Heals more than the others
+    Medic,
+}
+
+impl PlayerClass {
+    pub fn vitality(&self) -> Vitality {
+        match self {
+            PlayerClass::Fighter => Vitality::new(6, 100),
+            PlayerClass::Scout => Vitality::new(5, 130),
+            PlayerClass::Heavy => Vitality::new(9, 90),
+            PlayerClass::Medic => Vitality::new(5, 110),
+        }
+    }
+
+    pub fn attack(&self) -> Attack {
+        match self {
+            PlayerClass::Fighter => Attack::new(1, 0).with_crit(10, CRIT_MULTIPLIER),
+            PlayerClass::Scout => Attack::new(1, 10).with_crit(20, CRIT_MULTIPLIER),
+            PlayerClass::Heavy => Attack::new(2, -20).with_spread(1),
+            PlayerClass::Medic => Attack::new(1, -10),
+        }
+    }
+
+    /// Health the heal ability restores
+    pub fn heal_amount(&self) -> usize {
+        match self {
+            PlayerClass::Medic => 4,
+            _ => 2,
+        }
+    }
+
+    /// Order of the actions in a strict turn, the highest goes first
+    pub fn initiative(&self) -> usize {
+        match self {
+            PlayerClass::Scout => 3,
+            PlayerClass::Fighter | PlayerClass::Medic => 2,
+            PlayerClass::Heavy => 1,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Component, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct UserIdComponent(pub Id<UserMarker>);
 
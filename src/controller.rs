@@ -1,7 +1,6 @@
 use std::error::Error;
 
 use crossbeam_channel::Sender;
-use thiserror::Error;
 use twilight_http::Client;
 
 use twilight_model::http::interaction::{
@@ -12,26 +11,26 @@ use twilight_model::{
     application::interaction::{
         message_component::MessageComponentInteractionData, Interaction, InteractionData,
     },
-    guild::PartialMember,
+    guild::{PartialMember, Permissions},
     id::{marker::MessageMarker, Id},
 };
 
 use crate::discord_renderer::{
-    RenderedGame, RenderedGamePure, RenderedMessage, RenderedMessagePure,
+    RenderedGame, RenderedGamePure, RenderedMessage, RenderedMessagePure, ABILITY_MENU_ID,
+    CLASS_MENU_ID, PART_BUTTON_SEPARATOR,
 };
-use crate::game_helpers::{Difficulty, InteractionIds};
+use crate::game_helpers::{ArenaId, Difficulty, GameMode, InteractionIds, TurnMode};
 use crate::{
-    command_parser::BYGONE_PARTS_FROM_EMOJI_NAME,
-    components::PlayerName,
-    events::{GameStartEvent, InputEvent, PlayerAttackEvent},
-    localization::Localization,
+    components::{Ability, BygonePart, EnemySlot, PlayerClass, PlayerName},
+    events::{
+        ConfigRequestEvent, EndRequestEvent, GameStartEvent, InputEvent, JoinRequestEvent,
+        LeaderboardRequestEvent, PlayerAbilityEvent, PlayerAttackEvent,
+    },
+    guild_config::GuildConfigUpdate,
+    localization::{Language, LanguageChoice, Localizations},
     logging::format_time,
 };
 
-#[derive(Clone, Copy, Debug, Error)]
-#[error("need two messages to create a game")]
-pub struct GameCreateError;
-
 fn make_message_interaction_response(msg: RenderedMessagePure) -> InteractionResponse {
     InteractionResponse {
         kind: InteractionResponseType::ChannelMessageWithSource,
@@ -51,11 +50,13 @@ fn make_message_interaction_response(msg: RenderedMessagePure) -> InteractionRes
 }
 
 pub fn process_interaction(interaction: Interaction) -> Option<InputEvent> {
-    let emoji_name = match interaction.data {
+    let arena = interaction_arena(&interaction);
+    let (custom_id, values) = match interaction.data {
         Some(InteractionData::MessageComponent(MessageComponentInteractionData {
             custom_id,
+            values,
             ..
-        })) => custom_id,
+        })) => (custom_id, values),
         Some(_) => {
             println!(
                 "{} - controller - ERROR processing interaction with id {}: interaction data is not MessageComponent",
@@ -96,8 +97,8 @@ pub fn process_interaction(interaction: Interaction) -> Option<InputEvent> {
             return None;
         }
     };
-    let guild_id = match interaction.guild_id {
-        Some(guild_id) => guild_id,
+    let arena = match arena {
+        Some(arena) => arena,
         None => {
             println!(
                 "{} - controller - ERROR processing interaction with id {}: empty guild id",
@@ -108,35 +109,117 @@ pub fn process_interaction(interaction: Interaction) -> Option<InputEvent> {
         }
     };
 
-    if let Some(bygone_part) = BYGONE_PARTS_FROM_EMOJI_NAME.get(&emoji_name) {
-        let user_name = PlayerName(
-            match &user_nick {
-                Some(nick) => nick,
-                None => &user.name,
+    let user_name = PlayerName(
+        match &user_nick {
+            Some(nick) => nick,
+            None => &user.name,
+        }
+        .to_string(),
+    );
+
+    if custom_id == ABILITY_MENU_ID {
+        let (ability, target) = match values.first().and_then(|value| parse_ability_option(value)) {
+            Some(choice) => choice,
+            None => {
+                println!(
+                    "{} - controller - ERROR processing interaction with id {}: unknown ability {:?}",
+                    format_time(),
+                    interaction.id,
+                    values
+                );
+                return None;
             }
-            .to_string(),
-        );
+        };
+        return Some(InputEvent::PlayerAbility(PlayerAbilityEvent::new(
+            user.id, user_name, arena, ability, target,
+        )));
+    }
 
-        return Some(InputEvent::PlayerAttack(PlayerAttackEvent::new(
-            user.id,
-            user_name,
-            guild_id,
-            *bygone_part,
+    if custom_id == CLASS_MENU_ID {
+        let class = match values
+            .first()
+            .and_then(|value| value.parse::<PlayerClass>().ok())
+        {
+            Some(class) => class,
+            None => {
+                println!(
+                    "{} - controller - ERROR processing interaction with id {}: unknown class {:?}",
+                    format_time(),
+                    interaction.id,
+                    values
+                );
+                return None;
+            }
+        };
+        return Some(InputEvent::PlayerJoin(JoinRequestEvent::new(
+            user.id, user_name, arena, class,
         )));
-    } else {
-        println!(
-            "{} - controller - ERROR processing interaction with id {}: unknown bygone part emoji",
-            format_time(),
-            interaction.id
-        );
     }
-    return None;
+
+    // The game knows the parts of its enemies, button ids are checked there
+    let (enemy, part) = parse_part_button(&custom_id);
+    Some(InputEvent::PlayerAttack(PlayerAttackEvent::new(
+        user.id, user_name, arena, enemy, part,
+    )))
 }
 
+/// Ability options are the ability id, the focused shot adds the id of the part button it is aimed at
+fn parse_ability_option(value: &str) -> Option<(Ability, Option<(EnemySlot, BygonePart)>)> {
+    match value.split_once(PART_BUTTON_SEPARATOR) {
+        Some((ability, target)) => Some((ability.parse().ok()?, Some(parse_part_button(target)))),
+        None => Some((value.parse().ok()?, None)),
+    }
+}
+
+/// Part buttons are named `<enemy slot>:<part id>`, the ones from before escorts only had the part
+fn parse_part_button(custom_id: &str) -> (EnemySlot, BygonePart) {
+    match custom_id.split_once(PART_BUTTON_SEPARATOR) {
+        Some((slot, part)) => match slot.parse() {
+            Ok(slot) => (EnemySlot(slot), BygonePart::from(part)),
+            Err(_) => (EnemySlot::default(), BygonePart::from(custom_id)),
+        },
+        None => (EnemySlot::default(), BygonePart::from(custom_id)),
+    }
+}
+
+/// The guild and channel an interaction came from, the game decides how much of it keys a battle
+fn interaction_arena(interaction: &Interaction) -> Option<ArenaId> {
+    interaction.guild_id.map(|guild_id| {
+        ArenaId::new(
+            guild_id,
+            interaction.channel.as_ref().map(|channel| channel.id),
+        )
+    })
+}
+
+/// Discord fills in the member's permissions in the channel of the interaction
+fn can_manage_guild(interaction: &Interaction) -> bool {
+    interaction
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .is_some_and(|permissions| permissions.contains(Permissions::MANAGE_GUILD))
+}
+
+/// The language picked in the command along with the locales Discord sent, `listen` picks from them
+fn interaction_language(interaction: &Interaction, explicit: Option<Language>) -> LanguageChoice {
+    LanguageChoice::new(
+        explicit,
+        interaction.guild_locale.as_deref(),
+        interaction.locale.as_deref(),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn start_game(
     sender: &Sender<InputEvent>,
-    localization: Localization,
-    difficulty: Difficulty,
+    localizations: &Localizations,
+    language: Option<Language>,
+    difficulty: Option<Difficulty>,
+    enemy: String,
+    class: PlayerClass,
+    mode: GameMode,
+    turns: TurnMode,
     interaction: &Interaction,
 ) {
     if let (
@@ -145,9 +228,11 @@ pub fn start_game(
             nick: ref user_nick,
             ..
         }),
-        Some(guild_id),
-    ) = (&interaction.member, interaction.guild_id)
+        Some(arena),
+    ) = (&interaction.member, interaction_arena(interaction))
     {
+        let language = interaction_language(interaction, language);
+        let localization = localizations.get(language.resolve(None)).clone();
         let initial_player_name = PlayerName(
             match &user_nick {
                 Some(nick) => nick,
@@ -155,14 +240,19 @@ pub fn start_game(
             }
             .to_string(),
         );
-        if let Err(err) = sender.send(InputEvent::GameStart(GameStartEvent::new(
+        if let Err(err) = sender.send(InputEvent::GameStart(Box::new(GameStartEvent::new(
             user.id,
             initial_player_name,
             difficulty,
-            guild_id,
+            enemy,
+            class,
+            mode,
+            turns,
+            arena,
             interaction.id,
+            language,
             localization,
-        ))) {
+        )))) {
             println!(
                 "{} - controller - FAILED to send game start event: {}",
                 format_time(),
@@ -172,6 +262,74 @@ pub fn start_game(
     }
 }
 
+pub fn request_leaderboard(
+    sender: &Sender<InputEvent>,
+    localizations: &Localizations,
+    language: Option<Language>,
+    interaction: &Interaction,
+) {
+    if let Some(arena) = interaction_arena(interaction) {
+        let language = interaction_language(interaction, language);
+        let localization = localizations.get(language.resolve(None)).clone();
+        if let Err(err) = sender.send(InputEvent::Leaderboard(Box::new(
+            LeaderboardRequestEvent::new(arena, interaction.id, language, localization),
+        ))) {
+            println!(
+                "{} - controller - FAILED to send leaderboard request event: {}",
+                format_time(),
+                err
+            );
+        }
+    }
+}
+
+/// The language option of `/config` sets the guild's default, it doesn't pick the language of the answer
+pub fn configure_guild(
+    sender: &Sender<InputEvent>,
+    update: GuildConfigUpdate,
+    interaction: &Interaction,
+) {
+    if let Some(arena) = interaction_arena(interaction) {
+        if let Err(err) = sender.send(InputEvent::Config(Box::new(ConfigRequestEvent::new(
+            arena,
+            interaction.id,
+            interaction_language(interaction, None),
+            update,
+            can_manage_guild(interaction),
+        )))) {
+            println!(
+                "{} - controller - FAILED to send config request event: {}",
+                format_time(),
+                err
+            );
+        }
+    }
+}
+
+pub fn end_game(sender: &Sender<InputEvent>, interaction: &Interaction) {
+    if let (
+        Some(PartialMember {
+            user: Some(ref user),
+            ..
+        }),
+        Some(arena),
+    ) = (&interaction.member, interaction_arena(interaction))
+    {
+        if let Err(err) = sender.send(InputEvent::GameEnd(Box::new(EndRequestEvent::new(
+            user.id,
+            arena,
+            interaction.id,
+            interaction_language(interaction, None),
+        )))) {
+            println!(
+                "{} - controller - FAILED to send end request event: {}",
+                format_time(),
+                err
+            );
+        }
+    }
+}
+
 pub async fn create_message(
     http: &Client,
     oneshot: RenderedMessagePure,
@@ -197,7 +355,7 @@ pub async fn create_game_message(
         format_time(),
         interaction.id
     );
-    create_message(http, rendered_game.upper_message, &interaction).await?;
+    create_message(http, rendered_game.upper_message, interaction).await?;
     let followup_id = http
         .interaction(interaction.app_id)
         .create_followup(&interaction.token)
@@ -1,19 +1,32 @@
-use bevy_turborand::GlobalRng;
+use bevy_turborand::RngComponent;
+
+use crate::components::Attack;
 
 pub trait Dice {
     fn d100(&mut self) -> isize;
+    /// Attacks without a spread don't roll, so their battles play out the same as before spreads
+    fn damage(&mut self, attack: &Attack) -> usize;
 }
 
-impl Dice for GlobalRng {
+impl Dice for RngComponent {
     fn d100(&mut self) -> isize {
         self.isize(0..100)
     }
+
+    fn damage(&mut self, attack: &Attack) -> usize {
+        let range = attack.damage_range();
+        if range.start() == range.end() {
+            *range.start()
+        } else {
+            self.usize(range)
+        }
+    }
 }
 
-pub fn choose_mut<'a, T>(rng: &mut GlobalRng, items: &'a mut [T]) -> Option<&'a mut T> {
-    if items.len() == 0 {
+pub fn choose_index(rng: &mut RngComponent, len: usize) -> Option<usize> {
+    if len == 0 {
         None
     } else {
-        Some(&mut items[rng.usize(0..items.len())])
+        Some(rng.usize(0..len))
     }
 }
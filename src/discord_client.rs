@@ -1,39 +1,464 @@
 use std::{
     collections::HashMap,
     error::Error,
+    path::PathBuf,
     sync::{Arc, Mutex},
     time::Duration, future::IntoFuture,
 };
 
 use crossbeam_channel::{unbounded, Receiver, Sender};
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
 
 use twilight_gateway::{Intents, Shard, ShardId, Event};
 use twilight_http::Client as HttpClient;
 use twilight_model::{
     application::{
-        command::{CommandOption, CommandOptionChoice, CommandOptionType, CommandOptionChoiceValue},
+        command::{CommandOption, CommandOptionChoice, CommandOptionType, CommandOptionChoiceValue, CommandOptionValue},
         interaction::{InteractionData, InteractionType},
     },
+    guild::Permissions,
     http::interaction::{InteractionResponse, InteractionResponseType},
+    id::{
+        marker::{InteractionMarker, MessageMarker},
+        Id,
+    },
 };
 
-use tracing;
-
 use crate::{
     command_parser::{
-        is_game_starting, BATTLE_COMMAND, DIFFICULTY_COMMAND_OPTION, LANGUAGE_COMMAND_OPTION,
+        is_config_requested, is_game_ending, is_game_starting, is_leaderboard_requested,
+        ALLOW_CHANNEL_COMMAND_OPTION, BATTLE_COMMAND, CLASS_COMMAND_OPTION, CONFIG_COMMAND,
+        COOLDOWN_COMMAND_OPTION, DIFFICULTY_COMMAND_OPTION, DISALLOW_CHANNEL_COMMAND_OPTION,
+        END_COMMAND, ENDERS_COMMAND_OPTION, ENEMY_COMMAND_OPTION, LANGUAGE_COMMAND_OPTION,
+        LEADERBOARD_COMMAND, MODE_COMMAND_OPTION, TURNS_COMMAND_OPTION,
     },
+    components::PlayerClass,
     controller::{
-        create_game_message, create_message, process_interaction, start_game, update_game_message,
-        update_game_message_pure,
+        configure_guild, create_game_message, create_message, end_game, process_interaction,
+        request_leaderboard, start_game, update_game_message, update_game_message_pure,
     },
     discord_renderer::{DiscordRenderer, RenderedGame, RenderedMessage},
+    enemies::EnemyDefinitions,
     events::{GameRenderEvent, GameRenderPayload, InputEvent},
-    game_helpers::{Difficulty, InteractionIds},
+    game_helpers::{ArenaId, Difficulty, GameMode, InteractionIds, TurnMode},
+    guild_config::GameEnders,
+    io::{read_json, write_json},
     localization::{Language, Localizations},
     logging::format_time,
+    transport::{ChatTransport, TransportChannels},
 };
 
+/// Game message that can be picked up again after a restart
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct GameMessageSnapshot {
+    #[serde(alias = "guild_id")]
+    arena: ArenaId,
+    interaction: InteractionIds,
+    followup_id: Id<MessageMarker>,
+}
+
+type GameMessages = HashMap<ArenaId, (Id<InteractionMarker>, Id<MessageMarker>, RenderedGame)>;
+
+fn save_messages(
+    path: &Option<PathBuf>,
+    messages: &GameMessages,
+    cached_interactions: &Mutex<HashMap<Id<InteractionMarker>, InteractionIds>>,
+) {
+    let path = match path {
+        Some(path) => path,
+        None => return,
+    };
+    let snapshots: Vec<_> = match cached_interactions.lock() {
+        Ok(cached_interactions_lock) => messages
+            .iter()
+            .filter_map(|(arena, (interaction_id, followup_id, _))| {
+                cached_interactions_lock
+                    .get(interaction_id)
+                    .map(|interaction| GameMessageSnapshot {
+                        arena: *arena,
+                        interaction: interaction.clone(),
+                        followup_id: *followup_id,
+                    })
+            })
+            .collect(),
+        Err(_) => return,
+    };
+    if let Err(err) = write_json(&snapshots, path) {
+        println!(
+            "{} - discord_client - FAILED to save game messages: {}",
+            format_time(),
+            err
+        );
+    }
+}
+
+/// One choice per known enemy, named the way its localizations call it
+fn make_enemy_option(enemies: &EnemyDefinitions) -> CommandOption {
+    CommandOption {
+        autocomplete: Some(false),
+        channel_types: None,
+        choices: Some(
+            enemies
+                .0
+                .iter()
+                .map(|(id, definition)| CommandOptionChoice {
+                    name: definition.localization(Language::En).name.0.clone(),
+                    name_localizations: Some(
+                        definition
+                            .localizations
+                            .iter()
+                            .filter(|(language, _)| **language != Language::En)
+                            .map(|(language, localization)| {
+                                (language.to_string(), localization.name.0.clone())
+                            })
+                            .collect(),
+                    ),
+                    value: CommandOptionChoiceValue::String(id.clone()),
+                })
+                .collect(),
+        ),
+        description: "Who to fight".to_owned(),
+        description_localizations: Some(HashMap::from([(
+            Language::Ru.to_string(),
+            "С кем сразиться".to_owned(),
+        )])),
+        kind: CommandOptionType::String,
+        max_length: None,
+        min_length: None,
+        min_value: None,
+        max_value: None,
+        name: ENEMY_COMMAND_OPTION.to_owned(),
+        name_localizations: Some(HashMap::from([(
+            Language::Ru.to_string(),
+            "противник".to_owned(),
+        )])),
+        options: None,
+        required: Some(false),
+    }
+}
+
+/// One choice per class, the names come from the localizations
+fn make_class_option() -> CommandOption {
+    let localizations = Localizations::new();
+    let classes = &localizations.get(Language::En).classes;
+    let classes_ru = &localizations.get(Language::Ru).classes;
+    CommandOption {
+        autocomplete: Some(false),
+        channel_types: None,
+        choices: Some(
+            PlayerClass::iter()
+                .map(|class| CommandOptionChoice {
+                    name: classes.name(class),
+                    name_localizations: Some(HashMap::from([(
+                        Language::Ru.to_string(),
+                        classes_ru.name(class),
+                    )])),
+                    value: CommandOptionChoiceValue::String(class.to_string()),
+                })
+                .collect(),
+        ),
+        description: "Who to fight as".to_owned(),
+        description_localizations: Some(HashMap::from([(
+            Language::Ru.to_string(),
+            "Кем сражаться".to_owned(),
+        )])),
+        kind: CommandOptionType::String,
+        max_length: None,
+        min_length: None,
+        min_value: None,
+        max_value: None,
+        name: CLASS_COMMAND_OPTION.to_owned(),
+        name_localizations: Some(HashMap::from([(
+            Language::Ru.to_string(),
+            "класс".to_owned(),
+        )])),
+        options: None,
+        required: Some(false),
+    }
+}
+
+fn make_mode_option() -> CommandOption {
+    CommandOption {
+        autocomplete: Some(false),
+        channel_types: None,
+        choices: Some(vec![
+            CommandOptionChoice {
+                name: "Classic - One _03, one battle".to_owned(),
+                name_localizations: Some(HashMap::from([(
+                    Language::Ru.to_string(),
+                    "Классика - Один _03, одна битва".to_owned(),
+                )])),
+                value: CommandOptionChoiceValue::String(GameMode::Classic.to_string()),
+            },
+            CommandOptionChoice {
+                name: "Survival - They keep coming, each one stronger".to_owned(),
+                name_localizations: Some(HashMap::from([(
+                    Language::Ru.to_string(),
+                    "Выживание - Они всё идут, и каждый сильнее".to_owned(),
+                )])),
+                value: CommandOptionChoiceValue::String(GameMode::Survival.to_string()),
+            },
+        ]),
+        description: "Battle mode".to_owned(),
+        description_localizations: Some(HashMap::from([(
+            Language::Ru.to_string(),
+            "Режим битвы".to_owned(),
+        )])),
+        kind: CommandOptionType::String,
+        max_length: None,
+        min_length: None,
+        min_value: None,
+        max_value: None,
+        name: MODE_COMMAND_OPTION.to_owned(),
+        name_localizations: Some(HashMap::from([(
+            Language::Ru.to_string(),
+            "режим".to_owned(),
+        )])),
+        options: None,
+        required: Some(false),
+    }
+}
+
+fn make_turns_option() -> CommandOption {
+    CommandOption {
+        autocomplete: Some(false),
+        channel_types: None,
+        choices: Some(vec![
+            CommandOptionChoice {
+                name: "Real time - Act whenever you like, _03 won't wait".to_owned(),
+                name_localizations: Some(HashMap::from([(
+                    Language::Ru.to_string(),
+                    "Реальное время - Действуйте когда угодно, _03 ждать не будет".to_owned(),
+                )])),
+                value: CommandOptionChoiceValue::String(TurnMode::RealTime.to_string()),
+            },
+            CommandOptionChoice {
+                name: "Strict - The turn waits until everyone has acted".to_owned(),
+                name_localizations: Some(HashMap::from([(
+                    Language::Ru.to_string(),
+                    "Пошагово - Ход ждёт, пока все не походят".to_owned(),
+                )])),
+                value: CommandOptionChoiceValue::String(TurnMode::Strict.to_string()),
+            },
+        ]),
+        description: "How turns go".to_owned(),
+        description_localizations: Some(HashMap::from([(
+            Language::Ru.to_string(),
+            "Как идут ходы".to_owned(),
+        )])),
+        kind: CommandOptionType::String,
+        max_length: None,
+        min_length: None,
+        min_value: None,
+        max_value: None,
+        name: TURNS_COMMAND_OPTION.to_owned(),
+        name_localizations: Some(HashMap::from([(
+            Language::Ru.to_string(),
+            "ходы".to_owned(),
+        )])),
+        options: None,
+        required: Some(false),
+    }
+}
+
+fn make_difficulty_option() -> CommandOption {
+    CommandOption {
+        autocomplete: Some(false),
+        channel_types: None,
+        choices: Some(vec![
+            CommandOptionChoice {
+                name: "Easy - Just like in the game".to_owned(),
+                name_localizations: Some(HashMap::from([(
+                    Language::Ru.to_string(),
+                    "Легко - Совсем как в игре".to_owned(),
+                )])),
+                value: CommandOptionChoiceValue::String(Difficulty::Easy.to_string()),
+            },
+            CommandOptionChoice {
+                name: "Medium - Take a buddy with you".to_owned(),
+                name_localizations: Some(HashMap::from([(
+                    Language::Ru.to_string(),
+                    "Средне - Позови друга".to_owned(),
+                )])),
+                value: CommandOptionChoiceValue::String(Difficulty::Medium.to_string()),
+            },
+            CommandOptionChoice {
+                name: "Hard - You shall not pass!".to_owned(),
+                name_localizations: Some(HashMap::from([(
+                    Language::Ru.to_string(),
+                    "Сложно - Ты не пройдёшь!".to_owned(),
+                )])),
+                value: CommandOptionChoiceValue::String(Difficulty::Hard.to_string()),
+            },
+            CommandOptionChoice {
+                name: "Real bullets - Forgive me, Mister Pikes...".to_owned(),
+                name_localizations: Some(HashMap::from([(
+                    Language::Ru.to_string(),
+                    "Боевые патроны - Простите меня, мистер Пайкс...".to_owned(),
+                )])),
+                value: CommandOptionChoiceValue::String(Difficulty::RealBullets.to_string()),
+            },
+        ]),
+        description: "Battle difficulty".to_owned(),
+        description_localizations: Some(HashMap::from([(
+            Language::Ru.to_string(),
+            "Сложность битвы".to_owned(),
+        )])),
+        kind: CommandOptionType::String,
+        max_length: None,
+        min_length: None,
+        min_value: None,
+        max_value: None,
+        name: DIFFICULTY_COMMAND_OPTION.to_owned(),
+        name_localizations: Some(HashMap::from([(
+            Language::Ru.to_string(),
+            "сложность".to_owned(),
+        )])),
+        options: None,
+        required: Some(false),
+    }
+}
+
+fn make_language_option() -> CommandOption {
+    CommandOption {
+        autocomplete: Some(false),
+        channel_types: None,
+        choices: Some(vec![
+            CommandOptionChoice {
+                name: "English".to_owned(),
+                name_localizations: Some(HashMap::from([(
+                    Language::Ru.to_string(),
+                    "Английский".to_owned(),
+                )])),
+                value: CommandOptionChoiceValue::String(Language::En.to_string()),
+            },
+            CommandOptionChoice {
+                name: "Russian".to_owned(),
+                name_localizations: Some(HashMap::from([(
+                    Language::Ru.to_string(),
+                    "Русский".to_owned(),
+                )])),
+                value: CommandOptionChoiceValue::String(Language::Ru.to_string()),
+            },
+        ]),
+        description: "Interface language".to_owned(),
+        description_localizations: Some(HashMap::from([(
+            Language::Ru.to_string(),
+            "Язык интерфейса".to_owned(),
+        )])),
+        kind: CommandOptionType::String,
+        max_length: None,
+        min_length: None,
+        min_value: None,
+        max_value: None,
+        name: LANGUAGE_COMMAND_OPTION.to_owned(),
+        name_localizations: Some(HashMap::from([(
+            Language::Ru.to_string(),
+            "язык".to_owned(),
+        )])),
+        options: None,
+        required: Some(false),
+    }
+}
+
+fn make_cooldown_option() -> CommandOption {
+    CommandOption {
+        autocomplete: Some(false),
+        channel_types: None,
+        choices: None,
+        description: "Seconds from the start of a battle until the next one".to_owned(),
+        description_localizations: Some(HashMap::from([(
+            Language::Ru.to_string(),
+            "Секунды от начала битвы до следующей".to_owned(),
+        )])),
+        kind: CommandOptionType::Integer,
+        max_length: None,
+        min_length: None,
+        min_value: Some(CommandOptionValue::Integer(0)),
+        max_value: None,
+        name: COOLDOWN_COMMAND_OPTION.to_owned(),
+        name_localizations: Some(HashMap::from([(
+            Language::Ru.to_string(),
+            "перерыв".to_owned(),
+        )])),
+        options: None,
+        required: Some(false),
+    }
+}
+
+fn make_channel_option(
+    name: &str,
+    name_ru: &str,
+    description: &str,
+    description_ru: &str,
+) -> CommandOption {
+    CommandOption {
+        autocomplete: None,
+        channel_types: None,
+        choices: None,
+        description: description.to_owned(),
+        description_localizations: Some(HashMap::from([(
+            Language::Ru.to_string(),
+            description_ru.to_owned(),
+        )])),
+        kind: CommandOptionType::Channel,
+        max_length: None,
+        min_length: None,
+        min_value: None,
+        max_value: None,
+        name: name.to_owned(),
+        name_localizations: Some(HashMap::from([(
+            Language::Ru.to_string(),
+            name_ru.to_owned(),
+        )])),
+        options: None,
+        required: Some(false),
+    }
+}
+
+fn make_enders_option() -> CommandOption {
+    CommandOption {
+        autocomplete: Some(false),
+        channel_types: None,
+        choices: Some(vec![
+            CommandOptionChoice {
+                name: "Starter - Only the one who started the battle".to_owned(),
+                name_localizations: Some(HashMap::from([(
+                    Language::Ru.to_string(),
+                    "Зачинщик - Только тот, кто начал битву".to_owned(),
+                )])),
+                value: CommandOptionChoiceValue::String(GameEnders::Starter.to_string()),
+            },
+            CommandOptionChoice {
+                name: "Anyone - Whoever had enough".to_owned(),
+                name_localizations: Some(HashMap::from([(
+                    Language::Ru.to_string(),
+                    "Кто угодно - Любой, кому надоело".to_owned(),
+                )])),
+                value: CommandOptionChoiceValue::String(GameEnders::Anyone.to_string()),
+            },
+        ]),
+        description: "Who can end a battle early".to_owned(),
+        description_localizations: Some(HashMap::from([(
+            Language::Ru.to_string(),
+            "Кто может закончить битву раньше".to_owned(),
+        )])),
+        kind: CommandOptionType::String,
+        max_length: None,
+        min_length: None,
+        min_value: None,
+        max_value: None,
+        name: ENDERS_COMMAND_OPTION.to_owned(),
+        name_localizations: Some(HashMap::from([(
+            Language::Ru.to_string(),
+            "кто_заканчивает".to_owned(),
+        )])),
+        options: None,
+        required: Some(false),
+    }
+}
+
 fn merge_with_cached(rendered_game: RenderedGame, cached: &mut RenderedGame) {
     match rendered_game.upper_message {
         RenderedMessage::Message(message) => {
@@ -58,26 +483,30 @@ fn merge_with_cached(rendered_game: RenderedGame, cached: &mut RenderedGame) {
 pub struct DiscordClient {
     http_write: Arc<HttpClient>,
     http_read: Arc<HttpClient>,
+    shard: Shard,
+    messages_path: Option<PathBuf>,
 }
 
 impl DiscordClient {
-    pub fn new(token: String) -> (Self, Shard) {
+    pub fn new(token: String, messages_path: Option<PathBuf>) -> Self {
         let shard = Shard::new(ShardId::ONE, token.to_owned(), Intents::empty());
 
         let http_read = Arc::new(HttpClient::new(token.to_owned()));
         let http_write = Arc::new(HttpClient::new(token));
 
-        (
-            Self {
-                http_write,
-                http_read,
-            },
-            shard
-        )
+        Self {
+            http_write,
+            http_read,
+            shard,
+            messages_path,
+        }
     }
 
-    pub fn register_commands(&self) {
-        async fn inner(http: Arc<HttpClient>) -> Result<(), Box<dyn Error + Sync + Send>> {
+    pub fn register_commands(&self, enemies: &EnemyDefinitions) {
+        async fn inner(
+            http: Arc<HttpClient>,
+            enemy_option: CommandOption,
+        ) -> Result<(), Box<dyn Error + Sync + Send>> {
             let app_id = http
                 .current_user_application()
                 .await?
@@ -97,102 +526,60 @@ impl DiscordClient {
                     "Сразиться с _03".to_owned(),
                 )]))?
                 .command_options(&[
-                    CommandOption {
-                        autocomplete: Some(false),
-                        channel_types: None,
-                        choices: Some(vec![
-                            CommandOptionChoice {
-                                name: "Easy - Just like in the game".to_owned(),
-                                name_localizations: Some(HashMap::from([(
-                                    Language::Ru.to_string(),
-                                    "Легко - Совсем как в игре".to_owned(),
-                                )])),
-                                value: CommandOptionChoiceValue::String(Difficulty::Easy.to_string()),
-                            },
-                            CommandOptionChoice {
-                                name: "Medium - Take a buddy with you".to_owned(),
-                                name_localizations: Some(HashMap::from([(
-                                    Language::Ru.to_string(),
-                                    "Средне - Позови друга".to_owned(),
-                                )])),
-                                value: CommandOptionChoiceValue::String(Difficulty::Medium.to_string()),
-                            },
-                            CommandOptionChoice {
-                                name: "Hard - You shall not pass!".to_owned(),
-                                name_localizations: Some(HashMap::from([(
-                                    Language::Ru.to_string(),
-                                    "Сложно - Ты не пройдёшь!".to_owned(),
-                                )])),
-                                value: CommandOptionChoiceValue::String(Difficulty::Hard.to_string()),
-                            },
-                            CommandOptionChoice {
-                                name: "Real bullets - Forgive me, Mister Pikes...".to_owned(),
-                                name_localizations: Some(HashMap::from([(
-                                    Language::Ru.to_string(),
-                                    "Боевые патроны - Простите меня, мистер Пайкс...".to_owned(),
-                                )])),
-                                value: CommandOptionChoiceValue::String(Difficulty::RealBullets.to_string()),
-                            },
-                        ]),
-                        description: "Battle difficulty".to_owned(),
-                        description_localizations: Some(HashMap::from([(
-                            Language::Ru.to_string(),
-                            "Сложность битвы".to_owned(),
-                        )])),
-                        kind: CommandOptionType::String,
-                        max_length: None,
-                        min_length: None,
-                        min_value: None,
-                        max_value: None,
-                        name: DIFFICULTY_COMMAND_OPTION.to_owned(),
-                        name_localizations: Some(HashMap::from([(
-                            Language::Ru.to_string(),
-                            "сложность".to_owned(),
-                        )])),
-                        options: None,
-                        required: Some(false),
-                    },
-                    CommandOption {
-                        autocomplete: Some(false),
-                        channel_types: None,
-                        choices: Some(vec![
-                            CommandOptionChoice {
-                                name: "English".to_owned(),
-                                name_localizations: Some(HashMap::from([(
-                                    Language::Ru.to_string(),
-                                    "Английский".to_owned(),
-                                )])),
-                                value: CommandOptionChoiceValue::String(Language::En.to_string()),
-                            },
-                            CommandOptionChoice {
-                                name: "Russian".to_owned(),
-                                name_localizations: Some(HashMap::from([(
-                                    Language::Ru.to_string(),
-                                    "Русский".to_owned(),
-                                )])),
-                                value: CommandOptionChoiceValue::String(Language::Ru.to_string()),
-                            },
-                        ]),
-                        description: "Interface language".to_owned(),
-                        description_localizations: Some(HashMap::from([(
-                            Language::Ru.to_string(),
-                            "Язык интерфейса".to_owned(),
-                        )])),
-                        kind: CommandOptionType::String,
-                        max_length: None,
-                        min_length: None,
-                        min_value: None,
-                        max_value: None,
-                        name: LANGUAGE_COMMAND_OPTION.to_owned(),
-                        name_localizations: Some(HashMap::from([(
-                            Language::Ru.to_string(),
-                            "язык".to_owned(),
-                        )])),
-                        options: None,
-                        required: Some(false),
-                    },
+                    make_difficulty_option(),
+                    make_language_option(),
+                    enemy_option,
+                    make_class_option(),
+                    make_mode_option(),
+                    make_turns_option(),
                 ])?
                 .await?;
+            http.interaction(app_id)
+                .create_global_command()
+                .chat_input(LEADERBOARD_COMMAND, "Show the best _03 fighters")?
+                .description_localizations(&HashMap::from([(
+                    Language::Ru.to_string(),
+                    "Показать лучших бойцов с _03".to_owned(),
+                )]))?
+                .command_options(&[make_language_option()])?
+                .await?;
+            http.interaction(app_id)
+                .create_global_command()
+                .chat_input(CONFIG_COMMAND, "Change the battle settings of the server")?
+                .description_localizations(&HashMap::from([(
+                    Language::Ru.to_string(),
+                    "Изменить настройки битв на сервере".to_owned(),
+                )]))?
+                .default_member_permissions(Permissions::MANAGE_GUILD)
+                .dm_permission(false)
+                .command_options(&[
+                    make_language_option(),
+                    make_difficulty_option(),
+                    make_cooldown_option(),
+                    make_channel_option(
+                        ALLOW_CHANNEL_COMMAND_OPTION,
+                        "разрешить_канал",
+                        "Let battles start in this channel, only battle channels are allowed once there are some",
+                        "Разрешить битвы в этом канале, когда такие каналы есть, битвы идут только в них",
+                    ),
+                    make_channel_option(
+                        DISALLOW_CHANNEL_COMMAND_OPTION,
+                        "запретить_канал",
+                        "Take the channel off the battle channels",
+                        "Убрать канал из каналов для битв",
+                    ),
+                    make_enders_option(),
+                ])?
+                .await?;
+            http.interaction(app_id)
+                .create_global_command()
+                .chat_input(END_COMMAND, "Call off the battle in this channel")?
+                .description_localizations(&HashMap::from([(
+                    Language::Ru.to_string(),
+                    "Отменить битву в этом канале".to_owned(),
+                )]))?
+                .dm_permission(false)
+                .await?;
             println!(
                 "{} - discord_client - Commands register success",
                 format_time()
@@ -201,17 +588,16 @@ impl DiscordClient {
         }
 
         let http = Arc::clone(&self.http_write);
+        let enemy_option = make_enemy_option(enemies);
         tokio::spawn(async move {
-            inner(http).await.unwrap();
+            inner(http, enemy_option).await.unwrap();
         });
     }
 
-    pub async fn listen_discord(
-        &self,
+    fn listen_discord(
+        http: Arc<HttpClient>,
         mut shard: Shard
-    ) -> Result<(Receiver<InputEvent>, Receiver<InteractionIds>), Box<dyn Error + Send + Sync>>
-    {
-        let http = Arc::clone(&self.http_read);
+    ) -> (Receiver<InputEvent>, Receiver<InteractionIds>) {
         let (input_sender, input_receiver) = unbounded();
         let (interaction_sender, interaction_receiver) = unbounded();
 
@@ -286,16 +672,75 @@ impl DiscordClient {
                             Some(InteractionData::ApplicationCommand(ref command)),
                         ) = (interaction.guild_id, &interaction.data)
                         {
-                            if let Some((language, difficulty)) = is_game_starting(&command) {
+                            if let Some((language, difficulty, enemy, class, mode, turns)) = is_game_starting(command) {
+                                println!(
+                                    "{} - discord_client - Attempting to start game in guild {} with lang {:?}, difficulty {:?}, enemy {}, class {}, mode {} and turns {}",
+                                    format_time(),
+                                    guild_id,
+                                    language,
+                                    difficulty,
+                                    enemy,
+                                    class,
+                                    mode,
+                                    turns,
+                                );
+                                start_game(&input_sender, &localizations, language, difficulty, enemy, class, mode, turns, &interaction);
+                                if let Err(err) = interaction_sender.send(InteractionIds {
+                                    id: interaction.id,
+                                    app_id: interaction.application_id,
+                                    token: interaction.token.clone(),
+                                }) {
+                                    println!(
+                                        "{} - discord_client - FAILED to send interaction ids: {}",
+                                        format_time(),
+                                        err
+                                    );
+                                }
+                            } else if let Some(language) = is_leaderboard_requested(command) {
                                 println!(
-                                    "{} - discord_client - Attempting to start game in guild {} with lang {} and difficulty {}",
+                                    "{} - discord_client - Showing leaderboard in guild {} with lang {:?}",
                                     format_time(),
                                     guild_id,
-                                    language.to_string(),
-                                    difficulty.to_string(),
+                                    language,
                                 );
-                                let localization = localizations.get(language).clone();
-                                start_game(&input_sender, localization, difficulty, &interaction);
+                                request_leaderboard(&input_sender, &localizations, language, &interaction);
+                                if let Err(err) = interaction_sender.send(InteractionIds {
+                                    id: interaction.id,
+                                    app_id: interaction.application_id,
+                                    token: interaction.token.clone(),
+                                }) {
+                                    println!(
+                                        "{} - discord_client - FAILED to send interaction ids: {}",
+                                        format_time(),
+                                        err
+                                    );
+                                }
+                            } else if let Some(update) = is_config_requested(command) {
+                                println!(
+                                    "{} - discord_client - Configuring guild {} with {:?}",
+                                    format_time(),
+                                    guild_id,
+                                    update,
+                                );
+                                configure_guild(&input_sender, update, &interaction);
+                                if let Err(err) = interaction_sender.send(InteractionIds {
+                                    id: interaction.id,
+                                    app_id: interaction.application_id,
+                                    token: interaction.token.clone(),
+                                }) {
+                                    println!(
+                                        "{} - discord_client - FAILED to send interaction ids: {}",
+                                        format_time(),
+                                        err
+                                    );
+                                }
+                            } else if is_game_ending(command) {
+                                println!(
+                                    "{} - discord_client - Attempting to end game in guild {}",
+                                    format_time(),
+                                    guild_id,
+                                );
+                                end_game(&input_sender, &interaction);
                                 if let Err(err) = interaction_sender.send(InteractionIds {
                                     id: interaction.id,
                                     app_id: interaction.application_id,
@@ -319,16 +764,33 @@ impl DiscordClient {
             }
         });
 
-        Ok((input_receiver, interaction_receiver))
+        (input_receiver, interaction_receiver)
     }
 
-    pub async fn listen_game(
-        &self,
+    fn listen_game(
+        http_write: Arc<HttpClient>,
         interactions_receiver: Receiver<InteractionIds>,
+        messages_path: Option<PathBuf>,
     ) -> Sender<GameRenderEvent> {
-        let cached_interactions = Arc::new(Mutex::new(HashMap::new()));
+        let mut cached_interactions = HashMap::new();
+        let mut messages: GameMessages = HashMap::new();
+        if let Some(path) = &messages_path {
+            for snapshot in read_json::<Vec<GameMessageSnapshot>>(path) {
+                // The cached render is only used as a base for progress bar updates,
+                // the first draw of a restored game overwrites it
+                let placeholder = RenderedGame {
+                    upper_message: RenderedMessage::Skip,
+                    lower_message: RenderedMessage::Skip,
+                };
+                messages.insert(
+                    snapshot.arena,
+                    (snapshot.interaction.id, snapshot.followup_id, placeholder),
+                );
+                cached_interactions.insert(snapshot.interaction.id, snapshot.interaction);
+            }
+        }
+        let cached_interactions = Arc::new(Mutex::new(cached_interactions));
         let cached_interactions_input = Arc::clone(&cached_interactions);
-        let http_write = Arc::clone(&self.http_write);
         let (output_sender, output_receiver) = unbounded::<GameRenderEvent>();
 
         tokio::spawn(async move {
@@ -343,11 +805,10 @@ impl DiscordClient {
         });
 
         tokio::spawn(async move {
-            let mut messages = HashMap::new();
             loop {
                 let ev = output_receiver.recv_timeout(Duration::from_secs(1));
                 if let Ok(ev) = ev {
-                    let guild_id = ev.guild_id;
+                    let arena = ev.arena;
 
                     let mut interaction_ids = None;
                     if let Ok(cached_interactions_lock) = cached_interactions.lock() {
@@ -363,7 +824,7 @@ impl DiscordClient {
                             GameRenderPayload::OngoingGame(payload) => {
                                 let rendered_game =
                                     DiscordRenderer::render_ongoing_game(&ev.loc, &payload);
-                                match messages.get_mut(&guild_id) {
+                                match messages.get_mut(&arena) {
                                     Some((cached_interaction_id, followup_id, cached))
                                         if interaction_ids.id == *cached_interaction_id =>
                                     {
@@ -395,13 +856,14 @@ impl DiscordClient {
                                             Ok(followup_id) => {
                                                 println!("{} - discord_client - Created game message with interaction id {}", format_time(), interaction_ids.id);
                                                 messages.insert(
-                                                    guild_id,
+                                                    arena,
                                                     (
                                                         interaction_ids.id,
                                                         followup_id,
                                                         rendered_game.into(),
                                                     ),
                                                 );
+                                                save_messages(&messages_path, &messages, &cached_interactions);
                                             }
                                             Err(err) => {
                                                 println!("{} - discord_client - ERROR creating game message with interaction id {}: {}", format_time(), interaction_ids.id, err);
@@ -414,7 +876,7 @@ impl DiscordClient {
                                 let rendered_game =
                                     DiscordRenderer::render_finished_game(&ev.loc, status);
                                 let mut remove = false;
-                                match messages.get(&guild_id) {
+                                match messages.get(&arena) {
                                     Some((cached_interaction_id, followup_id, _cached))
                                         if interaction_ids.id == *cached_interaction_id =>
                                     {
@@ -440,16 +902,17 @@ impl DiscordClient {
                                     }
                                 }
                                 if remove {
-                                    messages.remove(&guild_id);
+                                    messages.remove(&arena);
+                                    save_messages(&messages_path, &messages, &cached_interactions);
                                 }
                             }
                             GameRenderPayload::TurnProgress(progress) => {
-                                match messages.get_mut(&guild_id) {
+                                match messages.get_mut(&arena) {
                                     Some((cached_interaction_id, followup_id, cached))
                                         if interaction_ids.id == *cached_interaction_id =>
                                     {
                                         match DiscordRenderer::render_turn_progress(
-                                            guild_id, cached, &ev.loc, progress,
+                                            arena, cached, &ev.loc, progress,
                                         ) {
                                             Ok(rendered_game) => {
                                                 match update_game_message(
@@ -480,6 +943,15 @@ impl DiscordClient {
                                     }
                                 }
                             }
+                            GameRenderPayload::Leaderboard(stats) => {
+                                let leaderboard_message =
+                                    DiscordRenderer::render_leaderboard(&stats, &ev.loc);
+                                match create_message(&http_write, leaderboard_message, &interaction_ids).await {
+                                    Ok(()) => println!("{} - discord_client - Created leaderboard message with interaction id {}", format_time(), interaction_ids.id),
+                                    Err(err) =>
+                                        println!("{} - discord_client - ERROR creating leaderboard message with interaction id {}: {}", format_time(), interaction_ids.id, err),
+                                }
+                            }
                             GameRenderPayload::OneshotMessage(oneshot_type) => {
                                 let oneshot_message =
                                     DiscordRenderer::render_oneshot(oneshot_type, &ev.loc);
@@ -498,3 +970,17 @@ impl DiscordClient {
         output_sender
     }
 }
+
+impl ChatTransport for DiscordClient {
+    fn connect(self: Box<Self>) -> Result<TransportChannels, Box<dyn Error + Send + Sync>> {
+        let Self {
+            http_write,
+            http_read,
+            shard,
+            messages_path,
+        } = *self;
+        let (inputs, interactions) = Self::listen_discord(http_read, shard);
+        let renders = Self::listen_game(http_write, interactions, messages_path);
+        Ok(TransportChannels { inputs, renders })
+    }
+}
@@ -1,24 +1,31 @@
 use derive_new::new;
 use rand::seq::SliceRandom;
 use thiserror::Error;
+use strum::IntoEnumIterator;
 use twilight_model::{
     channel::message::{
-        component::{ButtonStyle, ActionRow, Button, Component},
+        component::{ButtonStyle, ActionRow, Button, Component, SelectMenu, SelectMenuOption},
         embed::Embed, ReactionType, MessageFlags
     },
-    id::{marker::GuildMarker, Id},
 };
 use twilight_util::builder::embed::{EmbedBuilder, EmbedFieldBuilder, ImageSource};
 
 use crate::{
-    components::{BygonePart, Health},
+    components::{Ability, EnemySlot, Health, PlayerClass},
+    enemies::{PartDefinition, PARTS_PER_ROW},
     events::{OneshotType, OngoingGamePayload},
-    game_helpers::FinishedGameStatus,
+    game_helpers::{ArenaId, FinishedGameStatus},
+    guild_config::GuildConfig,
     localization::{Localization, RenderText},
+    logging::format_time,
+    scoreboard::PlayerStats,
 };
 
 const PROGRESS_BAR_SIZE: usize = 4;
 const PROGRESS_BAR_SCALE: f32 = PROGRESS_BAR_SIZE as f32 + 1.0;
+pub const PART_BUTTON_SEPARATOR: char = ':';
+pub const ABILITY_MENU_ID: &str = "ability";
+pub const CLASS_MENU_ID: &str = "class";
 
 fn get_button_style(health: &Health) -> ButtonStyle {
     if health.current() == 0 {
@@ -30,19 +37,104 @@ fn get_button_style(health: &Health) -> ButtonStyle {
     }
 }
 
-fn make_controls_button(emoji: &str, health: &Health) -> Component {
+/// Buttons of the same part on two drones look alike, they are labeled with the enemy number then
+fn make_controls_button(
+    slot: EnemySlot,
+    encounter_size: usize,
+    part: &PartDefinition,
+    health: &Health,
+) -> Component {
     Component::Button(Button {
-        custom_id: Some(emoji.to_owned()),
+        custom_id: Some(format!("{}{}{}", slot.0, PART_BUTTON_SEPARATOR, part.id)),
         disabled: !health.alive(),
         emoji: Some(ReactionType::Unicode {
-            name: emoji.to_owned(),
+            name: part.emoji.clone(),
         }),
-        label: None,
+        label: if encounter_size > 1 {
+            Some((slot.0 + 1).to_string())
+        } else {
+            None
+        },
         style: get_button_style(health),
         url: None,
     })
 }
 
+fn make_ability_option(loc: &Localization, ability: Ability, label: String, value: String) -> SelectMenuOption {
+    SelectMenuOption {
+        default: false,
+        description: if ability.cooldown() > 0 {
+            Some(loc.abilities.cooldown.insert_turns(ability.cooldown()).0)
+        } else {
+            None
+        },
+        emoji: None,
+        label,
+        value,
+    }
+}
+
+/// The focused shot needs a target, so there is an option for every part that is still standing.
+/// Its value is the ability followed by the part button id.
+fn make_ability_menu(loc: &Localization, payload: &OngoingGamePayload) -> Component {
+    let mut options = Vec::new();
+    for ability in Ability::iter() {
+        let ability_name = loc.abilities.name(ability).to_string();
+        let ability_id: &str = ability.into();
+        if ability != Ability::FocusedShot {
+            options.push(make_ability_option(loc, ability, ability_name, ability_id.to_string()));
+            continue;
+        }
+        for enemy in payload.enemies.iter() {
+            let enemy_loc = enemy.definition.localization(loc.language);
+            for part in enemy.definition.parts.iter() {
+                if !matches!(enemy.parts.get(&part.id), Some(vitality) if vitality.health().alive()) {
+                    continue;
+                }
+                let target = if payload.enemies.len() > 1 {
+                    format!("{} ({})", enemy_loc.part_name(&part.id), enemy_loc.name_in(enemy.slot, payload.enemies.len()))
+                } else {
+                    enemy_loc.part_name(&part.id)
+                };
+                options.push(make_ability_option(
+                    loc,
+                    ability,
+                    format!("{}: {}", ability_name, target),
+                    format!("{}{}{}{}{}", ability_id, PART_BUTTON_SEPARATOR, enemy.slot.0, PART_BUTTON_SEPARATOR, part.id),
+                ));
+            }
+        }
+    }
+    Component::SelectMenu(SelectMenu {
+        custom_id: ABILITY_MENU_ID.to_string(),
+        disabled: false,
+        max_values: Some(1),
+        min_values: Some(1),
+        options,
+        placeholder: Some(loc.abilities.menu.to_string()),
+    })
+}
+
+/// Only users who are not in the battle yet can use it, a class can't be changed once joined
+fn make_class_menu(loc: &Localization) -> Component {
+    Component::SelectMenu(SelectMenu {
+        custom_id: CLASS_MENU_ID.to_string(),
+        disabled: false,
+        max_values: Some(1),
+        min_values: Some(1),
+        options: PlayerClass::iter()
+            .map(|class| SelectMenuOption {
+                default: false,
+                description: loc.classes.description(class),
+                emoji: None,
+                label: loc.classes.name(class),
+                value: class.to_string(),
+            })
+            .collect(),
+        placeholder: Some(loc.classes.menu.to_string()),
+    })
+}
+
 fn render_turn_timer(cur: usize, max: usize) -> String {
     let cur = cur.min(max);
     format!(
@@ -52,9 +144,9 @@ fn render_turn_timer(cur: usize, max: usize) -> String {
     )
 }
 #[derive(Clone, Debug, Error, new)]
-#[error("{msg}, guild id: {id}")]
+#[error("{msg}, arena: {id}")]
 pub struct GameRenderError {
-    id: Id<GuildMarker>,
+    id: ArenaId,
     msg: String,
 }
 
@@ -107,63 +199,98 @@ impl DiscordRenderer {
         loc: &Localization,
         payload: &OngoingGamePayload,
     ) -> RenderedGamePure {
-        let title = EmbedBuilder::new()
-            .description(&loc.title)
-            .image(
-                ImageSource::url(
-                    "https://cdn.discordapp.com/attachments/924690917108121661/1015027218264641556/bygone_03_cropped.gif"
-                )
-                .unwrap(),
-            )
-            .build();
+        // The leading enemy sets the scene for the whole encounter
+        let mut title_builder = EmbedBuilder::new();
+        if let Some(leader) = payload.enemies.first() {
+            title_builder =
+                title_builder.description(&leader.definition.localization(loc.language).title.0);
+            if let Some(image) = &leader.definition.image {
+                match ImageSource::url(image) {
+                    Ok(image) => title_builder = title_builder.image(image),
+                    Err(err) => println!(
+                        "{} - discord_renderer - ERROR rendering enemy image {}: {}",
+                        format_time(),
+                        image,
+                        err
+                    ),
+                }
+            }
+        }
+        let title = title_builder.build();
 
-        let bygone_status = format!(
-            " • {}\n • {}: {}",
-            payload.bygone_attack.render_text(loc),
-            &loc.core,
-            payload.bygone_stage.render_text(loc)
-        );
-        let sensor = payload.bygone_parts[BygonePart::Sensor].render_text(loc);
-        let core = payload.bygone_parts[BygonePart::Core].render_text(loc);
-        let gun = payload.bygone_parts[BygonePart::Gun].render_text(loc);
-        let right_wing = payload.bygone_parts[BygonePart::RightWing].render_text(loc);
-        let left_wing = payload.bygone_parts[BygonePart::LeftWing].render_text(loc);
-
-        let enemies = EmbedBuilder::new()
-            .field(EmbedFieldBuilder::new(&loc.status_title, bygone_status).build())
-            .field(EmbedFieldBuilder::new(&loc.sensor_title, sensor).inline())
-            .field(EmbedFieldBuilder::new(&loc.core_title, core).inline())
-            .field(EmbedFieldBuilder::new(&loc.gun_title, gun).inline())
-            .field(EmbedFieldBuilder::new(&loc.right_wing_title, right_wing).inline())
-            .field(EmbedFieldBuilder::new(&loc.left_wing_title, left_wing).inline())
-            .build();
+        let mut embeds = vec![title];
+        let mut controls = Vec::new();
+        for enemy in payload.enemies.iter() {
+            let enemy_loc = enemy.definition.localization(loc.language);
+            let status_title = if payload.enemies.len() > 1 {
+                enemy_loc.name_in(enemy.slot, payload.enemies.len())
+            } else {
+                loc.status_title.to_string()
+            };
+            let mut bygone_status = format!(
+                " • {}\n • {}: {}",
+                enemy.attack.render_text(loc),
+                &enemy_loc.stage_title.0,
+                enemy_loc.stage_name(&enemy.stage)
+            );
+            if let (Some(wave), 0) = (payload.wave, enemy.slot.0) {
+                bygone_status = format!(
+                    " • {}: {}\n{}",
+                    loc.survival.wave_title, wave, bygone_status
+                );
+            }
+            if !enemy.effects.is_empty() {
+                bygone_status += &format!(
+                    "\n • {}: {}",
+                    loc.effects.title,
+                    loc.effects.list(&enemy.effects)
+                );
+            }
+            if let Some(special) = &enemy.specials.telegraph {
+                bygone_status += &format!(
+                    "\n • {}: {}",
+                    loc.specials.next_title,
+                    loc.specials.get(special).name
+                );
+            }
+            if let Some(part) = &enemy.specials.shielded {
+                bygone_status += &format!(
+                    "\n • {}: {}",
+                    loc.specials.shield_title,
+                    enemy_loc.part_name(part)
+                );
+            }
 
-        let controls = vec![
-            Component::ActionRow(ActionRow {
-                components: vec![
-                    make_controls_button("🇸", payload.bygone_parts[BygonePart::Sensor].health()),
-                    make_controls_button("🇨", payload.bygone_parts[BygonePart::Core].health()),
-                    make_controls_button("🇬", payload.bygone_parts[BygonePart::Gun].health()),
-                ],
-            }),
-            Component::ActionRow(ActionRow {
-                components: vec![
-                    make_controls_button("🇷", payload.bygone_parts[BygonePart::RightWing].health()),
-                    Component::Button(Button {
-                        custom_id: Some("status".to_owned()),
-                        disabled: true,
-                        emoji: None, //Some(ReactionType::Custom { name: (*rng.sample(&AUXILIARY_EMOJIS).unwrap_or(&"")).to_owned(), id: None, animated: false }),
-                        label: Some(".".to_owned()),
-                        style: ButtonStyle::Secondary,
-                        url: None,
-                    }),
-                    make_controls_button("🇱", payload.bygone_parts[BygonePart::LeftWing].health()),
-                ],
-            }),
-        ];
+            let mut enemy_embed_builder = EmbedBuilder::new()
+                .field(EmbedFieldBuilder::new(status_title, bygone_status).build());
+            let mut buttons = Vec::new();
+            for part in enemy.definition.parts.iter() {
+                if let Some(vitality) = enemy.parts.get(&part.id) {
+                    enemy_embed_builder = enemy_embed_builder.field(
+                        EmbedFieldBuilder::new(
+                            enemy_loc.part_title(&part.id),
+                            vitality.render_text(loc),
+                        )
+                        .inline(),
+                    );
+                    buttons.push(make_controls_button(
+                        enemy.slot,
+                        payload.enemies.len(),
+                        part,
+                        vitality.health(),
+                    ));
+                }
+            }
+            embeds.push(enemy_embed_builder.build());
+            controls.extend(buttons.chunks(PARTS_PER_ROW).map(|row| {
+                Component::ActionRow(ActionRow {
+                    components: row.to_vec(),
+                })
+            }));
+        }
 
         let upper_message = RenderedMessagePure {
-            embeds: vec![title, enemies],
+            embeds,
             components: controls,
             flags: MessageFlags::empty(),
         };
@@ -181,17 +308,29 @@ impl DiscordRenderer {
             .build();
 
         let mut players_embed_builder = EmbedBuilder::new();
-        for (name, vitality) in payload.players.iter() {
+        for (name, class, vitality, effects) in payload.players.iter() {
+            let mut player_status = vitality.health().render_text(loc);
+            if !effects.is_empty() {
+                player_status += &format!("\n{}", loc.effects.list(effects));
+            }
             players_embed_builder = players_embed_builder.field(EmbedFieldBuilder::new(
-                &name.0,
-                vitality.health().render_text(loc),
+                format!("{} • {}", name.0, loc.classes.name(*class)),
+                player_status,
             ));
         }
         let players = players_embed_builder.build();
 
+        // The part buttons can take every row of the upper message, the menus go under the log
         let lower_message = RenderedMessagePure {
             embeds: vec![turn_progress, log, players],
-            components: Vec::new(),
+            components: vec![
+                Component::ActionRow(ActionRow {
+                    components: vec![make_ability_menu(loc, payload)],
+                }),
+                Component::ActionRow(ActionRow {
+                    components: vec![make_class_menu(loc)],
+                }),
+            ],
             flags: MessageFlags::empty(),
         };
 
@@ -203,11 +342,13 @@ impl DiscordRenderer {
 
     pub fn render_finished_game(loc: &Localization, status: FinishedGameStatus) -> RenderedGame {
         let message = match status {
-            FinishedGameStatus::Won => &loc.won.0,
-            FinishedGameStatus::Lost => &loc.lost.0,
-            FinishedGameStatus::Expired => &loc.expired.choose(&mut rand::thread_rng()).unwrap().0,
+            FinishedGameStatus::Won => loc.won.clone(),
+            FinishedGameStatus::Lost => loc.lost.clone(),
+            FinishedGameStatus::Expired => loc.expired.choose(&mut rand::thread_rng()).unwrap().clone(),
+            FinishedGameStatus::Survived(waves) => loc.survival.summary.insert_waves(waves),
+            FinishedGameStatus::Ended => loc.ending.ended.clone(),
         };
-        let embed = EmbedBuilder::new().description(message).build();
+        let embed = EmbedBuilder::new().description(message.0).build();
 
         RenderedGame {
             upper_message: RenderedMessagePure {
@@ -221,7 +362,7 @@ impl DiscordRenderer {
     }
 
     pub fn render_turn_progress(
-        id: Id<GuildMarker>,
+        id: ArenaId,
         previous: &RenderedGame,
         loc: &Localization,
         progress: f32,
@@ -255,12 +396,48 @@ impl DiscordRenderer {
         }
     }
 
+    pub fn render_leaderboard(stats: &[PlayerStats], loc: &Localization) -> RenderedMessagePure {
+        let mut leaderboard_builder = EmbedBuilder::new().title(&loc.leaderboard_title);
+        if stats.is_empty() {
+            leaderboard_builder = leaderboard_builder.description(&loc.leaderboard_empty);
+        }
+        for (place, player_stats) in stats.iter().enumerate() {
+            leaderboard_builder = leaderboard_builder.field(EmbedFieldBuilder::new(
+                format!("{}. {}", place + 1, player_stats.name.0),
+                loc.leaderboard_stats.insert_player_stats(player_stats).0,
+            ));
+        }
+        RenderedMessagePure {
+            embeds: vec![leaderboard_builder.build()],
+            components: Vec::new(),
+            flags: MessageFlags::empty(),
+        }
+    }
+
+    pub fn render_config(config: &GuildConfig, loc: &Localization) -> RenderedMessagePure {
+        let mut config_builder = EmbedBuilder::new().title(&loc.config.title);
+        for (name, value) in loc.config.settings(config, |channel_id| format!("<#{}>", channel_id)) {
+            config_builder = config_builder.field(EmbedFieldBuilder::new(name, value));
+        }
+        RenderedMessagePure {
+            embeds: vec![config_builder.build()],
+            components: Vec::new(),
+            flags: MessageFlags::EPHEMERAL,
+        }
+    }
+
     pub fn render_oneshot(oneshot_type: OneshotType, loc: &Localization) -> RenderedMessagePure {
         let oneshot_message = match oneshot_type {
             OneshotType::Cooldown(duration_left) => {
                 loc.battle_cooldown.insert_duration(&duration_left)
             }
             OneshotType::OtherGameInProgress => loc.other_battle_ongoing.clone(),
+            OneshotType::ChannelNotAllowed => loc.config.channel_not_allowed.clone(),
+            OneshotType::NotPermitted => loc.config.not_permitted.clone(),
+            OneshotType::Config(config) => return Self::render_config(&config, loc),
+            OneshotType::GameEnded => loc.ending.called_off.clone(),
+            OneshotType::NotAllowedToEnd => loc.ending.not_allowed.clone(),
+            OneshotType::NoGameToEnd => loc.ending.no_battle.clone(),
         };
         let oneshot_embed = EmbedBuilder::new().description(&oneshot_message.0).build();
         RenderedMessagePure {
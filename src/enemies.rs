@@ -0,0 +1,489 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::RangeInclusive,
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use crate::{
+    components::{BygonePart, BygoneStage, EnemySlot, SpecialMove, TargetingPolicy, TimedEffect},
+    game_helpers::Difficulty,
+    io::read_json,
+    localization::{Language, LocalizedLine},
+    logging::format_time,
+};
+
+/// Built-in enemies, also used when the enemies file can't be loaded
+const DEFAULT_ENEMIES_JSON: &str = include_str!("../data/enemies.json");
+pub const DEFAULT_ENEMY: &str = "bygone03";
+/// For saves and logs made before there were several enemies
+pub fn default_enemy() -> String {
+    DEFAULT_ENEMY.to_owned()
+}
+
+/// The renderer puts up to 3 part buttons in a row, every enemy of an encounter starts a new one
+pub const PARTS_PER_ROW: usize = 3;
+/// Discord fits 5 rows of buttons under a message
+const MAX_BUTTON_ROWS: usize = 5;
+
+/// What destroying a part does to the enemy
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum PartEffect {
+    /// Added to the enemy's accuracy
+    Accuracy(isize),
+    /// Added to the dodge of every part
+    Dodge(isize),
+    /// The enemy moves to its next stage and the part is rebuilt, in the last stage the enemy dies instead
+    NextStage,
+    /// Put on the enemy itself
+    Status(TimedEffect),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PartDefinition {
+    pub id: BygonePart,
+    /// Short name to type in the terminal frontend
+    pub key: String,
+    /// Shown on the part's button
+    pub emoji: String,
+    pub dodge: isize,
+    /// Parts in the same group roll their health once and share it, like the wings do
+    #[serde(default)]
+    pub health_group: Option<String>,
+    #[serde(default)]
+    pub on_destroyed: Vec<PartEffect>,
+    /// Percent of the damage the part takes in a stage, like the armored core of _03 shrugging off
+    /// half of it. Stages that aren't listed take all of it.
+    #[serde(default)]
+    pub damage_taken: HashMap<BygoneStage, usize>,
+}
+
+impl PartDefinition {
+    pub fn damage_taken(&self, stage: &BygoneStage) -> usize {
+        self.damage_taken.get(stage).copied().unwrap_or(100)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SpecialDefinition {
+    pub special: SpecialMove,
+    /// Announced after every this many attacks
+    pub every: usize,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct StatRange {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl StatRange {
+    pub fn range(&self) -> RangeInclusive<usize> {
+        self.min..=self.max
+    }
+}
+
+/// Health is rolled for every part, damage once for the enemy
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct DifficultyScaling {
+    pub health: StatRange,
+    pub damage: StatRange,
+    /// Overrides the targeting of the enemy on this difficulty
+    #[serde(default)]
+    pub targeting: Option<TargetingPolicy>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PartLocalization {
+    /// Goes into the battle log lines
+    pub name: LocalizedLine,
+    /// Heads the part's health in the status
+    pub title: LocalizedLine,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EnemyLocalization {
+    pub name: LocalizedLine,
+    pub title: LocalizedLine,
+    /// What the stage is shown as in the status, like the core for _03
+    pub stage_title: LocalizedLine,
+    pub parts: HashMap<BygonePart, PartLocalization>,
+    pub stages: HashMap<BygoneStage, LocalizedLine>,
+    pub miss: Vec<LocalizedLine>,
+    pub hit: Vec<LocalizedLine>,
+    pub dead: Vec<LocalizedLine>,
+}
+
+impl EnemyLocalization {
+    /// Enemies are numbered when there are several of them, so players can tell the drones apart
+    pub fn name_in(&self, slot: EnemySlot, encounter_size: usize) -> String {
+        if encounter_size > 1 {
+            format!("{} {}", self.name.0, slot.0 + 1)
+        } else {
+            self.name.0.clone()
+        }
+    }
+
+    pub fn part_name(&self, part: &BygonePart) -> String {
+        match self.parts.get(part) {
+            Some(localization) => localization.name.0.clone(),
+            None => part.0.clone(),
+        }
+    }
+
+    pub fn part_title(&self, part: &BygonePart) -> String {
+        match self.parts.get(part) {
+            Some(localization) => localization.title.0.clone(),
+            None => part.0.clone(),
+        }
+    }
+
+    pub fn stage_name(&self, stage: &BygoneStage) -> String {
+        match self.stages.get(stage) {
+            Some(name) => name.0.clone(),
+            None => stage.0.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EnemyDefinition {
+    #[serde(default)]
+    pub image: Option<String>,
+    pub accuracy: isize,
+    /// Ids of the enemies that come along into the battle, they don't bring their own escort
+    #[serde(default)]
+    pub escort: Vec<String>,
+    /// In the order they are shown, health is rolled in this order too so seeds keep their battles
+    pub parts: Vec<PartDefinition>,
+    /// In the order the enemy goes through them, reaching the last one means defeat
+    pub stages: Vec<BygoneStage>,
+    /// Put on the enemy when it enters the stage, like the burns of _03
+    #[serde(default)]
+    pub stage_effects: HashMap<BygoneStage, Vec<TimedEffect>>,
+    /// Put on the players the enemy hits
+    #[serde(default)]
+    pub on_hit: Vec<TimedEffect>,
+    /// Every attack rolls its damage up to this much over the damage of the difficulty
+    #[serde(default)]
+    pub damage_spread: usize,
+    #[serde(default)]
+    pub crit_chance: isize,
+    #[serde(default)]
+    pub targeting: TargetingPolicy,
+    /// The special move of each stage, stages that aren't listed only have the usual shot
+    #[serde(default)]
+    pub specials: HashMap<BygoneStage, SpecialDefinition>,
+    pub difficulties: BTreeMap<Difficulty, DifficultyScaling>,
+    pub localizations: HashMap<Language, EnemyLocalization>,
+}
+
+impl EnemyDefinition {
+    pub fn part(&self, part: &BygonePart) -> Option<&PartDefinition> {
+        self.parts.iter().find(|definition| definition.id == *part)
+    }
+
+    /// Finds a part by its id, key or emoji, whatever the frontend sent
+    pub fn find_part(&self, name: &str) -> Option<&BygonePart> {
+        self.parts
+            .iter()
+            .find(|definition| {
+                definition.id.0.eq_ignore_ascii_case(name)
+                    || definition.key.eq_ignore_ascii_case(name)
+                    || definition.emoji == name
+            })
+            .map(|definition| &definition.id)
+    }
+
+    /// The part that moves the enemy through its stages, burns go there
+    pub fn stage_part(&self) -> Option<&BygonePart> {
+        self.parts
+            .iter()
+            .find(|definition| definition.on_destroyed.contains(&PartEffect::NextStage))
+            .map(|definition| &definition.id)
+    }
+
+    pub fn stage_effects(&self, stage: &BygoneStage) -> &[TimedEffect] {
+        self.stage_effects
+            .get(stage)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn special(&self, stage: &BygoneStage) -> Option<&SpecialDefinition> {
+        self.specials.get(stage)
+    }
+
+    pub fn first_stage(&self) -> BygoneStage {
+        self.stages[0].clone()
+    }
+
+    pub fn next_stage(&self, stage: &BygoneStage) -> BygoneStage {
+        match self.stages.iter().position(|known| known == stage) {
+            Some(idx) => self.stages[(idx + 1).min(self.stages.len() - 1)].clone(),
+            None => stage.clone(),
+        }
+    }
+
+    pub fn terminal(&self, stage: &BygoneStage) -> bool {
+        self.stages.last() == Some(stage)
+    }
+
+    pub fn scaling(&self, difficulty: Difficulty) -> DifficultyScaling {
+        self.difficulties[&difficulty]
+    }
+
+    pub fn targeting(&self, difficulty: Difficulty) -> TargetingPolicy {
+        self.scaling(difficulty).targeting.unwrap_or(self.targeting)
+    }
+
+    pub fn localization(&self, language: Language) -> &EnemyLocalization {
+        self.localizations
+            .get(&language)
+            .unwrap_or(&self.localizations[&Language::En])
+    }
+
+    fn button_rows(&self) -> usize {
+        self.parts.len().div_ceil(PARTS_PER_ROW)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.parts.is_empty() {
+            return Err("no parts".to_string());
+        }
+        let part_ids: HashSet<_> = self.parts.iter().map(|part| &part.id).collect();
+        if part_ids.len() != self.parts.len() {
+            return Err("part ids must be unique".to_string());
+        }
+        // Only the stage part takes the enemy down
+        if self.stage_part().is_none() {
+            return Err("no part moves it to its next stage".to_string());
+        }
+        if self.stages.is_empty() {
+            return Err("no stages".to_string());
+        }
+        for difficulty in Difficulty::iter() {
+            let scaling = self
+                .difficulties
+                .get(&difficulty)
+                .ok_or(format!("no scaling for difficulty {}", difficulty))?;
+            if scaling.health.min == 0 || scaling.health.min > scaling.health.max {
+                return Err(format!("bad health range for difficulty {}", difficulty));
+            }
+            if scaling.damage.min > scaling.damage.max {
+                return Err(format!("bad damage range for difficulty {}", difficulty));
+            }
+        }
+        for (stage, special) in self.specials.iter() {
+            if special.every == 0 {
+                return Err(format!("special of stage {} never comes", stage.0));
+            }
+            if let Some(part) = special
+                .special
+                .part()
+                .filter(|part| self.part(part).is_none())
+            {
+                return Err(format!(
+                    "special of stage {} needs unknown part {}",
+                    stage.0, part.0
+                ));
+            }
+        }
+        if !self.localizations.contains_key(&Language::En) {
+            return Err("no en localization".to_string());
+        }
+        for (language, localization) in self.localizations.iter() {
+            if localization.miss.is_empty()
+                || localization.hit.is_empty()
+                || localization.dead.is_empty()
+            {
+                return Err(format!("{} localization lacks battle log lines", language));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Every enemy the bot knows, by id
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EnemyDefinitions(pub BTreeMap<String, EnemyDefinition>);
+
+impl EnemyDefinitions {
+    /// Reads the enemies file if there is one, falls back to the built-in enemies if it is broken
+    pub fn load(path: &Option<PathBuf>) -> Self {
+        let definitions = match path {
+            Some(path) => read_json::<Self>(path),
+            None => return Self::default(),
+        };
+        match definitions.validate() {
+            Ok(()) => definitions,
+            Err(err) => {
+                println!(
+                    "{} - enemies - FAILED to load enemy definitions, using the built-in ones: {}",
+                    format_time(),
+                    err
+                );
+                Self::default()
+            }
+        }
+    }
+
+    /// Unknown ids, like ones from a save made with another enemies file, get the default enemy
+    pub fn get(&self, id: &str) -> &EnemyDefinition {
+        self.0.get(id).unwrap_or(&self.0[DEFAULT_ENEMY])
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.0.contains_key(id)
+    }
+
+    /// Ids of every enemy in the battle against the given one, in slot order
+    pub fn encounter<'a>(&'a self, id: &'a str) -> Vec<&'a str> {
+        let mut encounter = vec![id];
+        encounter.extend(self.get(id).escort.iter().map(String::as_str));
+        encounter
+    }
+
+    /// Definition of the enemy in the given slot of the battle against `id`
+    pub fn in_slot(&self, id: &str, slot: EnemySlot) -> Option<&EnemyDefinition> {
+        self.encounter(id)
+            .get(slot.0)
+            .map(|enemy_id| self.get(enemy_id))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !self.0.contains_key(DEFAULT_ENEMY) {
+            return Err(format!("no {} enemy", DEFAULT_ENEMY));
+        }
+        for (id, definition) in self.0.iter() {
+            definition
+                .validate()
+                .map_err(|err| format!("enemy {}: {}", id, err))?;
+            if let Some(escort) = definition
+                .escort
+                .iter()
+                .find(|escort| !self.contains(escort))
+            {
+                return Err(format!("enemy {}: unknown escort {}", id, escort));
+            }
+            let rows: usize = self
+                .encounter(id)
+                .iter()
+                .map(|enemy_id| self.get(enemy_id).button_rows())
+                .sum();
+            if rows > MAX_BUTTON_ROWS {
+                return Err(format!(
+                    "enemy {}: the parts of the encounter don't fit in {} rows of buttons",
+                    id, MAX_BUTTON_ROWS
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for EnemyDefinitions {
+    fn default() -> Self {
+        serde_json::from_str(DEFAULT_ENEMIES_JSON).expect("built-in enemy definitions are valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EnemyDefinitions, PartEffect, DEFAULT_ENEMY};
+    use crate::test_support::TempFile;
+
+    fn built_in_ids() -> Vec<String> {
+        EnemyDefinitions::default().0.into_keys().collect()
+    }
+
+    #[test]
+    fn valid_enemies_file_is_used() {
+        let mut definitions = EnemyDefinitions::default();
+        let mut scout = definitions.get(DEFAULT_ENEMY).clone();
+        scout.accuracy = 5;
+        scout.escort.clear();
+        definitions.0.insert("scout".to_string(), scout);
+        let file = TempFile::new(
+            "enemies-valid.json",
+            &serde_json::to_string(&definitions).unwrap(),
+        );
+
+        let loaded = EnemyDefinitions::load(&file.path());
+
+        assert!(loaded.contains("scout"));
+        assert_eq!(loaded.get("scout").accuracy, 5);
+    }
+
+    #[test]
+    fn broken_enemies_file_falls_back_to_the_built_in_enemies() {
+        let file = TempFile::new("enemies-broken.json", "{\"bygone03\": {\"parts\": ");
+
+        let loaded = EnemyDefinitions::load(&file.path());
+
+        assert_eq!(loaded.0.into_keys().collect::<Vec<_>>(), built_in_ids());
+    }
+
+    #[test]
+    fn enemies_file_needs_the_default_enemy_and_known_escorts() {
+        let mut without_default = EnemyDefinitions::default();
+        let bygone03 = without_default.0.remove(DEFAULT_ENEMY).unwrap();
+        without_default.0.insert("other".to_string(), bygone03);
+        assert!(without_default.validate().is_err());
+        let file = TempFile::new(
+            "enemies-without-default.json",
+            &serde_json::to_string(&without_default).unwrap(),
+        );
+        let loaded = EnemyDefinitions::load(&file.path());
+        assert!(!loaded.contains("other"));
+        assert_eq!(loaded.0.into_keys().collect::<Vec<_>>(), built_in_ids());
+
+        let mut unknown_escort = EnemyDefinitions::default();
+        unknown_escort
+            .0
+            .get_mut(DEFAULT_ENEMY)
+            .unwrap()
+            .escort
+            .push("ghost".to_string());
+        assert_eq!(
+            unknown_escort.validate(),
+            Err(format!("enemy {}: unknown escort ghost", DEFAULT_ENEMY))
+        );
+        let file = TempFile::new(
+            "enemies-unknown-escort.json",
+            &serde_json::to_string(&unknown_escort).unwrap(),
+        );
+        let loaded = EnemyDefinitions::load(&file.path());
+        assert!(loaded.get(DEFAULT_ENEMY).escort.is_empty());
+    }
+
+    #[test]
+    fn enemy_needs_a_part_that_takes_it_down() {
+        let mut undefeatable = EnemyDefinitions::default();
+        for part in undefeatable
+            .0
+            .get_mut(DEFAULT_ENEMY)
+            .unwrap()
+            .parts
+            .iter_mut()
+        {
+            part.on_destroyed
+                .retain(|effect| *effect != PartEffect::NextStage);
+        }
+        assert_eq!(
+            undefeatable.validate(),
+            Err(format!(
+                "enemy {}: no part moves it to its next stage",
+                DEFAULT_ENEMY
+            ))
+        );
+        let file = TempFile::new(
+            "enemies-undefeatable.json",
+            &serde_json::to_string(&undefeatable).unwrap(),
+        );
+        let loaded = EnemyDefinitions::load(&file.path());
+        assert!(loaded.get(DEFAULT_ENEMY).stage_part().is_some());
+    }
+}
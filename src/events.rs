@@ -2,55 +2,141 @@ use std::time::Duration;
 
 use bevy::prelude::*;
 use derive_new::new;
-use enum_map::EnumMap;
+use serde::{Deserialize, Serialize};
 use twilight_model::id::{
-    marker::{GuildMarker, InteractionMarker, UserMarker},
+    marker::{InteractionMarker, UserMarker},
     Id,
 };
 
 use crate::{
-    components::{Attack, Bygone03Stage, BygonePart, GameId, PlayerName, Vitality},
-    game_helpers::{Difficulty, FinishedGameStatus},
-    localization::Localization,
+    bundles::BygoneParts,
+    components::{
+        Ability, Attack, BygonePart, BygoneStage, EnemySlot, GameId, PlayerClass, PlayerName,
+        SpecialMove, SpecialMoves, StatusEffect, StatusEffects, TargetingPolicy, TimedEffect,
+        Vitality,
+    },
+    enemies::EnemyDefinition,
+    game_helpers::{ArenaId, Difficulty, FinishedGameStatus, GameMode, TurnMode},
+    guild_config::{GuildConfig, GuildConfigUpdate},
+    localization::{LanguageChoice, Localization},
+    scoreboard::PlayerStats,
 };
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct DeactivateEvent(pub Entity);
 
-#[derive(Clone, Copy, Debug, Eq, Hash, new, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Eq, Hash, new, Ord, PartialEq, PartialOrd)]
 pub struct BygonePartDeathEvent {
     pub entity: Entity,
     pub part: BygonePart,
-    pub guild_id: Id<GuildMarker>,
+    pub arena: ArenaId,
+}
+
+/// Puts an effect on a player or an enemy, an effect it already has gets its turns renewed
+#[derive(Clone, Debug, Eq, Hash, new, Ord, PartialEq, PartialOrd)]
+pub struct StatusEffectEvent {
+    pub entity: Entity,
+    pub arena: ArenaId,
+    pub effect: TimedEffect,
 }
 
 #[derive(Clone, Debug, Eq, Hash, new, Ord, PartialEq, PartialOrd)]
 pub struct PlayerAttackEvent {
     pub player: Id<UserMarker>,
     pub player_name: PlayerName,
-    pub guild_id: Id<GuildMarker>,
+    pub arena: ArenaId,
+    pub enemy: EnemySlot,
     pub target: BygonePart,
 }
 
+#[derive(Clone, Debug, Eq, Hash, new, Ord, PartialEq, PartialOrd)]
+pub struct PlayerAbilityEvent {
+    pub player: Id<UserMarker>,
+    pub player_name: PlayerName,
+    pub arena: ArenaId,
+    pub ability: Ability,
+    /// Only the focused shot is aimed at a part
+    pub target: Option<(EnemySlot, BygonePart)>,
+}
+
+/// An action of a strict turn, it waits for the other players' and resolves along with them
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum PlayerAction {
+    Attack(PlayerAttackEvent),
+    Ability(PlayerAbilityEvent),
+}
+
+impl PlayerAction {
+    pub fn player(&self) -> Id<UserMarker> {
+        match self {
+            PlayerAction::Attack(ev) => ev.player,
+            PlayerAction::Ability(ev) => ev.player,
+        }
+    }
+
+    pub fn arena(&self) -> ArenaId {
+        match self {
+            PlayerAction::Attack(ev) => ev.arena,
+            PlayerAction::Ability(ev) => ev.arena,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, Hash, new, Ord, PartialEq, PartialOrd)]
 pub struct EnemyAttackEvent {
-    pub guild_id: Id<GuildMarker>,
+    pub arena: ArenaId,
     pub game_id: GameId,
 }
 
+#[allow(clippy::too_many_arguments)]
 #[derive(Clone, Debug, new)]
 pub struct GameStartEvent {
     pub initial_player: Id<UserMarker>,
     pub initial_player_name: PlayerName,
-    pub difficulty: Difficulty,
-    pub guild_id: Id<GuildMarker>,
+    /// The guild's default difficulty is used when the command has none
+    pub difficulty: Option<Difficulty>,
+    pub enemy: String,
+    /// Class of the player who starts the battle
+    pub class: PlayerClass,
+    pub mode: GameMode,
+    pub turns: TurnMode,
+    pub arena: ArenaId,
     pub interaction: Id<InteractionMarker>,
+    /// `listen` settles it with the guild's settings and replaces `localization` with its lines
+    pub language: LanguageChoice,
     pub localization: Localization,
 }
 
+#[derive(Clone, Debug, new)]
+pub struct LeaderboardRequestEvent {
+    pub arena: ArenaId,
+    pub interaction: Id<InteractionMarker>,
+    pub language: LanguageChoice,
+    pub localization: Localization,
+}
+
+/// The guild's settings change only if the member can manage the guild
+#[derive(Clone, Debug, new)]
+pub struct ConfigRequestEvent {
+    pub arena: ArenaId,
+    pub interaction: Id<InteractionMarker>,
+    pub language: LanguageChoice,
+    pub update: GuildConfigUpdate,
+    pub manage_guild: bool,
+}
+
+/// A player calls off the battle in the arena, the guild's settings say whether they may
+#[derive(Clone, Debug, new)]
+pub struct EndRequestEvent {
+    pub player: Id<UserMarker>,
+    pub arena: ArenaId,
+    pub interaction: Id<InteractionMarker>,
+    pub language: LanguageChoice,
+}
+
 #[derive(Clone, Copy, Debug, new)]
 pub struct GameDrawEvent {
-    pub guild_id: Id<GuildMarker>,
+    pub arena: ArenaId,
 }
 #[derive(Clone, Copy, Debug, new)]
 pub struct TurnEndEvent {
@@ -59,7 +145,7 @@ pub struct TurnEndEvent {
 
 #[derive(Clone, Copy, Debug, new)]
 pub struct ProgressBarUpdateEvent {
-    pub guild_id: Id<GuildMarker>,
+    pub arena: ArenaId,
     pub progress: f32,
 }
 
@@ -67,14 +153,41 @@ pub struct ProgressBarUpdateEvent {
 pub struct PlayerJoinEvent {
     pub player: Id<UserMarker>,
     pub player_name: PlayerName,
+    pub class: PlayerClass,
     pub game_id: GameId,
-    pub guild_id: Id<GuildMarker>,
+    pub arena: ArenaId,
+}
+
+/// A user picks their class to join a battle, they can't change it once they are in
+#[derive(Clone, Debug, Eq, Hash, new, Ord, PartialEq, PartialOrd)]
+pub struct JoinRequestEvent {
+    pub player: Id<UserMarker>,
+    pub player_name: PlayerName,
+    pub arena: ArenaId,
+    pub class: PlayerClass,
 }
 
 #[derive(Clone, Debug, Eq, Hash, new, Ord, PartialEq, PartialOrd)]
 pub struct BygoneSpawnEvent {
+    pub enemy: String,
     pub difficulty: Difficulty,
     pub game_id: GameId,
+    /// Waves cleared before this one in a survival battle, the enemies get stronger with each
+    #[new(default)]
+    pub wave: usize,
+}
+
+impl BygoneSpawnEvent {
+    pub fn in_wave(self, wave: usize) -> Self {
+        Self { wave, ..self }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, new, PartialEq)]
+pub struct GameEndEvent {
+    pub arena: ArenaId,
+    pub game_id: GameId,
+    pub status: FinishedGameStatus,
 }
 
 #[derive(Clone, Debug, Eq, Hash, new, Ord, PartialEq, PartialOrd)]
@@ -87,15 +200,20 @@ pub struct EventsPlugin;
 
 impl Plugin for EventsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<(Id<GuildMarker>, BattleLogEvent)>()
+        app.add_event::<(ArenaId, BattleLogEvent)>()
             .add_event::<BygonePartDeathEvent>()
             .add_event::<DeactivateEvent>()
             .add_event::<DelayedEvent>()
             .add_event::<EnemyAttackEvent>()
             .add_event::<GameDrawEvent>()
+            .add_event::<GameEndEvent>()
             .add_event::<GameStartEvent>()
+            .add_event::<LeaderboardRequestEvent>()
             .add_event::<(GameId, PlayerAttackEvent)>()
+            .add_event::<(GameId, PlayerAbilityEvent)>()
+            .add_event::<(GameId, PlayerAction)>()
             .add_event::<PlayerJoinEvent>()
+            .add_event::<StatusEffectEvent>()
             .add_event::<BygoneSpawnEvent>()
             .add_event::<DeallocateGameResourcesEvent>()
             .add_event::<TurnEndEvent>()
@@ -103,54 +221,118 @@ impl Plugin for EventsPlugin {
     }
 }
 
-#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+/// Whoever a status effect is on
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum Combatant {
+    Player(PlayerName),
+    Enemy(EnemySlot),
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub enum BattleLogEvent {
-    PlayerDead(PlayerName),
-    PlayerHit(PlayerName, BygonePart),
-    PlayerMiss(PlayerName),
-    BygoneHit(PlayerName),
-    BygoneMiss,
-    BygoneDead,
+    /// The scoreboard counts the events with a user id for that user, names can repeat
+    PlayerDead(Id<UserMarker>, PlayerName),
+    PlayerHit(Id<UserMarker>, PlayerName, EnemySlot, BygonePart),
+    PlayerMiss(Id<UserMarker>, PlayerName),
+    /// A hit that did more damage than usual, counts as a hit
+    PlayerCrit(Id<UserMarker>, PlayerName, EnemySlot, BygonePart),
+    BygoneHit(EnemySlot, PlayerName),
+    BygoneCrit(EnemySlot, PlayerName),
+    /// Goes before the attack when the enemy's targeting picked the player, not chance
+    BygoneTargets(EnemySlot, PlayerName, TargetingPolicy),
+    BygoneMiss(EnemySlot),
+    BygoneDead(EnemySlot),
+    /// The healer and the one who got healed, it can be the same player
+    PlayerHeal(PlayerName, PlayerName),
+    PlayerDefend(PlayerName),
+    /// Goes before the hit or miss of the focused shot
+    PlayerFocus(PlayerName),
+    /// The reviver and the revived player
+    PlayerRevive(PlayerName, PlayerName),
+    EffectApplied(Combatant, StatusEffect),
+    EffectExpired(Combatant, StatusEffect),
+    /// Burns of an enemy go to the part that moves its stage
+    BurnDamage(Combatant),
+    /// The enemy announces the special move of its next attack
+    BygoneTelegraph(EnemySlot, SpecialMove),
+    /// Goes before the shots of the special move, if it has any
+    BygoneSpecial(EnemySlot, SpecialMove),
+    /// The player hit the part that calls off the enemy's charged shot
+    SpecialInterrupted(PlayerName, EnemySlot),
+    /// The player's shot hit the enemy's shield, the part took no damage
+    PartShielded(PlayerName, EnemySlot, BygonePart),
+    /// A survival battle moves on to the wave with this number, counting from one
+    NextWave(usize),
 }
 
 #[derive(Clone, Debug)]
 pub enum InputEvent {
-    GameStart(GameStartEvent),
+    GameStart(Box<GameStartEvent>),
     PlayerAttack(PlayerAttackEvent),
+    PlayerAbility(PlayerAbilityEvent),
+    PlayerJoin(JoinRequestEvent),
+    Leaderboard(Box<LeaderboardRequestEvent>),
+    Config(Box<ConfigRequestEvent>),
+    GameEnd(Box<EndRequestEvent>),
 }
 
 #[derive(Clone, Debug)]
 pub enum DelayedEvent {
     GameDraw(GameDrawEvent),
     PlayerAttack((GameId, PlayerAttackEvent)),
+    PlayerAbility((GameId, PlayerAbilityEvent)),
+}
+
+#[derive(Clone, Debug)]
+pub struct EnemyStatus {
+    pub slot: EnemySlot,
+    /// Everything the frontends need to show the enemy: its parts, their buttons and its own lines
+    pub definition: EnemyDefinition,
+    pub parts: BygoneParts,
+    pub attack: Attack,
+    pub stage: BygoneStage,
+    pub effects: StatusEffects,
+    pub specials: SpecialMoves,
 }
 
 #[derive(Clone, Debug)]
 pub struct OngoingGamePayload {
-    pub bygone_parts: EnumMap<BygonePart, Vitality>,
-    pub bygone_attack: Attack,
-    pub bygone_stage: Bygone03Stage,
+    /// In slot order, the leading enemy first
+    pub enemies: Vec<EnemyStatus>,
     pub battle_log_lines: Vec<String>,
-    pub players: Vec<(PlayerName, Vitality)>,
+    pub players: Vec<(PlayerName, PlayerClass, Vitality, StatusEffects)>,
+    /// Number of the current wave of a survival battle, counting from one
+    pub wave: Option<usize>,
 }
 
-#[derive(Clone, Copy, Debug)]
+/// Only the user who asked sees these, in their own language
+#[derive(Clone, Debug)]
 pub enum OneshotType {
     Cooldown(Duration),
     OtherGameInProgress,
+    /// The guild only lets battles start in its battle channels
+    ChannelNotAllowed,
+    /// The member can't manage the guild, so its settings stay as they are
+    NotPermitted,
+    /// The guild's settings after the change
+    Config(GuildConfig),
+    GameEnded,
+    NotAllowedToEnd,
+    NoGameToEnd,
 }
 
 #[derive(Clone, Debug)]
 pub enum GameRenderPayload {
-    OngoingGame(OngoingGamePayload),
+    OngoingGame(Box<OngoingGamePayload>),
     FinishedGame(FinishedGameStatus),
     TurnProgress(f32),
     OneshotMessage(OneshotType),
+    Leaderboard(Vec<PlayerStats>),
 }
 
 #[derive(Clone, Debug, new)]
 pub struct GameRenderEvent {
-    pub guild_id: Id<GuildMarker>,
+    pub arena: ArenaId,
     pub interaction_id: Id<InteractionMarker>,
     pub loc: Localization,
     pub payload: GameRenderPayload,
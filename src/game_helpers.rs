@@ -1,17 +1,25 @@
-use std::time::{Duration, Instant, SystemTime};
+use std::{
+    cmp::Reverse,
+    collections::VecDeque,
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
 
 use derive_new::new;
-use enum_map::Enum;
-use serde::{Deserialize, Serialize};
-use strum::{Display, EnumString, IntoStaticStr};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use strum::{Display, EnumIter, EnumString, IntoStaticStr};
 use twilight_model::id::{
-    marker::{ApplicationMarker, InteractionMarker},
+    marker::{ApplicationMarker, ChannelMarker, GuildMarker, InteractionMarker, UserMarker},
     Id,
 };
 
-use crate::{components::GameId, localization::Localization};
+use crate::{
+    components::{EnemySlot, GameId}, enemies::default_enemy, events::PlayerAction, localization::Localization,
+    timings::Timings,
+};
 
-#[derive(Clone, Copy, Debug, Display, PartialEq, Eq, EnumString, IntoStaticStr, PartialOrd, Ord, Hash)]
+#[derive(Clone, Copy, Debug, Deserialize, Display, PartialEq, Eq, EnumIter, EnumString, IntoStaticStr, PartialOrd, Ord, Hash, Serialize)]
 pub enum Difficulty {
     Easy,
     Medium,
@@ -19,28 +27,52 @@ pub enum Difficulty {
     RealBullets,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
-pub struct EventDelay(pub Duration);
+/// Time source for every timer in the game.
+/// `Manual` stands still until advanced, so tests and simulations can step through turns without sleeping
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Clock {
+    #[default]
+    Real,
+    Manual(SystemTime),
+}
+
+impl Clock {
+    pub fn manual() -> Self {
+        Clock::Manual(SystemTime::UNIX_EPOCH)
+    }
+
+    pub fn now(&self) -> SystemTime {
+        match self {
+            Clock::Real => SystemTime::now(),
+            Clock::Manual(now) => *now,
+        }
+    }
+
+    pub fn elapsed_since(&self, earlier: SystemTime) -> Duration {
+        self.now().duration_since(earlier).unwrap_or_default()
+    }
+
+    pub fn advance(&mut self, duration: Duration) {
+        if let Clock::Manual(now) = self {
+            *now += duration;
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct GameTimer {
-    start: Instant,
+    start: SystemTime,
+    timings: Timings,
     enemy_attacked: bool,
     turn_ended: bool,
     progress_bar_ticks: u64,
 }
 
 impl GameTimer {
-    const TURN_DURATION_SECS: u64 = 10;
-    const PROGRESS_BAR_TICK_SECS: u64 = 2;
-
-    const TURN_DURATION: Duration = Duration::from_secs(Self::TURN_DURATION_SECS);
-    const ENEMY_ATTACK_DELAY: Duration =
-        Duration::from_millis(Self::TURN_DURATION_SECS * 1000 - 500);
-
-    pub fn new() -> Self {
+    pub fn new(clock: &Clock, timings: Timings) -> Self {
         Self {
-            start: Instant::now(),
+            start: clock.now(),
+            timings,
             enemy_attacked: false,
             turn_ended: false,
             progress_bar_ticks: 0,
@@ -51,10 +83,10 @@ impl GameTimer {
         self.enemy_attacked && self.turn_ended
     }
 
-    pub fn enemy_attack(&mut self) -> bool {
-        if self.timer_finished()
+    pub fn enemy_attack(&mut self, clock: &Clock) -> bool {
+        if self.timer_finished(clock)
             || self.enemy_attacked
-            || self.start.elapsed() < Self::ENEMY_ATTACK_DELAY
+            || clock.elapsed_since(self.start) < self.timings.enemy_attack_delay()
         {
             false
         } else {
@@ -63,8 +95,8 @@ impl GameTimer {
         }
     }
 
-    pub fn turn_end(&mut self) -> bool {
-        if self.turn_ended || !self.timer_finished() {
+    pub fn turn_end(&mut self, clock: &Clock) -> bool {
+        if self.turn_ended || !self.timer_finished(clock) {
             false
         } else {
             self.turn_ended = true;
@@ -72,28 +104,110 @@ impl GameTimer {
         }
     }
 
-    pub fn progress_bar_update(&mut self) -> Option<f32> {
-        if self.timer_finished() {
+    pub fn progress_bar_update(&mut self, clock: &Clock) -> Option<f32> {
+        if self.timer_finished(clock) {
             return None;
         }
-        let elapsed = self.start.elapsed().as_secs();
-        let next_progress_bar_pos = ((self.progress_bar_ticks + 1) * Self::PROGRESS_BAR_TICK_SECS)
-            .min(Self::TURN_DURATION_SECS);
+        let Timings {
+            turn_secs,
+            progress_bar_tick_secs,
+            ..
+        } = self.timings;
+        let elapsed = clock.elapsed_since(self.start).as_secs();
+        let next_progress_bar_pos =
+            ((self.progress_bar_ticks + 1) * progress_bar_tick_secs).min(turn_secs);
         if elapsed >= next_progress_bar_pos {
-            self.progress_bar_ticks +=
-                1 + (elapsed - next_progress_bar_pos) / Self::PROGRESS_BAR_TICK_SECS;
-            Some(
-                (elapsed as f32 / Self::TURN_DURATION_SECS as f32)
-                    .max(0.0)
-                    .min(1.0),
-            )
+            self.progress_bar_ticks += 1 + (elapsed - next_progress_bar_pos) / progress_bar_tick_secs;
+            Some((elapsed as f32 / turn_secs as f32).clamp(0.0, 1.0))
         } else {
             None
         }
     }
 
-    fn timer_finished(&self) -> bool {
-        self.start.elapsed() > Self::TURN_DURATION
+    fn timer_finished(&self, clock: &Clock) -> bool {
+        clock.elapsed_since(self.start) > self.timings.turn()
+    }
+}
+
+#[derive(Clone, Debug)]
+enum StrictPhase {
+    Collecting,
+    Resolving(VecDeque<PlayerAction>),
+    EnemyAttacked,
+    Ended,
+}
+
+/// A turn of a strict battle. The actions wait until every player has submitted one or the timeout
+/// runs out, then they go out one a frame in initiative order and the enemies answer them
+#[derive(Clone, Debug)]
+pub struct StrictTurn {
+    pub arena: ArenaId,
+    start: SystemTime,
+    /// Initiative and id of the player with the action, the queue is sorted by them
+    submitted: Vec<(usize, Id<UserMarker>, PlayerAction)>,
+    phase: StrictPhase,
+}
+
+/// What a strict turn does on the current frame
+#[derive(Clone, Debug)]
+pub enum StrictStep {
+    Wait,
+    Act(PlayerAction),
+    EnemyAttack,
+    TurnEnd,
+}
+
+impl StrictTurn {
+    pub fn new(arena: ArenaId, clock: &Clock) -> Self {
+        Self {
+            arena,
+            start: clock.now(),
+            submitted: Vec::new(),
+            phase: StrictPhase::Collecting,
+        }
+    }
+
+    pub fn submitted(&self, player: Id<UserMarker>) -> bool {
+        self.submitted.iter().any(|(_, id, _)| *id == player)
+    }
+
+    /// Returns false if the player has acted already or the turn is resolving, the action has to wait for the next one
+    pub fn submit(&mut self, initiative: usize, player: Id<UserMarker>, action: PlayerAction) -> bool {
+        if !matches!(self.phase, StrictPhase::Collecting) || self.submitted(player) {
+            return false;
+        }
+        self.submitted.push((initiative, player, action));
+        true
+    }
+
+    /// `everyone_submitted` tells whether the rest of the players are done too
+    pub fn step(&mut self, clock: &Clock, timeout: Duration, everyone_submitted: bool) -> StrictStep {
+        match &mut self.phase {
+            StrictPhase::Collecting => {
+                if everyone_submitted || clock.elapsed_since(self.start) >= timeout {
+                    self.submitted
+                        .sort_by_key(|(initiative, player, _)| (Reverse(*initiative), *player));
+                    let queue = self.submitted.iter().map(|(_, _, action)| action.clone()).collect();
+                    self.phase = StrictPhase::Resolving(queue);
+                }
+                StrictStep::Wait
+            }
+            StrictPhase::Resolving(queue) => match queue.pop_front() {
+                Some(action) => StrictStep::Act(action),
+                None => {
+                    self.phase = StrictPhase::EnemyAttacked;
+                    StrictStep::EnemyAttack
+                }
+            },
+            StrictPhase::EnemyAttacked | StrictPhase::Ended => {
+                self.phase = StrictPhase::Ended;
+                StrictStep::TurnEnd
+            }
+        }
+    }
+
+    pub fn ended(&self) -> bool {
+        matches!(self.phase, StrictPhase::Ended)
     }
 }
 
@@ -109,11 +223,115 @@ impl From<FinishedGameStatus> for GameStatus {
     }
 }
 
-#[derive(Clone, Copy, Debug, Deserialize, Enum, Eq, Hash, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub enum FinishedGameStatus {
     Won,
     Lost,
     Expired,
+    /// A survival battle is over, with the number of waves the players cleared
+    Survived(usize),
+    /// A player called the battle off before it was over
+    Ended,
+}
+
+/// Whether the battle ends with its enemy or keeps sending stronger ones
+#[derive(Clone, Copy, Debug, Default, Deserialize, Display, EnumIter, EnumString, Eq, Hash, PartialEq, Serialize)]
+#[strum(serialize_all = "snake_case")]
+pub enum GameMode {
+    #[default]
+    Classic,
+    Survival,
+}
+
+/// Whether players act whenever they like or the turn waits for all of them
+#[derive(Clone, Copy, Debug, Default, Deserialize, Display, EnumIter, EnumString, Eq, Hash, PartialEq, Serialize)]
+#[strum(serialize_all = "snake_case")]
+pub enum TurnMode {
+    #[default]
+    RealTime,
+    Strict,
+}
+
+/// Progress of a survival battle, the waves get stronger with every one cleared
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, new, PartialEq, Serialize)]
+pub struct Survival {
+    pub difficulty: Difficulty,
+    #[new(default)]
+    pub waves: usize,
+}
+
+impl Survival {
+    /// Every wave rolls on its own seed, the first one on the game's
+    pub fn wave_seed(&self, game_seed: u64) -> u64 {
+        game_seed.wrapping_add((self.waves as u64) << 32)
+    }
+}
+
+/// How battles are split up on a server, at most one battle runs in each arena at a time
+#[derive(Clone, Copy, Debug, Default, Display, PartialEq, Eq, EnumString, Hash)]
+#[strum(serialize_all = "snake_case")]
+pub enum GameScope {
+    Guild,
+    #[default]
+    Channel,
+}
+
+impl GameScope {
+    /// Narrows the place an input came from down to the arena its battle lives in
+    pub fn arena(&self, arena: ArenaId) -> ArenaId {
+        match self {
+            GameScope::Guild => ArenaId::new(arena.guild_id, None),
+            GameScope::Channel => arena,
+        }
+    }
+}
+
+/// Where a battle takes place: a whole guild or one of its channels.
+/// Stored as `guild` or `guild/channel` so it can key JSON maps, plain guild ids from older saves still parse.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, new, PartialOrd, Ord)]
+pub struct ArenaId {
+    pub guild_id: Id<GuildMarker>,
+    pub channel_id: Option<Id<ChannelMarker>>,
+}
+
+impl fmt::Display for ArenaId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.channel_id {
+            Some(channel_id) => write!(f, "{}/{}", self.guild_id, channel_id),
+            None => write!(f, "{}", self.guild_id),
+        }
+    }
+}
+
+impl FromStr for ArenaId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ids = s.splitn(2, '/');
+        let guild_id = ids
+            .next()
+            .and_then(|id| Id::from_str(id).ok())
+            .ok_or(format!("bad guild id in arena {}", s))?;
+        let channel_id = match ids.next() {
+            Some(id) => Some(Id::from_str(id).map_err(|_| format!("bad channel id in arena {}", s))?),
+            None => None,
+        };
+        Ok(Self::new(guild_id, channel_id))
+    }
+}
+
+impl Serialize for ArenaId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ArenaId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -123,6 +341,20 @@ pub struct Game {
     pub interaction_id: Id<InteractionMarker>,
     pub localization: Localization,
     pub status: GameStatus,
+    /// Seeds the enemy's rolls, a battle replayed with the same seed and inputs plays out the same
+    #[serde(default)]
+    pub seed: u64,
+    /// Id of the enemy definition the battle is against
+    #[serde(default = "default_enemy")]
+    pub enemy: String,
+    /// Only survival battles have it
+    #[serde(default)]
+    pub survival: Option<Survival>,
+    #[serde(default)]
+    pub turns: TurnMode,
+    /// The player who started the battle, battles from before `/config` have none
+    #[serde(default)]
+    pub starter: Option<Id<UserMarker>>,
 }
 
 impl Game {
@@ -130,25 +362,74 @@ impl Game {
         game_id: GameId,
         interaction_id: Id<InteractionMarker>,
         localization: Localization,
+        enemy: String,
+        seed: u64,
+        start_time: SystemTime,
     ) -> Self {
         Self {
-            start_time: SystemTime::now(),
+            start_time,
             id: game_id,
             interaction_id,
             localization,
             status: GameStatus::Ongoing,
+            seed,
+            enemy,
+            survival: None,
+            turns: TurnMode::default(),
+            starter: None,
         }
     }
 
-    pub fn duration_secs(&self) -> u64 {
-        match self.start_time.elapsed() {
-            Ok(dur) => dur.as_secs(),
-            Err(_) => 0,
+    pub fn with_starter(self, starter: Id<UserMarker>) -> Self {
+        Self {
+            starter: Some(starter),
+            ..self
         }
     }
+
+    pub fn with_turns(self, turns: TurnMode) -> Self {
+        Self { turns, ..self }
+    }
+
+    pub fn with_mode(self, mode: GameMode, difficulty: Difficulty) -> Self {
+        let survival = match mode {
+            GameMode::Classic => None,
+            GameMode::Survival => Some(Survival::new(difficulty)),
+        };
+        Self { survival, ..self }
+    }
+
+    /// A survival battle only ends with the players, it counts the waves however that happens
+    pub fn ending(&self, status: FinishedGameStatus) -> FinishedGameStatus {
+        match self.survival {
+            Some(survival) => FinishedGameStatus::Survived(survival.waves),
+            None => status,
+        }
+    }
+
+    /// Seeds the rolls of the enemy in the slot, every escort and every survival wave rolls on its own.
+    /// The leading enemy of the first wave rolls with the game seed itself.
+    pub fn enemy_seed(&self, slot: EnemySlot) -> u64 {
+        let wave_seed = match self.survival {
+            Some(survival) => survival.wave_seed(self.seed),
+            None => self.seed,
+        };
+        wave_seed.wrapping_add(slot.0 as u64)
+    }
+
+    pub fn mode(&self) -> GameMode {
+        match self.survival {
+            Some(_) => GameMode::Survival,
+            None => GameMode::Classic,
+        }
+    }
+
+    pub fn duration_secs(&self, clock: &Clock) -> u64 {
+        clock.elapsed_since(self.start_time).as_secs()
+    }
 }
 
-#[derive(Clone, Debug, new)]
+#[derive(Clone, Debug, Deserialize, new, Serialize)]
 pub struct InteractionIds {
     pub id: Id<InteractionMarker>,
     pub app_id: Id<ApplicationMarker>,
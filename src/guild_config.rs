@@ -0,0 +1,119 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Mutex,
+};
+
+use bevy::prelude::*;
+use crossbeam_channel::Sender;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+use twilight_model::id::{
+    marker::{ChannelMarker, GuildMarker, UserMarker},
+    Id,
+};
+
+use crate::{
+    game_helpers::{Difficulty, Game},
+    localization::Language,
+    logging::format_time,
+};
+
+/// Who can end a battle before the enemy or the players fall
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Display, EnumString, Eq, PartialEq, Serialize,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum GameEnders {
+    /// The player who started the battle
+    #[default]
+    Starter,
+    Anyone,
+}
+
+/// What a guild set with `/config`, anything left unset falls back to the bot's defaults
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct GuildConfig {
+    /// Battles started without a language speak this one
+    pub language: Option<Language>,
+    /// Battles started without a difficulty are fought on this one
+    pub difficulty: Option<Difficulty>,
+    /// Takes the place of the guild's game cooldown from the timings
+    pub cooldown_secs: Option<u64>,
+    /// Battles can be started anywhere while it is empty
+    pub allowed_channels: BTreeSet<Id<ChannelMarker>>,
+    pub enders: GameEnders,
+}
+
+impl GuildConfig {
+    pub fn apply(&mut self, update: &GuildConfigUpdate) {
+        if let Some(language) = update.language {
+            self.language = Some(language);
+        }
+        if let Some(difficulty) = update.difficulty {
+            self.difficulty = Some(difficulty);
+        }
+        if let Some(cooldown_secs) = update.cooldown_secs {
+            self.cooldown_secs = Some(cooldown_secs);
+        }
+        if let Some(channel_id) = update.allow_channel {
+            self.allowed_channels.insert(channel_id);
+        }
+        if let Some(channel_id) = update.disallow_channel {
+            self.allowed_channels.remove(&channel_id);
+        }
+        if let Some(enders) = update.enders {
+            self.enders = enders;
+        }
+    }
+
+    pub fn allows(&self, channel_id: Option<Id<ChannelMarker>>) -> bool {
+        self.allowed_channels.is_empty()
+            || channel_id.is_some_and(|channel_id| self.allowed_channels.contains(&channel_id))
+    }
+
+    /// Battles that don't know their starter can be ended by anyone, nobody could otherwise
+    pub fn can_end(&self, game: &Game, player: Id<UserMarker>) -> bool {
+        match self.enders {
+            GameEnders::Starter => game.starter.is_none_or(|starter| starter == player),
+            GameEnders::Anyone => true,
+        }
+    }
+}
+
+/// The options of one `/config` command, only the given ones change
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GuildConfigUpdate {
+    pub language: Option<Language>,
+    pub difficulty: Option<Difficulty>,
+    pub cooldown_secs: Option<u64>,
+    pub allow_channel: Option<Id<ChannelMarker>>,
+    pub disallow_channel: Option<Id<ChannelMarker>>,
+    pub enders: Option<GameEnders>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct GuildConfigs(pub HashMap<Id<GuildMarker>, GuildConfig>);
+
+impl GuildConfigs {
+    pub fn get(&self, guild_id: Id<GuildMarker>) -> GuildConfig {
+        self.0.get(&guild_id).cloned().unwrap_or_default()
+    }
+}
+
+pub fn save_guild_configs(sender: Mutex<Sender<GuildConfigs>>) -> impl FnMut(Res<GuildConfigs>) {
+    move |configs| {
+        if !configs.is_changed() {
+            return;
+        }
+        if let Ok(ref mut sender_lock) = sender.lock() {
+            if let Err(err) = sender_lock.send(configs.clone()) {
+                println!(
+                    "{} - guild_config - FAILED to send save guild configs event: {}",
+                    format_time(),
+                    err
+                );
+            }
+        }
+    }
+}
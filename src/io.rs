@@ -1,31 +1,131 @@
-// use std::{
-//     fs, io,
-//     path::{Path, PathBuf},
-//     time::Duration,
-// };
-
-// use crossbeam_channel::Receiver;
-// use serde::{de::DeserializeOwned, Serialize};
-
-// pub fn read_json<T: Default + DeserializeOwned>(path: &PathBuf) -> T {
-//     match fs::read(path) {
-//         Ok(games_data) => match serde_json::from_slice(&games_data) {
-//             Ok(deserialized_games) => deserialized_games,
-//             Err(_) => T::default(),
-//         },
-//         Err(_) => T::default(),
-//     }
-// }
-
-// pub fn write_json_from_channel<T: Serialize, P: AsRef<Path>>(
-//     receiver: &Receiver<T>,
-//     path: P,
-// ) -> io::Result<()> {
-//     let data = receiver.recv_timeout(Duration::from_secs(1));
-//     if let Ok(data) = data {
-//         if let Ok(serialized) = serde_json::to_string(&data) {
-//             fs::write(path, serialized)?;
-//         }
-//     }
-//     Ok(())
-// }
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crossbeam_channel::{Receiver, RecvError};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::logging::format_time;
+
+pub fn read_json<T: Default + DeserializeOwned>(path: &PathBuf) -> T {
+    match fs::read(path) {
+        Ok(data) => match serde_json::from_slice(&data) {
+            Ok(deserialized) => deserialized,
+            Err(err) => {
+                println!(
+                    "{} - io - FAILED to parse {}: {}",
+                    format_time(),
+                    path.display(),
+                    err
+                );
+                T::default()
+            }
+        },
+        Err(err) => {
+            println!(
+                "{} - io - FAILED to read {}: {}",
+                format_time(),
+                path.display(),
+                err
+            );
+            T::default()
+        }
+    }
+}
+
+/// Sibling of the file with `.tmp` after its whole name, so `games.json` and `games.jsonl` don't share one
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".tmp");
+    PathBuf::from(name)
+}
+
+pub fn write_json<T: Serialize, P: AsRef<Path>>(data: &T, path: P) -> io::Result<()> {
+    let serialized = serde_json::to_vec(data)?;
+    // Write to a sibling file first so a crash mid-write never leaves a truncated snapshot
+    let tmp_path = tmp_path(path.as_ref());
+    fs::write(&tmp_path, serialized)?;
+    fs::rename(tmp_path, path)
+}
+
+/// Waits for something to save, the `RecvError` means the game is gone and there never will be
+pub fn write_json_from_channel<T: Serialize, P: AsRef<Path>>(
+    receiver: &Receiver<T>,
+    path: P,
+) -> Result<io::Result<()>, RecvError> {
+    let data = receiver.recv()?;
+    // Only the latest state matters, skip everything that piled up while writing
+    let data = receiver.try_iter().last().unwrap_or(data);
+    Ok(write_json(&data, path))
+}
+
+/// Waits for something to append, the `RecvError` means the game is gone and there never will be
+pub fn append_json_lines_from_channel<T: Serialize, P: AsRef<Path>>(
+    receiver: &Receiver<T>,
+    path: P,
+) -> Result<io::Result<()>, RecvError> {
+    let data = receiver.recv()?;
+    Ok(append_json_lines(
+        std::iter::once(data).chain(receiver.try_iter()),
+        path,
+    ))
+}
+
+fn append_json_lines<T: Serialize, P: AsRef<Path>>(
+    data: impl Iterator<Item = T>,
+    path: P,
+) -> io::Result<()> {
+    let mut lines = Vec::new();
+    for data in data {
+        serde_json::to_writer(&mut lines, &data)?;
+        lines.push(b'\n');
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(&lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crossbeam_channel::unbounded;
+
+    use super::{read_json, tmp_path, write_json_from_channel};
+    use crate::test_support::TempFile;
+
+    #[test]
+    fn outputs_with_the_same_stem_write_through_their_own_temp_files() {
+        assert_ne!(
+            tmp_path(Path::new("data/games.json")),
+            tmp_path(Path::new("data/games.jsonl"))
+        );
+        assert_eq!(
+            tmp_path(Path::new("data/games.json")),
+            Path::new("data/games.json.tmp")
+        );
+    }
+
+    #[test]
+    fn channel_writes_the_latest_state_until_the_game_is_gone() {
+        let file = TempFile::new("io-latest.json", "");
+        let path = file.path().unwrap();
+        let (sender, receiver) = unbounded();
+        sender.send(vec![1]).unwrap();
+        sender.send(vec![1, 2]).unwrap();
+
+        assert!(matches!(
+            write_json_from_channel(&receiver, &path),
+            Ok(Ok(()))
+        ));
+        assert_eq!(read_json::<Vec<u64>>(&path), vec![1, 2]);
+        assert!(!tmp_path(&path).exists());
+
+        drop(sender);
+        assert!(write_json_from_channel(&receiver, &path).is_err());
+    }
+}
@@ -0,0 +1,489 @@
+use std::{
+    error::Error,
+    io::{self, BufRead},
+    str::FromStr,
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+};
+
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use twilight_model::id::{
+    marker::{ChannelMarker, InteractionMarker, UserMarker},
+    Id,
+};
+
+use crate::{
+    components::{Ability, BygonePart, EnemySlot, PlayerClass, PlayerName},
+    enemies::DEFAULT_ENEMY,
+    events::{
+        ConfigRequestEvent, EndRequestEvent, GameRenderEvent, GameRenderPayload, GameStartEvent,
+        InputEvent, JoinRequestEvent, LeaderboardRequestEvent, PlayerAbilityEvent,
+        PlayerAttackEvent,
+    },
+    game_helpers::{ArenaId, Difficulty, GameMode, TurnMode},
+    guild_config::{GameEnders, GuildConfigUpdate},
+    localization::{Language, LanguageChoice, Localizations},
+    text_renderer::TextRenderer,
+    transport::{ChatTransport, TransportChannels},
+};
+
+const LOCAL_GUILD_ID: u64 = 1;
+const LOCAL_CHANNEL_ID: u64 = 1;
+const LOCAL_OWNER_ID: u64 = 1;
+const USAGE: &str = "Commands:
+  start <player> [Easy|Medium|Hard|RealBullets] [en|ru] [enemy] [class] [classic|survival] [real_time|strict]
+  join <player> <fighter|scout|heavy|medic>
+  attack <player> <part> [enemy number]
+  focus <player> <part> [enemy number]
+  heal|defend|revive <player>
+  leaderboard [en|ru]
+  config <player> [language=en|ru] [difficulty=<difficulty>] [cooldown=<secs>] [allow=<channel>] [disallow=<channel>] [who_can_end=starter|anyone]
+  end <player>
+  help
+
+Player 1 owns the server, only they can change its settings";
+
+static NEXT_INTERACTION_ID: AtomicU64 = AtomicU64::new(1);
+
+fn next_interaction_id() -> Id<InteractionMarker> {
+    Id::new(NEXT_INTERACTION_ID.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Terminal frontend: reads commands from stdin and prints the game as plain text.
+/// Every player is a fake user numbered from 1 to `players`.
+pub struct LocalClient {
+    players: u64,
+}
+
+impl LocalClient {
+    pub fn new(players: u64) -> Self {
+        Self { players }
+    }
+
+    fn player(&self, arg: Option<&str>) -> Result<(Id<UserMarker>, PlayerName), String> {
+        let number = arg
+            .and_then(|arg| arg.parse::<u64>().ok())
+            .filter(|number| (1..=self.players).contains(number))
+            .ok_or(format!(
+                "player must be a number from 1 to {}",
+                self.players
+            ))?;
+        Ok((Id::new(number), PlayerName(format!("Player {}", number))))
+    }
+
+    fn class(arg: Option<&str>) -> Result<PlayerClass, String> {
+        match arg {
+            Some(arg) => PlayerClass::from_str(arg).map_err(|_| format!("unknown class {}", arg)),
+            None => Ok(PlayerClass::default()),
+        }
+    }
+
+    fn channel(arg: &str) -> Result<Id<ChannelMarker>, String> {
+        arg.parse::<u64>()
+            .ok()
+            .and_then(Id::new_checked)
+            .ok_or(format!("unknown channel {}", arg))
+    }
+
+    /// Enemies are numbered from 1 on the screen
+    fn enemy(arg: Option<&str>) -> Result<EnemySlot, String> {
+        match arg {
+            Some(arg) => arg
+                .parse::<usize>()
+                .ok()
+                .filter(|number| *number > 0)
+                .map(|number| EnemySlot(number - 1))
+                .ok_or(format!("unknown enemy {}", arg)),
+            None => Ok(EnemySlot::default()),
+        }
+    }
+
+    fn parse_command(
+        &self,
+        line: &str,
+        localizations: &Localizations,
+    ) -> Result<Option<InputEvent>, String> {
+        let arena = ArenaId::new(Id::new(LOCAL_GUILD_ID), Some(Id::new(LOCAL_CHANNEL_ID)));
+        let mut args = line.split_whitespace();
+        match args.next() {
+            Some("start") => {
+                let (player, player_name) = self.player(args.next())?;
+                let difficulty = args
+                    .next()
+                    .map(|arg| {
+                        Difficulty::from_str(arg).map_err(|_| format!("unknown difficulty {}", arg))
+                    })
+                    .transpose()?;
+                let language = args
+                    .next()
+                    .map(|arg| {
+                        Language::from_str(arg).map_err(|_| format!("unknown language {}", arg))
+                    })
+                    .transpose()?;
+                let enemy = args.next().unwrap_or(DEFAULT_ENEMY).to_string();
+                let class = Self::class(args.next())?;
+                let mode = match args.next() {
+                    Some(arg) => {
+                        GameMode::from_str(arg).map_err(|_| format!("unknown mode {}", arg))?
+                    }
+                    None => GameMode::Classic,
+                };
+                let turns = match args.next() {
+                    Some(arg) => {
+                        TurnMode::from_str(arg).map_err(|_| format!("unknown turns {}", arg))?
+                    }
+                    None => TurnMode::RealTime,
+                };
+                Ok(Some(InputEvent::GameStart(Box::new(GameStartEvent::new(
+                    player,
+                    player_name,
+                    difficulty,
+                    enemy,
+                    class,
+                    mode,
+                    turns,
+                    arena,
+                    next_interaction_id(),
+                    LanguageChoice::new(language, None, None),
+                    localizations.get(language.unwrap_or_default()).clone(),
+                )))))
+            }
+            Some("join") => {
+                let (player, player_name) = self.player(args.next())?;
+                let class = args.next().ok_or("name the class to join as")?;
+                Ok(Some(InputEvent::PlayerJoin(JoinRequestEvent::new(
+                    player,
+                    player_name,
+                    arena,
+                    Self::class(Some(class))?,
+                ))))
+            }
+            Some("attack") => {
+                let (player, player_name) = self.player(args.next())?;
+                let part = args
+                    .next()
+                    .map(BygonePart::from)
+                    .ok_or("name the part to attack")?;
+                let enemy = Self::enemy(args.next())?;
+                Ok(Some(InputEvent::PlayerAttack(PlayerAttackEvent::new(
+                    player,
+                    player_name,
+                    arena,
+                    enemy,
+                    part,
+                ))))
+            }
+            Some("focus") => {
+                let (player, player_name) = self.player(args.next())?;
+                let part = args
+                    .next()
+                    .map(BygonePart::from)
+                    .ok_or("name the part to aim at")?;
+                let enemy = Self::enemy(args.next())?;
+                Ok(Some(InputEvent::PlayerAbility(PlayerAbilityEvent::new(
+                    player,
+                    player_name,
+                    arena,
+                    Ability::FocusedShot,
+                    Some((enemy, part)),
+                ))))
+            }
+            Some(command @ ("heal" | "defend" | "revive")) => {
+                let (player, player_name) = self.player(args.next())?;
+                let ability = Ability::from_str(command).map_err(|err| err.to_string())?;
+                Ok(Some(InputEvent::PlayerAbility(PlayerAbilityEvent::new(
+                    player,
+                    player_name,
+                    arena,
+                    ability,
+                    None,
+                ))))
+            }
+            Some("leaderboard") => {
+                let language = args
+                    .next()
+                    .map(|arg| {
+                        Language::from_str(arg).map_err(|_| format!("unknown language {}", arg))
+                    })
+                    .transpose()?;
+                Ok(Some(InputEvent::Leaderboard(Box::new(
+                    LeaderboardRequestEvent::new(
+                        arena,
+                        next_interaction_id(),
+                        LanguageChoice::new(language, None, None),
+                        localizations.get(language.unwrap_or_default()).clone(),
+                    ),
+                ))))
+            }
+            Some("config") => {
+                let (player, _) = self.player(args.next())?;
+                let mut update = GuildConfigUpdate::default();
+                for arg in args {
+                    let (key, value) = arg
+                        .split_once('=')
+                        .ok_or(format!("settings go as key=value, got {}", arg))?;
+                    let unknown = || format!("unknown {} {}", key, value);
+                    match key {
+                        "language" => {
+                            update.language =
+                                Some(Language::from_str(value).map_err(|_| unknown())?)
+                        }
+                        "difficulty" => {
+                            update.difficulty =
+                                Some(Difficulty::from_str(value).map_err(|_| unknown())?)
+                        }
+                        "cooldown" => {
+                            update.cooldown_secs = Some(value.parse().map_err(|_| unknown())?)
+                        }
+                        "allow" => update.allow_channel = Some(Self::channel(value)?),
+                        "disallow" => update.disallow_channel = Some(Self::channel(value)?),
+                        "who_can_end" => {
+                            update.enders =
+                                Some(GameEnders::from_str(value).map_err(|_| unknown())?)
+                        }
+                        _ => return Err(format!("unknown setting {}", key)),
+                    }
+                }
+                Ok(Some(InputEvent::Config(Box::new(ConfigRequestEvent::new(
+                    arena,
+                    next_interaction_id(),
+                    LanguageChoice::default(),
+                    update,
+                    player == Id::new(LOCAL_OWNER_ID),
+                )))))
+            }
+            Some("end") => {
+                let (player, _) = self.player(args.next())?;
+                Ok(Some(InputEvent::GameEnd(Box::new(EndRequestEvent::new(
+                    player,
+                    arena,
+                    next_interaction_id(),
+                    LanguageChoice::default(),
+                )))))
+            }
+            Some("help") => {
+                println!("{}", USAGE);
+                Ok(None)
+            }
+            Some(command) => Err(format!("unknown command {}", command)),
+            None => Ok(None),
+        }
+    }
+
+    fn listen_stdin(self) -> Receiver<InputEvent> {
+        let (input_sender, input_receiver) = unbounded();
+        thread::spawn(move || {
+            let localizations = Localizations::new();
+            println!("{}", USAGE);
+            for line in io::stdin().lock().lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                match self.parse_command(&line, &localizations) {
+                    Ok(Some(ev)) => {
+                        if input_sender.send(ev).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(err) => println!("{}\n{}", err, USAGE),
+                }
+            }
+        });
+        input_receiver
+    }
+
+    fn listen_game() -> Sender<GameRenderEvent> {
+        let (output_sender, output_receiver) = unbounded::<GameRenderEvent>();
+        thread::spawn(move || {
+            for ev in output_receiver.iter() {
+                let text = match ev.payload {
+                    GameRenderPayload::OngoingGame(payload) => {
+                        TextRenderer::render_ongoing_game(&ev.loc, &payload)
+                    }
+                    GameRenderPayload::FinishedGame(status) => {
+                        TextRenderer::render_finished_game(&ev.loc, status)
+                    }
+                    GameRenderPayload::TurnProgress(progress) => {
+                        TextRenderer::render_turn_progress(&ev.loc, progress)
+                    }
+                    GameRenderPayload::OneshotMessage(oneshot_type) => {
+                        TextRenderer::render_oneshot(oneshot_type, &ev.loc)
+                    }
+                    GameRenderPayload::Leaderboard(stats) => {
+                        TextRenderer::render_leaderboard(&stats, &ev.loc)
+                    }
+                };
+                println!("{}\n", text);
+            }
+        });
+        output_sender
+    }
+}
+
+impl ChatTransport for LocalClient {
+    fn connect(self: Box<Self>) -> Result<TransportChannels, Box<dyn Error + Send + Sync>> {
+        Ok(TransportChannels {
+            inputs: self.listen_stdin(),
+            renders: Self::listen_game(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use twilight_model::id::{marker::UserMarker, Id};
+
+    use super::{LocalClient, LOCAL_CHANNEL_ID, LOCAL_GUILD_ID};
+    use crate::{
+        components::{Ability, BygonePart, EnemySlot, PlayerClass, PlayerName},
+        events::{InputEvent, JoinRequestEvent, PlayerAbilityEvent, PlayerAttackEvent},
+        game_helpers::{ArenaId, Difficulty, GameMode, TurnMode},
+        localization::{Language, Localizations},
+    };
+
+    fn parse(line: &str) -> Result<Option<InputEvent>, String> {
+        LocalClient::new(3).parse_command(line, &Localizations::new())
+    }
+
+    fn arena() -> ArenaId {
+        ArenaId::new(Id::new(LOCAL_GUILD_ID), Some(Id::new(LOCAL_CHANNEL_ID)))
+    }
+
+    fn player(number: u64) -> (Id<UserMarker>, PlayerName) {
+        (Id::new(number), PlayerName(format!("Player {}", number)))
+    }
+
+    #[test]
+    fn start_takes_the_battle_settings_and_the_class() {
+        let ev = match parse("start 2 Hard ru drone scout survival strict") {
+            Ok(Some(InputEvent::GameStart(ev))) => ev,
+            other => panic!("expected a game start, got {:?}", other),
+        };
+        assert_eq!((ev.initial_player, ev.initial_player_name), player(2));
+        assert_eq!(ev.difficulty, Some(Difficulty::Hard));
+        assert_eq!(ev.language.explicit, Some(Language::Ru));
+        assert_eq!(ev.enemy, "drone");
+        assert_eq!(ev.class, PlayerClass::Scout);
+        assert_eq!(ev.mode, GameMode::Survival);
+        assert_eq!(ev.turns, TurnMode::Strict);
+        assert_eq!(ev.arena, arena());
+
+        let ev = match parse("start 1") {
+            Ok(Some(InputEvent::GameStart(ev))) => ev,
+            other => panic!("expected a game start, got {:?}", other),
+        };
+        assert_eq!(ev.difficulty, None);
+        assert_eq!(ev.class, PlayerClass::default());
+        assert_eq!(ev.mode, GameMode::Classic);
+        assert_eq!(ev.turns, TurnMode::RealTime);
+    }
+
+    #[test]
+    fn join_needs_a_known_class() {
+        let (user_id, name) = player(2);
+        assert!(matches!(
+            parse("join 2 medic"),
+            Ok(Some(InputEvent::PlayerJoin(ev)))
+                if ev == JoinRequestEvent::new(user_id, name, arena(), PlayerClass::Medic)
+        ));
+        assert_eq!(
+            parse("join 2").unwrap_err(),
+            "name the class to join as".to_string()
+        );
+        assert_eq!(
+            parse("join 2 wizard").unwrap_err(),
+            "unknown class wizard".to_string()
+        );
+    }
+
+    #[test]
+    fn attack_counts_the_enemies_from_one() {
+        let (user_id, name) = player(1);
+        assert!(matches!(
+            parse("attack 1 Gun"),
+            Ok(Some(InputEvent::PlayerAttack(ev)))
+                if ev == PlayerAttackEvent::new(
+                    user_id,
+                    name.clone(),
+                    arena(),
+                    EnemySlot(0),
+                    BygonePart::from("Gun"),
+                )
+        ));
+        assert!(matches!(
+            parse("attack 1 Hull 2"),
+            Ok(Some(InputEvent::PlayerAttack(ev)))
+                if ev == PlayerAttackEvent::new(
+                    user_id,
+                    name,
+                    arena(),
+                    EnemySlot(1),
+                    BygonePart::from("Hull"),
+                )
+        ));
+    }
+
+    #[test]
+    fn abilities_name_their_player_and_only_focus_takes_a_part() {
+        let (user_id, name) = player(3);
+        assert!(matches!(
+            parse("heal 3"),
+            Ok(Some(InputEvent::PlayerAbility(ev)))
+                if ev == PlayerAbilityEvent::new(user_id, name.clone(), arena(), Ability::Heal, None)
+        ));
+        assert!(matches!(
+            parse("revive 3"),
+            Ok(Some(InputEvent::PlayerAbility(ev))) if ev.ability == Ability::Revive
+        ));
+        assert!(matches!(
+            parse("focus 3 Gun 2"),
+            Ok(Some(InputEvent::PlayerAbility(ev)))
+                if ev == PlayerAbilityEvent::new(
+                    user_id,
+                    name,
+                    arena(),
+                    Ability::FocusedShot,
+                    Some((EnemySlot(1), BygonePart::from("Gun"))),
+                )
+        ));
+    }
+
+    #[test]
+    fn malformed_commands_are_explained() {
+        assert!(matches!(parse(""), Ok(None)));
+        assert_eq!(
+            parse("dance 1").unwrap_err(),
+            "unknown command dance".to_string()
+        );
+        for line in ["attack Gun", "attack 0 Gun", "attack 4 Gun", "heal"] {
+            assert_eq!(
+                parse(line).unwrap_err(),
+                "player must be a number from 1 to 3".to_string(),
+                "{}",
+                line
+            );
+        }
+        assert_eq!(
+            parse("attack 1").unwrap_err(),
+            "name the part to attack".to_string()
+        );
+        assert_eq!(
+            parse("attack 1 Gun 0").unwrap_err(),
+            "unknown enemy 0".to_string()
+        );
+        assert_eq!(
+            parse("start 1 Impossible").unwrap_err(),
+            "unknown difficulty Impossible".to_string()
+        );
+        assert_eq!(
+            parse("config 1 language").unwrap_err(),
+            "settings go as key=value, got language".to_string()
+        );
+        assert_eq!(
+            parse("config 1 colour=red").unwrap_err(),
+            "unknown setting colour".to_string()
+        );
+    }
+}
@@ -1,18 +1,66 @@
-use std::{collections::HashMap, fmt::Display, time::Duration};
+use std::{collections::HashMap, fmt::Display, str::FromStr, time::Duration};
 
 use serde::{Deserialize, Serialize};
 use strum::{EnumString, Display, IntoStaticStr};
+use twilight_model::id::{marker::ChannelMarker, Id};
 
-use crate::components::PlayerName;
+use crate::{
+    components::{Ability, PlayerClass, PlayerName, SpecialMove, StatusEffect, StatusEffects, TargetingPolicy},
+    guild_config::{GameEnders, GuildConfig},
+    scoreboard::PlayerStats,
+};
 
-#[derive(Clone, Copy, Debug, Display, EnumString, PartialEq, Eq, Hash, IntoStaticStr)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Display, EnumString, PartialEq, Eq, Hash, IntoStaticStr, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Language {
     #[strum(serialize = "ru")]
     Ru,
+    #[default]
     #[strum(serialize = "en")]
     En,
 }
 
+impl Language {
+    /// Discord locales look like `en-US` or `ru`, the ones the bot doesn't speak give nothing
+    pub fn from_locale(locale: &str) -> Option<Self> {
+        let language = locale.split('-').next().unwrap_or(locale);
+        Self::from_str(&language.to_lowercase()).ok()
+    }
+}
+
+/// Everything a command says about its language. The first one there wins: the command's own choice,
+/// the guild's default language, the guild's locale, the user's locale and English in the end
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct LanguageChoice {
+    pub explicit: Option<Language>,
+    pub guild_locale: Option<Language>,
+    pub user_locale: Option<Language>,
+}
+
+impl LanguageChoice {
+    pub fn new(explicit: Option<Language>, guild_locale: Option<&str>, user_locale: Option<&str>) -> Self {
+        Self {
+            explicit,
+            guild_locale: guild_locale.and_then(Language::from_locale),
+            user_locale: user_locale.and_then(Language::from_locale),
+        }
+    }
+
+    /// Only the game knows the guild's default language
+    pub fn resolve(&self, guild_default: Option<Language>) -> Language {
+        self.explicit
+            .or(guild_default)
+            .or(self.guild_locale)
+            .or(self.user_locale)
+            .unwrap_or_default()
+    }
+
+    /// For the messages only the user who asked sees, those speak the user's own language first
+    pub fn personal(&self, guild_default: Option<Language>) -> Language {
+        self.user_locale.unwrap_or_else(|| self.resolve(guild_default))
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct LocalizedLine(pub String);
 
@@ -21,12 +69,48 @@ impl LocalizedLine {
         LocalizedLine(self.0.replace("{PLAYER_NAME}", &name.0))
     }
 
+    pub fn insert_ally_name(&self, name: &PlayerName) -> Self {
+        LocalizedLine(self.0.replace("{ALLY_NAME}", &name.0))
+    }
+
+    pub fn insert_turns(&self, turns: usize) -> Self {
+        LocalizedLine(self.0.replace("{TURNS}", &turns.to_string()))
+    }
+
+    pub fn insert_target_name(&self, name: &str) -> Self {
+        LocalizedLine(self.0.replace("{TARGET_NAME}", name))
+    }
+
+    pub fn insert_effect_name(&self, name: &str) -> Self {
+        LocalizedLine(self.0.replace("{EFFECT_NAME}", name))
+    }
+
     pub fn insert_enemy_name(&self, name: &str) -> Self {
         LocalizedLine(self.0.replace("{ENEMY_NAME}", name))
     }
 
-    pub fn insert_bygone_part_name(&self, name: &str) -> Self {
-        LocalizedLine(self.0.replace("{BYGONE03_PART_NAME}", name))
+    pub fn insert_part_name(&self, name: &str) -> Self {
+        LocalizedLine(self.0.replace("{PART_NAME}", name))
+    }
+
+    pub fn insert_player_stats(&self, stats: &PlayerStats) -> Self {
+        LocalizedLine(
+            self.0
+                .replace("{WINS}", &stats.wins.to_string())
+                .replace("{LOSSES}", &stats.losses.to_string())
+                .replace("{HITS}", &stats.hits.to_string())
+                .replace("{MISSES}", &stats.misses.to_string())
+                .replace("{DEATHS}", &stats.deaths.to_string())
+                .replace("{WAVES}", &stats.best_waves.to_string()),
+        )
+    }
+
+    pub fn insert_waves(&self, waves: usize) -> Self {
+        LocalizedLine(self.0.replace("{WAVES}", &waves.to_string()))
+    }
+
+    pub fn insert_wave(&self, wave: usize) -> Self {
+        LocalizedLine(self.0.replace("{WAVE}", &wave.to_string()))
     }
 
     pub fn insert_duration(&self, duration: &Duration) -> Self {
@@ -37,9 +121,9 @@ impl LocalizedLine {
     }
 }
 
-impl Into<String> for &LocalizedLine {
-    fn into(self) -> String {
-        self.0.clone()
+impl From<&LocalizedLine> for String {
+    fn from(line: &LocalizedLine) -> Self {
+        line.0.clone()
     }
 }
 
@@ -57,25 +141,12 @@ impl Display for LocalizedLine {
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Localization {
+    /// Picks the enemy's own lines, those come with the enemy definitions
+    #[serde(default)]
+    pub language: Language,
     pub attack: LocalizedLine,
 
     pub status_title: LocalizedLine,
-    pub core_title: LocalizedLine,
-    pub sensor_title: LocalizedLine,
-    pub gun_title: LocalizedLine,
-    pub left_wing_title: LocalizedLine,
-    pub right_wing_title: LocalizedLine,
-
-    pub core: LocalizedLine,
-    pub sensor: LocalizedLine,
-    pub gun: LocalizedLine,
-    pub left_wing: LocalizedLine,
-    pub right_wing: LocalizedLine,
-
-    pub core_armored: LocalizedLine,
-    pub core_exposed: LocalizedLine,
-    pub core_burning: LocalizedLine,
-    pub core_destroyed: LocalizedLine,
 
     pub turn_progress_title: LocalizedLine,
     pub log_title: LocalizedLine,
@@ -83,16 +154,319 @@ pub struct Localization {
     pub player_miss: Vec<LocalizedLine>,
     pub player_hit: Vec<LocalizedLine>,
     pub player_dead: Vec<LocalizedLine>,
-    pub bygone03_miss: Vec<LocalizedLine>,
-    pub bygone03_hit: Vec<LocalizedLine>,
-    pub bygone03_dead: Vec<LocalizedLine>,
 
-    pub title: LocalizedLine,
     pub lost: LocalizedLine,
     pub won: LocalizedLine,
     pub expired: Vec<LocalizedLine>,
     pub battle_cooldown: LocalizedLine,
     pub other_battle_ongoing: LocalizedLine,
+
+    pub leaderboard_title: LocalizedLine,
+    pub leaderboard_empty: LocalizedLine,
+    pub leaderboard_stats: LocalizedLine,
+
+    /// Saves from before abilities get the English lines
+    #[serde(default)]
+    pub abilities: AbilityLocalization,
+    /// Saves from before classes get the English lines
+    #[serde(default)]
+    pub classes: ClassesLocalization,
+    /// Saves from before status effects get the English lines
+    #[serde(default)]
+    pub effects: EffectsLocalization,
+    /// Saves from before crits get the English lines
+    #[serde(default)]
+    pub crits: CritLocalization,
+    /// Saves from before enemy targeting get the English lines
+    #[serde(default)]
+    pub targeting: TargetingLocalization,
+    /// Saves from before special moves get the English lines
+    #[serde(default)]
+    pub specials: SpecialsLocalization,
+    /// Saves from before survival battles get the English lines
+    #[serde(default)]
+    pub survival: SurvivalLocalization,
+    /// Saves from before guild settings get the English lines
+    #[serde(default)]
+    pub config: ConfigLocalization,
+    /// Saves from before battles could be called off get the English lines
+    #[serde(default)]
+    pub ending: EndingLocalization,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SurvivalLocalization {
+    pub wave_title: LocalizedLine,
+    /// {WAVE} is the number of the wave that begins
+    pub next_wave: Vec<LocalizedLine>,
+    /// The end of a survival battle, {WAVES} is how many of them the players cleared
+    pub summary: LocalizedLine,
+}
+
+impl Default for SurvivalLocalization {
+    fn default() -> Self {
+        Localizations::new().get(Language::En).survival.clone()
+    }
+}
+
+/// The guild's settings as `/config` shows them
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ConfigLocalization {
+    pub title: LocalizedLine,
+    pub language: LocalizedLine,
+    pub difficulty: LocalizedLine,
+    pub cooldown: LocalizedLine,
+    /// {DURATION} is the cooldown in seconds
+    pub cooldown_value: LocalizedLine,
+    pub channels: LocalizedLine,
+    pub any_channel: LocalizedLine,
+    pub enders: LocalizedLine,
+    pub enders_starter: LocalizedLine,
+    pub enders_anyone: LocalizedLine,
+    /// A setting the guild left to the bot
+    pub default_value: LocalizedLine,
+    pub channel_not_allowed: LocalizedLine,
+    pub not_permitted: LocalizedLine,
+}
+
+impl ConfigLocalization {
+    /// Every setting with its value, the frontend says how a channel is shown
+    pub fn settings(
+        &self,
+        config: &GuildConfig,
+        channel_name: impl Fn(Id<ChannelMarker>) -> String,
+    ) -> Vec<(String, String)> {
+        let or_default = |value: Option<String>| value.unwrap_or_else(|| self.default_value.0.clone());
+        let channels = if config.allowed_channels.is_empty() {
+            self.any_channel.0.clone()
+        } else {
+            config
+                .allowed_channels
+                .iter()
+                .map(|channel_id| channel_name(*channel_id))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let enders = match config.enders {
+            GameEnders::Starter => &self.enders_starter,
+            GameEnders::Anyone => &self.enders_anyone,
+        };
+        vec![
+            (self.language.0.clone(), or_default(config.language.map(|language| language.to_string()))),
+            (self.difficulty.0.clone(), or_default(config.difficulty.map(|difficulty| difficulty.to_string()))),
+            (
+                self.cooldown.0.clone(),
+                or_default(config.cooldown_secs.map(|secs| {
+                    self.cooldown_value.insert_duration(&Duration::from_secs(secs)).0
+                })),
+            ),
+            (self.channels.0.clone(), channels),
+            (self.enders.0.clone(), enders.0.clone()),
+        ]
+    }
+}
+
+impl Default for ConfigLocalization {
+    fn default() -> Self {
+        Localizations::new().get(Language::En).config.clone()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EndingLocalization {
+    /// The end of a battle a player called off
+    pub ended: LocalizedLine,
+    /// The answer to the player who called it off
+    pub called_off: LocalizedLine,
+    pub not_allowed: LocalizedLine,
+    pub no_battle: LocalizedLine,
+}
+
+impl Default for EndingLocalization {
+    fn default() -> Self {
+        Localizations::new().get(Language::En).ending.clone()
+    }
+}
+
+/// Lines of the enemies' special moves, {PART_NAME} is the part the move shields or that calls it off
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SpecialsLocalization {
+    pub next_title: LocalizedLine,
+    pub shield_title: LocalizedLine,
+    pub barrage: SpecialLocalization,
+    pub charged_shot: SpecialLocalization,
+    pub shield: SpecialLocalization,
+    pub interrupted: Vec<LocalizedLine>,
+    pub blocked: Vec<LocalizedLine>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SpecialLocalization {
+    pub name: LocalizedLine,
+    /// Announces the move an attack ahead
+    pub telegraph: Vec<LocalizedLine>,
+    pub unleash: Vec<LocalizedLine>,
+}
+
+impl SpecialsLocalization {
+    pub fn get(&self, special: &SpecialMove) -> &SpecialLocalization {
+        match special {
+            SpecialMove::Barrage => &self.barrage,
+            SpecialMove::ChargedShot { .. } => &self.charged_shot,
+            SpecialMove::Shield(_) => &self.shield,
+        }
+    }
+}
+
+impl Default for SpecialsLocalization {
+    fn default() -> Self {
+        Localizations::new().get(Language::En).specials.clone()
+    }
+}
+
+/// Why the enemy went after the player, there are no lines for random picks
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TargetingLocalization {
+    pub lowest_health: Vec<LocalizedLine>,
+    pub threat: Vec<LocalizedLine>,
+    pub spread: Vec<LocalizedLine>,
+    pub healers: Vec<LocalizedLine>,
+}
+
+impl TargetingLocalization {
+    pub fn lines(&self, policy: TargetingPolicy) -> &[LocalizedLine] {
+        match policy {
+            TargetingPolicy::Random => &[],
+            TargetingPolicy::LowestHealth => &self.lowest_health,
+            TargetingPolicy::Threat => &self.threat,
+            TargetingPolicy::Spread => &self.spread,
+            TargetingPolicy::Healers => &self.healers,
+        }
+    }
+}
+
+impl Default for TargetingLocalization {
+    fn default() -> Self {
+        Localizations::new().get(Language::En).targeting.clone()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CritLocalization {
+    pub player_crit: Vec<LocalizedLine>,
+    /// Same for every enemy, unlike their usual hits
+    pub bygone_crit: Vec<LocalizedLine>,
+}
+
+impl Default for CritLocalization {
+    fn default() -> Self {
+        Localizations::new().get(Language::En).crits.clone()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EffectsLocalization {
+    /// Heads the effects in the status
+    pub title: LocalizedLine,
+    pub names: HashMap<StatusEffect, LocalizedLine>,
+    /// {TURNS} is how many turns the effect has left
+    pub turns_left: LocalizedLine,
+
+    pub applied: Vec<LocalizedLine>,
+    pub worn_off: Vec<LocalizedLine>,
+    pub burn: Vec<LocalizedLine>,
+}
+
+impl EffectsLocalization {
+    pub fn name(&self, effect: StatusEffect) -> String {
+        match self.names.get(&effect) {
+            Some(name) => name.0.clone(),
+            None => effect.to_string(),
+        }
+    }
+
+    /// Every effect with its turns left on one line, like `burning (2), stunned (1)`
+    pub fn list(&self, effects: &StatusEffects) -> String {
+        effects
+            .iter()
+            .map(|(effect, turns)| {
+                format!("{} {}", self.name(effect), self.turns_left.insert_turns(turns).0)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl Default for EffectsLocalization {
+    fn default() -> Self {
+        Localizations::new().get(Language::En).effects.clone()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ClassLocalization {
+    pub name: LocalizedLine,
+    /// What the class is good at, shown when picking it
+    pub description: LocalizedLine,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ClassesLocalization {
+    pub menu: LocalizedLine,
+    pub classes: HashMap<PlayerClass, ClassLocalization>,
+}
+
+impl ClassesLocalization {
+    pub fn name(&self, class: PlayerClass) -> String {
+        match self.classes.get(&class) {
+            Some(localization) => localization.name.0.clone(),
+            None => class.to_string(),
+        }
+    }
+
+    pub fn description(&self, class: PlayerClass) -> Option<String> {
+        self.classes
+            .get(&class)
+            .map(|localization| localization.description.0.clone())
+    }
+}
+
+impl Default for ClassesLocalization {
+    fn default() -> Self {
+        Localizations::new().get(Language::En).classes.clone()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AbilityLocalization {
+    pub menu: LocalizedLine,
+    pub heal: LocalizedLine,
+    pub defend: LocalizedLine,
+    pub focus: LocalizedLine,
+    pub revive: LocalizedLine,
+    pub cooldown: LocalizedLine,
+
+    pub player_heal: Vec<LocalizedLine>,
+    pub player_defend: Vec<LocalizedLine>,
+    pub player_focus: Vec<LocalizedLine>,
+    pub player_revive: Vec<LocalizedLine>,
+}
+
+impl AbilityLocalization {
+    pub fn name(&self, ability: Ability) -> &LocalizedLine {
+        match ability {
+            Ability::Heal => &self.heal,
+            Ability::Defend => &self.defend,
+            Ability::FocusedShot => &self.focus,
+            Ability::Revive => &self.revive,
+        }
+    }
+}
+
+impl Default for AbilityLocalization {
+    fn default() -> Self {
+        Localizations::new().get(Language::En).abilities.clone()
+    }
 }
 
 pub trait RenderText {
@@ -105,25 +479,10 @@ pub struct Localizations(HashMap<Language, Localization>);
 impl Localizations {
     pub fn new() -> Self {
         let localization_ru = Localization {
+            language: Language::Ru,
             attack: "АТК".into(),
 
             status_title: "Статус".into(),
-            core_title: ":regional_indicator_c: ядро".into(),
-            sensor_title: ":regional_indicator_s: сенсор".into(),
-            gun_title: ":regional_indicator_g: орудие".into(),
-            left_wing_title: ":regional_indicator_l: левое крыло".into(),
-            right_wing_title: ":regional_indicator_r: правое крыло".into(),
-
-            core: "ядро".into(),
-            sensor: "сенсор".into(),
-            gun: "орудие".into(),
-            left_wing: "левое крыло".into(),
-            right_wing: "правое крыло".into(),
-
-            core_armored: "*защищено бронёй*".into(),
-            core_exposed: "*открыто!*".into(),
-            core_burning: "*ГОРИТ!*".into(),
-            core_destroyed: "*УНИЧТОЖЕНО!*".into(),
 
             turn_progress_title: "Время хода".into(),
             log_title: "Лог битвы".into(),
@@ -138,30 +497,16 @@ impl Localizations {
                 "*{PLAYER_NAME}* отвлекается на силуэт в окне".into(),
             ],
             player_hit: vec![
-                "*{PLAYER_NAME}* попадает в *{BYGONE03_PART_NAME}*".into(),
-                "*{PLAYER_NAME}* крушит *{BYGONE03_PART_NAME}* из своей маленькой катапульты".into(),
-                "*{PLAYER_NAME}* подбивает *{BYGONE03_PART_NAME}*".into(),
+                "*{PLAYER_NAME}* попадает в *{PART_NAME}*".into(),
+                "*{PLAYER_NAME}* крушит *{PART_NAME}* из своей маленькой катапульты".into(),
+                "*{PLAYER_NAME}* подбивает *{PART_NAME}*".into(),
             ],
             player_dead: vec![
                 "*{PLAYER_NAME}* отправляется в отключку".into(),
                 "*{PLAYER_NAME}* идёт решать диалоговый паззл".into(),
                 "*{PLAYER_NAME}* получает разрыв попы массивными резиновыми шарами".into(),
             ],
-            bygone03_miss: vec![
-                "*{ENEMY_NAME}* промахивается".into(),
-                "У *{ENEMY_NAME}* в глазах двоится".into(),
-                "*{ENEMY_NAME}* вспоминает молодость".into(),
-            ],
-            bygone03_hit: vec![
-                "*{ENEMY_NAME}* попадает в *{PLAYER_NAME}*".into(),
-                "*{ENEMY_NAME}* ласкает грудь резиновыми пулями, а *{PLAYER_NAME}* и не против".into(),
-                "*{ENEMY_NAME}* предлагает бесплатный массаж, *{PLAYER_NAME}* спешит записаться".into(),
-            ],
-            bygone03_dead: vec![
-                "Человек торжествует над машиной!".into(),
-            ],
 
-            title: "УНИЧ...ТОЖИТЬ.".into(),
             lost: "*Так темно… Я что, умер? Здесь так спокойно.*".into(),
             won: "*Человек торжествует над машиной!*".into(),
             expired: vec![
@@ -169,29 +514,196 @@ impl Localizations {
                 "*Сердечко. Сердечко. Румяная кошачья мордочка. Сердечко*".into(),
             ],
             battle_cooldown: "*_03 ремонтирует себя, будет готов через {DURATION} сек*".into(),
-            other_battle_ongoing: "*_03 занят: кто-то уже пытается выйти из Ройса!*".into()
+            other_battle_ongoing: "*_03 занят: кто-то уже пытается выйти из Ройса!*".into(),
+
+            leaderboard_title: "Лучшие бойцы".into(),
+            leaderboard_empty: "*Здесь ещё никто не сражался с _03*".into(),
+            leaderboard_stats: "Победы: {WINS} • Поражения: {LOSSES}\nПопадания: {HITS} • Промахи: {MISSES} • Отключки: {DEATHS}\nЛучшее выживание: {WAVES}".into(),
+
+            abilities: AbilityLocalization {
+                menu: "Способности".into(),
+                heal: "Лечение".into(),
+                defend: "Защита".into(),
+                focus: "Прицельный выстрел".into(),
+                revive: "Поднять павшего".into(),
+                cooldown: "Перезарядка: {TURNS} х.".into(),
+
+                player_heal: vec![
+                    "*{PLAYER_NAME}* перевязывает *{ALLY_NAME}*".into(),
+                    "*{PLAYER_NAME}* делится с *{ALLY_NAME}* пластырем".into(),
+                ],
+                player_defend: vec![
+                    "*{PLAYER_NAME}* прячется за перевёрнутой партой".into(),
+                    "*{PLAYER_NAME}* уходит в глухую оборону".into(),
+                ],
+                player_focus: vec![
+                    "*{PLAYER_NAME}* тщательно прицеливается".into(),
+                    "*{PLAYER_NAME}* задерживает дыхание и целится".into(),
+                ],
+                player_revive: vec![
+                    "*{PLAYER_NAME}* приводит *{ALLY_NAME}* в чувство".into(),
+                    "*{PLAYER_NAME}* брызгает водой на *{ALLY_NAME}*, и тот(та) снова в строю".into(),
+                ],
+            },
+            classes: ClassesLocalization {
+                menu: "Вступить в бой как…".into(),
+                classes: HashMap::from([
+                    (
+                        PlayerClass::Fighter,
+                        ClassLocalization {
+                            name: "Боец".into(),
+                            description: "Ничего особенного, зато надёжно".into(),
+                        },
+                    ),
+                    (
+                        PlayerClass::Scout,
+                        ClassLocalization {
+                            name: "Разведчик".into(),
+                            description: "Увёртливый и меткий, но хрупкий".into(),
+                        },
+                    ),
+                    (
+                        PlayerClass::Heavy,
+                        ClassLocalization {
+                            name: "Громила".into(),
+                            description: "Больше здоровья и урона, но неуклюжий".into(),
+                        },
+                    ),
+                    (
+                        PlayerClass::Medic,
+                        ClassLocalization {
+                            name: "Медик".into(),
+                            description: "Лечит лучше всех".into(),
+                        },
+                    ),
+                ]),
+            },
+            effects: EffectsLocalization {
+                title: "Эффекты".into(),
+                names: HashMap::from([
+                    (StatusEffect::Burning, "горит".into()),
+                    (StatusEffect::Stunned, "оглушён".into()),
+                    (StatusEffect::Shielded, "под щитом".into()),
+                    (StatusEffect::Suppressed, "подавлен".into()),
+                ]),
+                turns_left: "({TURNS} х.)".into(),
+
+                applied: vec![
+                    "*{TARGET_NAME}*: {EFFECT_NAME}!".into(),
+                    "*{TARGET_NAME}* теперь {EFFECT_NAME}".into(),
+                ],
+                worn_off: vec![
+                    "*{TARGET_NAME}* больше не {EFFECT_NAME}".into(),
+                ],
+                burn: vec![
+                    "*{TARGET_NAME}* горит!".into(),
+                    "Пламя обжигает *{TARGET_NAME}*".into(),
+                ],
+            },
+            crits: CritLocalization {
+                player_crit: vec![
+                    "*{PLAYER_NAME}* попадает точно в уязвимое место: *{PART_NAME}*!".into(),
+                    "КРИТ! *{PLAYER_NAME}* разносит *{PART_NAME}*".into(),
+                ],
+                bygone_crit: vec![
+                    "*{ENEMY_NAME}* находит брешь в обороне *{PLAYER_NAME}*!".into(),
+                    "*{ENEMY_NAME}* бьёт *{PLAYER_NAME}* в самое больное место".into(),
+                ],
+            },
+            targeting: TargetingLocalization {
+                lowest_health: vec![
+                    "*{ENEMY_NAME}* чует слабость и берёт на прицел *{PLAYER_NAME}*".into(),
+                    "*{ENEMY_NAME}* решает добить *{PLAYER_NAME}*".into(),
+                ],
+                threat: vec![
+                    "*{ENEMY_NAME}* разворачивается к *{PLAYER_NAME}*: слишком уж больно бьёт".into(),
+                    "*{ENEMY_NAME}* запоминает, кто его ранил, и целится в *{PLAYER_NAME}*".into(),
+                ],
+                spread: vec![
+                    "*{ENEMY_NAME}* переключается на *{PLAYER_NAME}*".into(),
+                    "*{ENEMY_NAME}* водит стволом и останавливается на *{PLAYER_NAME}*".into(),
+                ],
+                healers: vec![
+                    "*{ENEMY_NAME}* охотится за медиком: *{PLAYER_NAME}*, берегись!".into(),
+                    "*{ENEMY_NAME}* замечает аптечку у *{PLAYER_NAME}*".into(),
+                ],
+            },
+            specials: SpecialsLocalization {
+                next_title: "Готовит".into(),
+                shield_title: "Под щитом".into(),
+                barrage: SpecialLocalization {
+                    name: "шквальный огонь".into(),
+                    telegraph: vec![
+                        "*{ENEMY_NAME}* раскручивает все стволы разом...".into(),
+                        "*{ENEMY_NAME}* наводится на всех сразу, готовьтесь!".into(),
+                    ],
+                    unleash: vec![
+                        "*{ENEMY_NAME}* поливает огнём всю комнату!".into(),
+                    ],
+                },
+                charged_shot: SpecialLocalization {
+                    name: "заряженный выстрел".into(),
+                    telegraph: vec![
+                        "*{ENEMY_NAME}* начинает заряжаться, бейте в *{PART_NAME}*, чтобы сбить заряд!".into(),
+                        "Гул нарастает: *{ENEMY_NAME}* копит заряд. *{PART_NAME}* ещё можно успеть подбить".into(),
+                    ],
+                    unleash: vec![
+                        "*{ENEMY_NAME}* выпускает накопленный заряд!".into(),
+                    ],
+                },
+                shield: SpecialLocalization {
+                    name: "щит".into(),
+                    telegraph: vec![
+                        "*{ENEMY_NAME}* прикрывает *{PART_NAME}* бронепластинами".into(),
+                    ],
+                    unleash: vec![
+                        "Щит закрывает *{PART_NAME}*, *{ENEMY_NAME}* неуязвим до следующей атаки".into(),
+                    ],
+                },
+                interrupted: vec![
+                    "*{PLAYER_NAME}* сбивает заряд, *{ENEMY_NAME}* теряет концентрацию!".into(),
+                    "Точное попадание *{PLAYER_NAME}*: заряд *{ENEMY_NAME}* рассеивается".into(),
+                ],
+                blocked: vec![
+                    "Выстрел *{PLAYER_NAME}* отскакивает от щита: *{PART_NAME}* цела".into(),
+                ],
+            },
+            survival: SurvivalLocalization {
+                wave_title: "Волна".into(),
+                next_wave: vec![
+                    "Волна {WAVE}! На смену приходит новый *{ENEMY_NAME}*, крепче прежнего".into(),
+                    "Волна {WAVE}: *{ENEMY_NAME}* снова здесь, и он зол".into(),
+                ],
+                summary: "Выживание окончено. Пройдено волн: {WAVES}".into(),
+            },
+            config: ConfigLocalization {
+                title: "Настройки битв".into(),
+                language: "Язык по умолчанию".into(),
+                difficulty: "Сложность по умолчанию".into(),
+                cooldown: "Перерыв между битвами".into(),
+                cooldown_value: "{DURATION} с".into(),
+                channels: "Каналы для битв".into(),
+                any_channel: "Любые".into(),
+                enders: "Кто может закончить битву".into(),
+                enders_starter: "Тот, кто её начал".into(),
+                enders_anyone: "Кто угодно".into(),
+                default_value: "Как у бота".into(),
+                channel_not_allowed: "В этом канале битвы не проводятся, загляни в один из каналов для битв".into(),
+                not_permitted: "Настройки битв меняют только те, кто может управлять сервером".into(),
+            },
+            ending: EndingLocalization {
+                ended: "Битва отменена, _03 уходит ни с чем".into(),
+                called_off: "Битва отменена".into(),
+                not_allowed: "Закончить битву может только тот, кто её начал".into(),
+                no_battle: "Здесь нет битвы, которую можно закончить".into(),
+            },
         };
 
         let localization_en = Localization {
+            language: Language::En,
             attack: "ATK".into(),
 
             status_title: "Status".into(),
-            core_title: ":regional_indicator_c:ore".into(),
-            sensor_title: ":regional_indicator_s:ensor".into(),
-            gun_title: ":regional_indicator_g:un".into(),
-            left_wing_title: ":regional_indicator_l:eft wing".into(),
-            right_wing_title: ":regional_indicator_r:ight wing".into(),
-
-            core: "core".into(),
-            sensor: "sensor".into(),
-            gun: "gun".into(),
-            left_wing: "left wing".into(),
-            right_wing: "right wing".into(),
-
-            core_armored: "*armored*".into(),
-            core_exposed: "*exposed!*".into(),
-            core_burning: "*BURNING!*".into(),
-            core_destroyed: "*DESTROYED!*".into(),
 
             turn_progress_title: "Turn timer".into(),
             log_title: "Battle log".into(),
@@ -206,30 +718,16 @@ impl Localizations {
                 "*{PLAYER_NAME}* gets distracted by a silhouette in the window".into(),
             ],
             player_hit: vec![
-                "*{PLAYER_NAME}* hits the *{BYGONE03_PART_NAME}*".into(),
-                "*{PLAYER_NAME}* smashes the *{BYGONE03_PART_NAME}* with their small catapult".into(),
-                "*{PLAYER_NAME}* damages the *{BYGONE03_PART_NAME}*".into(),
+                "*{PLAYER_NAME}* hits the *{PART_NAME}*".into(),
+                "*{PLAYER_NAME}* smashes the *{PART_NAME}* with their small catapult".into(),
+                "*{PLAYER_NAME}* damages the *{PART_NAME}*".into(),
             ],
             player_dead: vec![
                 "*{PLAYER_NAME}* is knocked out".into(),
                 "*{PLAYER_NAME}* proceeds to solve the dialogue puzzle".into(),
                 "*{PLAYER_NAME}* gets their tushy ruined by the massive rubber balls".into(),
             ],
-            bygone03_miss: vec![
-                "*{ENEMY_NAME}* misses".into(),
-                "*{ENEMY_NAME}* is seeing double".into(),
-                "*{ENEMY_NAME}* is reminiscing the old days".into(),
-            ],
-            bygone03_hit: vec![
-                "*{ENEMY_NAME}* hits *{PLAYER_NAME}*".into(),
-                "*{ENEMY_NAME}* gently punches *{PLAYER_NAME}* in the chest with a rubber bullet".into(),
-                "*{ENEMY_NAME}* offers *{PLAYER_NAME}* a free massage".into(),
-            ],
-            bygone03_dead: vec![
-                "Man triumphs over machine!".into(),
-            ],
 
-            title: "DES...TROY.".into(),
             lost: "*This darkness… Am I… dead? It’s so peaceful.*".into(),
             won: "*Man triumphs over machine!*".into(),
             expired: vec![
@@ -237,7 +735,189 @@ impl Localizations {
                 "*Heart. Heart. Blushing cat face. Heart.*".into(),
             ],
             battle_cooldown: "*_03 is repairing itself, it will be ready in {DURATION}s*".into(),
-            other_battle_ongoing: "*_03 is busy: somebody is already trying to leave Royce!*".into()
+            other_battle_ongoing: "*_03 is busy: somebody is already trying to leave Royce!*".into(),
+
+            leaderboard_title: "Top fighters".into(),
+            leaderboard_empty: "*Nobody has fought _03 here yet*".into(),
+            leaderboard_stats: "Wins: {WINS} • Losses: {LOSSES}\nHits: {HITS} • Misses: {MISSES} • Knockouts: {DEATHS}\nBest survival: {WAVES}".into(),
+
+            abilities: AbilityLocalization {
+                menu: "Abilities".into(),
+                heal: "Heal".into(),
+                defend: "Defend".into(),
+                focus: "Focused shot".into(),
+                revive: "Revive".into(),
+                cooldown: "Cooldown: {TURNS} turns".into(),
+
+                player_heal: vec![
+                    "*{PLAYER_NAME}* patches up *{ALLY_NAME}*".into(),
+                    "*{PLAYER_NAME}* shares a band-aid with *{ALLY_NAME}*".into(),
+                ],
+                player_defend: vec![
+                    "*{PLAYER_NAME}* hides behind a flipped desk".into(),
+                    "*{PLAYER_NAME}* braces for impact".into(),
+                ],
+                player_focus: vec![
+                    "*{PLAYER_NAME}* takes careful aim".into(),
+                    "*{PLAYER_NAME}* holds their breath and aims".into(),
+                ],
+                player_revive: vec![
+                    "*{PLAYER_NAME}* brings *{ALLY_NAME}* back to their senses".into(),
+                    "*{PLAYER_NAME}* splashes water on *{ALLY_NAME}*, they are back in the fight".into(),
+                ],
+            },
+            classes: ClassesLocalization {
+                menu: "Join the fight as…".into(),
+                classes: HashMap::from([
+                    (
+                        PlayerClass::Fighter,
+                        ClassLocalization {
+                            name: "Fighter".into(),
+                            description: "Nothing special, but reliable".into(),
+                        },
+                    ),
+                    (
+                        PlayerClass::Scout,
+                        ClassLocalization {
+                            name: "Scout".into(),
+                            description: "Evasive and accurate, but fragile".into(),
+                        },
+                    ),
+                    (
+                        PlayerClass::Heavy,
+                        ClassLocalization {
+                            name: "Heavy".into(),
+                            description: "More health and damage, but clumsy".into(),
+                        },
+                    ),
+                    (
+                        PlayerClass::Medic,
+                        ClassLocalization {
+                            name: "Medic".into(),
+                            description: "Heals better than anyone".into(),
+                        },
+                    ),
+                ]),
+            },
+            effects: EffectsLocalization {
+                title: "Effects".into(),
+                names: HashMap::from([
+                    (StatusEffect::Burning, "burning".into()),
+                    (StatusEffect::Stunned, "stunned".into()),
+                    (StatusEffect::Shielded, "shielded".into()),
+                    (StatusEffect::Suppressed, "suppressed".into()),
+                ]),
+                turns_left: "({TURNS})".into(),
+
+                applied: vec![
+                    "*{TARGET_NAME}* is {EFFECT_NAME}!".into(),
+                    "*{TARGET_NAME}* is now {EFFECT_NAME}".into(),
+                ],
+                worn_off: vec![
+                    "*{TARGET_NAME}* is no longer {EFFECT_NAME}".into(),
+                ],
+                burn: vec![
+                    "*{TARGET_NAME}* burns!".into(),
+                    "Flames lick at *{TARGET_NAME}*".into(),
+                ],
+            },
+            crits: CritLocalization {
+                player_crit: vec![
+                    "*{PLAYER_NAME}* hits the *{PART_NAME}* right in its weak spot!".into(),
+                    "CRITICAL! *{PLAYER_NAME}* wrecks the *{PART_NAME}*".into(),
+                ],
+                bygone_crit: vec![
+                    "*{ENEMY_NAME}* finds a gap in *{PLAYER_NAME}*'s guard!".into(),
+                    "*{ENEMY_NAME}* hits *{PLAYER_NAME}* where it hurts the most".into(),
+                ],
+            },
+            targeting: TargetingLocalization {
+                lowest_health: vec![
+                    "*{ENEMY_NAME}* smells weakness and locks onto *{PLAYER_NAME}*".into(),
+                    "*{ENEMY_NAME}* moves in to finish *{PLAYER_NAME}* off".into(),
+                ],
+                threat: vec![
+                    "*{ENEMY_NAME}* turns on *{PLAYER_NAME}*, who hurt it the most".into(),
+                    "*{ENEMY_NAME}* remembers who shot it and locks onto *{PLAYER_NAME}*".into(),
+                ],
+                spread: vec![
+                    "*{ENEMY_NAME}* switches its aim to *{PLAYER_NAME}*".into(),
+                    "*{ENEMY_NAME}* sweeps the room and stops on *{PLAYER_NAME}*".into(),
+                ],
+                healers: vec![
+                    "*{ENEMY_NAME}* hunts the medic: watch out, *{PLAYER_NAME}*!".into(),
+                    "*{ENEMY_NAME}* spots the first aid kit on *{PLAYER_NAME}*".into(),
+                ],
+            },
+            specials: SpecialsLocalization {
+                next_title: "Next".into(),
+                shield_title: "Shielded".into(),
+                barrage: SpecialLocalization {
+                    name: "barrage".into(),
+                    telegraph: vec![
+                        "*{ENEMY_NAME}* spins up every barrel it has...".into(),
+                        "*{ENEMY_NAME}* takes aim at everyone at once, brace yourselves!".into(),
+                    ],
+                    unleash: vec![
+                        "*{ENEMY_NAME}* sprays the whole room with fire!".into(),
+                    ],
+                },
+                charged_shot: SpecialLocalization {
+                    name: "charged shot".into(),
+                    telegraph: vec![
+                        "*{ENEMY_NAME}* starts charging up, hit the *{PART_NAME}* to stop it!".into(),
+                        "A hum grows louder as *{ENEMY_NAME}* charges. There's still time to hit the *{PART_NAME}*".into(),
+                    ],
+                    unleash: vec![
+                        "*{ENEMY_NAME}* lets the charge loose!".into(),
+                    ],
+                },
+                shield: SpecialLocalization {
+                    name: "shield".into(),
+                    telegraph: vec![
+                        "*{ENEMY_NAME}* moves armor plates over its *{PART_NAME}*".into(),
+                    ],
+                    unleash: vec![
+                        "A shield closes over the *{PART_NAME}*, *{ENEMY_NAME}* won't feel a thing until it attacks again".into(),
+                    ],
+                },
+                interrupted: vec![
+                    "*{PLAYER_NAME}* knocks the charge out of *{ENEMY_NAME}*!".into(),
+                    "A clean hit by *{PLAYER_NAME}* and the charge of *{ENEMY_NAME}* fizzles out".into(),
+                ],
+                blocked: vec![
+                    "The shot of *{PLAYER_NAME}* bounces off the shield, the *{PART_NAME}* is fine".into(),
+                ],
+            },
+            survival: SurvivalLocalization {
+                wave_title: "Wave".into(),
+                next_wave: vec![
+                    "Wave {WAVE}! Another *{ENEMY_NAME}* rolls in, tougher than the last".into(),
+                    "Wave {WAVE}: *{ENEMY_NAME}* is back, and it's angry".into(),
+                ],
+                summary: "Survival is over. Waves cleared: {WAVES}".into(),
+            },
+            config: ConfigLocalization {
+                title: "Battle settings".into(),
+                language: "Default language".into(),
+                difficulty: "Default difficulty".into(),
+                cooldown: "Cooldown between battles".into(),
+                cooldown_value: "{DURATION} s".into(),
+                channels: "Battle channels".into(),
+                any_channel: "Any".into(),
+                enders: "Who can end a battle".into(),
+                enders_starter: "The one who started it".into(),
+                enders_anyone: "Anyone".into(),
+                default_value: "Bot default".into(),
+                channel_not_allowed: "Battles don't happen in this channel, try one of the battle channels".into(),
+                not_permitted: "Only members who can manage the server can change the battle settings".into(),
+            },
+            ending: EndingLocalization {
+                ended: "The battle was called off, _03 leaves empty-handed".into(),
+                called_off: "You called off the battle".into(),
+                not_allowed: "Only the one who started the battle can end it".into(),
+                no_battle: "There is no battle to end here".into(),
+            },
         };
 
         let mut localizations = HashMap::with_capacity(2);
@@ -247,9 +927,10 @@ impl Localizations {
         Localizations(localizations)
     }
 
+    /// Languages without lines of their own get the English ones
     pub fn get(&self, language: Language) -> &Localization {
         self.0
             .get(&language)
-            .unwrap_or(self.0.get(&Language::En).unwrap())
+            .unwrap_or_else(|| &self.0[&Language::En])
     }
 }
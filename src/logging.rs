@@ -1,5 +1,19 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use chrono::Utc;
 
+/// Whether the battles print every roll they make
+static BATTLE_TRACES: AtomicBool = AtomicBool::new(true);
+
 pub fn format_time() -> String {
     Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
 }
+
+/// Headless runs print their report on stdout, thousands of rolls would bury it
+pub fn disable_battle_traces() {
+    BATTLE_TRACES.store(false, Ordering::Relaxed);
+}
+
+pub fn battle_traces() -> bool {
+    BATTLE_TRACES.load(Ordering::Relaxed)
+}
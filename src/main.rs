@@ -6,100 +6,255 @@ mod controller;
 mod dice;
 mod discord_client;
 mod discord_renderer;
+mod enemies;
 mod events;
 mod game_helpers;
+mod guild_config;
 mod io;
+mod local_client;
 mod localization;
 mod logging;
+mod replay;
+mod scoreboard;
+mod simulation;
+mod snapshot;
 mod systems;
+#[cfg(test)]
+mod test_support;
+mod text_renderer;
+mod timings;
+mod transport;
 
-use std::{collections::HashMap, env, error::Error, sync::Mutex, time::Duration};
+use std::{collections::HashMap, env, error::Error, sync::Mutex};
 
 use bevy_turborand::RngPlugin;
 use clap::Parser;
+use crossbeam_channel::{unbounded, Receiver, Sender};
 
 use discord_client::DiscordClient;
 
-use events::EventsPlugin;
+use events::{EventsPlugin, GameRenderEvent, InputEvent};
 
-use game_helpers::{EventDelay, Game};
+use game_helpers::{ArenaId, Clock, Game, GameScope};
 
-use crate::cli::Cli;
+use crate::cli::{Cli, Command};
+use crate::enemies::EnemyDefinitions;
+use crate::guild_config::{save_guild_configs, GuildConfigs};
+use crate::io::{append_json_lines_from_channel, read_json, write_json_from_channel};
+use crate::local_client::LocalClient;
+use crate::localization::Localizations;
+use crate::logging::format_time;
+use crate::replay::{record_replay, replay, ReplayRecord};
+use crate::scoreboard::{save_scoreboard, update_scoreboard, Scoreboard};
+use crate::simulation::simulate;
+use crate::snapshot::{restore_games, save_games, GamesSnapshot};
 use crate::systems::*;
+use crate::timings::TimingConfig;
+use crate::transport::{ChatTransport, TransportChannels};
 
 use bevy::{app::ScheduleRunnerSettings, prelude::*};
 
-use twilight_model::id::{marker::GuildMarker, Id};
+/// The battles with everything they need except a chat frontend, persistence and a runner.
+/// `main` runs it on a timer, tests step it by hand with a manual clock and a fixed seed.
+#[allow(clippy::too_many_arguments)]
+fn game_app(
+    input_receiver: Receiver<InputEvent>,
+    output_sender: Sender<GameRenderEvent>,
+    scoreboard: Scoreboard,
+    clock: Clock,
+    scope: GameScope,
+    enemies: EnemyDefinitions,
+    timings: TimingConfig,
+    seed: Option<u64>,
+) -> App {
+    let mut app = App::new();
+    app.insert_resource(timings)
+        .insert_resource(clock)
+        .insert_resource(scope)
+        .insert_resource(enemies)
+        .insert_resource(Localizations::new())
+        .insert_resource(GuildConfigs::default())
+        .insert_resource(HashMap::<ArenaId, Game>::new())
+        .insert_resource(scoreboard)
+        .insert_resource(HashMap::<ArenaId, Vec<String>>::new())
+        .add_plugin(RngPlugin::new(seed))
+        .add_plugin(EventsPlugin)
+        .add_plugin(BattlePlugin)
+        .add_system(
+            listen(
+                Mutex::new(input_receiver),
+                Mutex::new(output_sender.clone()),
+            )
+            .label(BattleLabel::Input)
+            .label(BattleLabel::Listen)
+            .before(BattleLabel::Render),
+        )
+        .add_system(
+            delay_events
+                .label(BattleLabel::Input)
+                .label(BattleLabel::DelayEvents)
+                .after(BattleLabel::Listen)
+                .before(BattleLabel::Render),
+        )
+        .add_system(
+            turn_timer
+                .label(BattleLabel::Input)
+                .after(BattleLabel::DelayEvents)
+                .before(BattleLabel::Render),
+        )
+        .add_system(
+            strict_turns
+                .label(BattleLabel::Input)
+                .after(BattleLabel::DelayEvents)
+                .before(BattleLabel::Render),
+        )
+        // Turn ends draw right away, their burns have to be in the log by then
+        .add_system(
+            log_battle
+                .after(BattleLabel::StatusEffects)
+                .before(BattleLabel::Render),
+        )
+        .add_system(update_scoreboard)
+        .add_system(render(Mutex::new(output_sender)).label(BattleLabel::Render))
+        .add_system(cleanup);
+    app
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
-    let token = env::var("DISCORD_TOKEN")?;
-    let (client, shard) = DiscordClient::new(token);
-    let (input_receiver, interaction_receiver) = client.listen_discord(shard).await?;
-    let output_sender = client.listen_game(interaction_receiver).await;
-
     let cli = Cli::parse();
-    let games = HashMap::<Id<GuildMarker>, Game>::new();
-    // read_json::<HashMap<Id<GuildMarker>, Game>>(&cli.games_path);
-    // let scoreboard = read_json::<HashMap::<Id<GuildMarker>, HashMap<Id<UserMarker>, usize>>>(&cli.scoreboard_path);
-    // let (games_sender, games_receiver) = unbounded::<HashMap<Id<GuildMarker>, Game>>();
-    // let games_path = cli.games_path.clone();
-
-    if cli.update_commands {
-        client.register_commands()
+
+    match &cli.command {
+        Some(Command::Simulate(args)) => {
+            simulate(
+                args,
+                EnemyDefinitions::load(&cli.enemies_path),
+                TimingConfig::load(&cli.timings_path, &cli.timings),
+            );
+            return Ok(());
+        }
+        Some(Command::Replay(args)) => {
+            replay(args, EnemyDefinitions::load(&cli.enemies_path));
+            return Ok(());
+        }
+        None => {}
     }
 
-    // tokio::spawn(async move {
-    //     loop {
-    //         if let Err(err) = write_json_from_channel(&games_receiver, &games_path) {
-    //             println!("{} - main - FAILED to save games: {}", format_time(), err);
-    //         }
-    //     }
-    // });
-
-    // let (scoreboard_sender, scoreboard_receiver) = unbounded::<HashMap::<Id<GuildMarker>, HashMap<Id<UserMarker>, usize>>>();
-    // let scoreboard_path = cli.scoreboard_path.clone();
-
-    // tokio::spawn(async move {
-    //     loop {
-    //         write_json_from_channel(&scoreboard_receiver, &scoreboard_path);
-    //     }
-    // });
-
-    let render_label = "render";
-
-    App::new()
-        .insert_resource(ScheduleRunnerSettings::run_loop(Duration::from_millis(100)))
-        .insert_resource(EventDelay(Duration::from_millis(500)))
-        .insert_resource(games)
-        // .insert_resource(scoreboard)
-        .insert_resource(HashMap::<Id<GuildMarker>, Vec<String>>::new())
+    let enemies = EnemyDefinitions::load(&cli.enemies_path);
+    let timings = TimingConfig::load(&cli.timings_path, &cli.timings);
+    let frame = timings.base.frame();
+    let transport: Box<dyn ChatTransport> = if cli.local {
+        Box::new(LocalClient::new(cli.local_players))
+    } else {
+        let token = env::var("DISCORD_TOKEN")?;
+        let client = DiscordClient::new(token, cli.messages_path.clone());
+        if cli.update_commands {
+            client.register_commands(&enemies)
+        }
+        Box::new(client)
+    };
+    let TransportChannels {
+        inputs: input_receiver,
+        renders: output_sender,
+    } = transport.connect()?;
+
+    let games_snapshot = match &cli.games_path {
+        Some(games_path) => read_json::<GamesSnapshot>(games_path),
+        None => GamesSnapshot::default(),
+    };
+    let scoreboard = match &cli.scoreboard_path {
+        Some(scoreboard_path) => read_json::<Scoreboard>(scoreboard_path),
+        None => Scoreboard::default(),
+    };
+    let guild_configs = match &cli.config_path {
+        Some(config_path) => read_json::<GuildConfigs>(config_path),
+        None => GuildConfigs::default(),
+    };
+    let (games_sender, games_receiver) = unbounded::<GamesSnapshot>();
+    let (scoreboard_sender, scoreboard_receiver) = unbounded::<Scoreboard>();
+    let (guild_configs_sender, guild_configs_receiver) = unbounded::<GuildConfigs>();
+    let (replay_sender, replay_receiver) = unbounded::<ReplayRecord>();
+
+    if let Some(games_path) = cli.games_path.clone() {
+        tokio::task::spawn_blocking(move || {
+            while let Ok(saved) = write_json_from_channel(&games_receiver, &games_path) {
+                if let Err(err) = saved {
+                    println!("{} - main - FAILED to save games: {}", format_time(), err);
+                }
+            }
+        });
+    }
+
+    if let Some(scoreboard_path) = cli.scoreboard_path.clone() {
+        tokio::task::spawn_blocking(move || {
+            while let Ok(saved) = write_json_from_channel(&scoreboard_receiver, &scoreboard_path) {
+                if let Err(err) = saved {
+                    println!(
+                        "{} - main - FAILED to save scoreboard: {}",
+                        format_time(),
+                        err
+                    );
+                }
+            }
+        });
+    }
+
+    if let Some(config_path) = cli.config_path.clone() {
+        tokio::task::spawn_blocking(move || {
+            while let Ok(saved) = write_json_from_channel(&guild_configs_receiver, &config_path) {
+                if let Err(err) = saved {
+                    println!(
+                        "{} - main - FAILED to save guild configs: {}",
+                        format_time(),
+                        err
+                    );
+                }
+            }
+        });
+    }
+
+    if let Some(replay_path) = cli.replay_path.clone() {
+        tokio::task::spawn_blocking(move || {
+            while let Ok(saved) = append_json_lines_from_channel(&replay_receiver, &replay_path) {
+                if let Err(err) = saved {
+                    println!(
+                        "{} - main - FAILED to save replay log: {}",
+                        format_time(),
+                        err
+                    );
+                }
+            }
+        });
+    }
+
+    let mut app = game_app(
+        input_receiver,
+        output_sender,
+        scoreboard,
+        Clock::Real,
+        cli.game_scope,
+        enemies,
+        timings,
+        None,
+    );
+    app.insert_resource(ScheduleRunnerSettings::run_loop(frame))
+        .insert_resource(games_snapshot)
+        .insert_resource(guild_configs)
         .add_plugins(MinimalPlugins)
-        .add_plugin(RngPlugin::default())
-        .add_plugin(EventsPlugin::default())
-        .add_system(
-            listen(
-                Mutex::new(input_receiver),
-                Mutex::new(output_sender.clone()),
-            )
-            .before(render_label),
-        )
-        .add_system(delay_events.before(render_label))
-        .add_system(turn_timer.before(render_label))
-        .add_system(spawn_bygones.before(render_label))
-        .add_system(spawn_players.before(render_label))
-        .add_system(damage_bygone.before(render_label))
-        .add_system(damage_players.before(render_label))
-        .add_system(process_bygone_part_death.before(render_label))
-        .add_system(deactivate.before(render_label))
-        .add_system(update_game_status.before(render_label))
-        .add_system(log_battle.before(render_label))
-        .add_system(render(Mutex::new(output_sender)).label(render_label))
-        .add_system(ready_players)
-        .add_system(cleanup)
-        // .add_system(save_games(Mutex::new(games_sender)))
-        // .add_system(save_scoreboard(Mutex::new(scoreboard_sender)))
-        .run();
+        .add_startup_system(restore_games);
+    if cli.games_path.is_some() {
+        app.add_system(save_games(Mutex::new(games_sender)));
+    }
+    if cli.replay_path.is_some() {
+        app.add_system(record_replay(Mutex::new(replay_sender)).after(BattleLabel::Input));
+    }
+    if cli.scoreboard_path.is_some() {
+        app.add_system(save_scoreboard(Mutex::new(scoreboard_sender)));
+    }
+    if cli.config_path.is_some() {
+        app.add_system(save_guild_configs(Mutex::new(guild_configs_sender)));
+    }
+    app.run();
 
     Ok(())
 }
@@ -0,0 +1,534 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    sync::Mutex,
+};
+
+use bevy::prelude::*;
+use crossbeam_channel::Sender;
+use serde::{Deserialize, Serialize};
+use twilight_model::id::{marker::UserMarker, Id};
+
+use crate::{
+    bundles::BygoneParts,
+    cli::ReplayArgs,
+    components::{
+        Ability, BygonePart, EnemySlot, GameId, Player, PlayerClass, PlayerName, Ready,
+        UserIdComponent,
+    },
+    enemies::{default_enemy, EnemyDefinitions},
+    events::*,
+    game_helpers::{ArenaId, Clock, Difficulty, Game, GameMode, TurnMode},
+    localization::{Language, Localizations},
+    logging::{disable_battle_traces, format_time},
+    systems::BattlePlugin,
+};
+
+/// Frames to run after the last recorded frame, enough for every chain of events in the systems to resolve
+const SETTLE_FRAMES: u64 = 3;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum ReplayEvent {
+    GameStart {
+        arena: ArenaId,
+        difficulty: Difficulty,
+        #[serde(default = "default_enemy")]
+        enemy: String,
+        seed: u64,
+        #[serde(default)]
+        mode: GameMode,
+        #[serde(default)]
+        turns: TurnMode,
+    },
+    PlayerJoin {
+        player: Id<UserMarker>,
+        player_name: PlayerName,
+        #[serde(default)]
+        class: PlayerClass,
+    },
+    PlayerAttack {
+        player: Id<UserMarker>,
+        player_name: PlayerName,
+        #[serde(default)]
+        enemy: EnemySlot,
+        target: BygonePart,
+    },
+    PlayerAbility {
+        player: Id<UserMarker>,
+        player_name: PlayerName,
+        ability: Ability,
+        target: Option<(EnemySlot, BygonePart)>,
+    },
+    EnemyAttack,
+    TurnEnd,
+    /// Not fed back into the game, the replayed battle log is checked against it
+    BattleLog(BattleLogEvent),
+}
+
+/// A line of the replay log, `frame` is the app update the event was sent on
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReplayRecord {
+    pub game_id: GameId,
+    pub frame: u64,
+    pub turn: usize,
+    pub event: ReplayEvent,
+}
+
+#[allow(clippy::type_complexity)]
+pub fn record_replay(
+    sender: Mutex<Sender<ReplayRecord>>,
+) -> impl FnMut(
+    Local<u64>,
+    Local<HashMap<GameId, usize>>,
+    Res<HashMap<ArenaId, Game>>,
+    EventReader<BygoneSpawnEvent>,
+    EventReader<PlayerJoinEvent>,
+    EventReader<(GameId, PlayerAttackEvent)>,
+    EventReader<(GameId, PlayerAbilityEvent)>,
+    EventReader<EnemyAttackEvent>,
+    EventReader<TurnEndEvent>,
+    EventReader<(ArenaId, BattleLogEvent)>,
+) {
+    move |mut frame,
+          mut turns,
+          games,
+          mut ev_bygone_spawn,
+          mut ev_player_join,
+          mut ev_player_attack,
+          mut ev_player_ability,
+          mut ev_enemy_attack,
+          mut ev_turn_end,
+          mut ev_battle_log| {
+        *frame += 1;
+        turns.retain(|game_id, _| games.values().any(|game| game.id == *game_id));
+
+        let mut records = Vec::new();
+        // Later waves of a survival battle spawn by themselves when the battle is replayed
+        for ev in ev_bygone_spawn.iter().filter(|ev| ev.wave == 0) {
+            if let Some((arena, game)) = games.iter().find(|(_, game)| game.id == ev.game_id) {
+                turns.insert(ev.game_id, 0);
+                records.push((
+                    ev.game_id,
+                    ReplayEvent::GameStart {
+                        arena: *arena,
+                        difficulty: ev.difficulty,
+                        enemy: ev.enemy.clone(),
+                        seed: game.seed,
+                        mode: game.mode(),
+                        turns: game.turns,
+                    },
+                ));
+            }
+        }
+        for ev in ev_player_join.iter() {
+            records.push((
+                ev.game_id,
+                ReplayEvent::PlayerJoin {
+                    player: ev.player,
+                    player_name: ev.player_name.clone(),
+                    class: ev.class,
+                },
+            ));
+        }
+        for (game_id, ev) in ev_player_attack.iter() {
+            records.push((
+                *game_id,
+                ReplayEvent::PlayerAttack {
+                    player: ev.player,
+                    player_name: ev.player_name.clone(),
+                    enemy: ev.enemy,
+                    target: ev.target.clone(),
+                },
+            ));
+        }
+        for (game_id, ev) in ev_player_ability.iter() {
+            records.push((
+                *game_id,
+                ReplayEvent::PlayerAbility {
+                    player: ev.player,
+                    player_name: ev.player_name.clone(),
+                    ability: ev.ability,
+                    target: ev.target.clone(),
+                },
+            ));
+        }
+        for ev in ev_enemy_attack.iter() {
+            records.push((ev.game_id, ReplayEvent::EnemyAttack));
+        }
+        for ev in ev_turn_end.iter() {
+            records.push((ev.game_id, ReplayEvent::TurnEnd));
+        }
+        for (arena, ev) in ev_battle_log.iter() {
+            if let Some(game) = games.get(arena) {
+                records.push((game.id, ReplayEvent::BattleLog(ev.clone())));
+            }
+        }
+
+        if let Ok(ref mut sender_lock) = sender.lock() {
+            for (game_id, event) in records {
+                let turn = turns.get(&game_id).copied().unwrap_or_default();
+                if matches!(event, ReplayEvent::TurnEnd) {
+                    turns.insert(game_id, turn + 1);
+                }
+                if let Err(err) = sender_lock.send(ReplayRecord {
+                    game_id,
+                    frame: *frame,
+                    turn,
+                    event,
+                }) {
+                    println!(
+                        "{} - replay - FAILED to send replay record: {}",
+                        format_time(),
+                        err
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Battle log events the replayed game produced, events themselves only live for two frames
+#[derive(Default)]
+struct ReplayedBattleLog(Vec<BattleLogEvent>);
+
+fn collect_battle_log(
+    mut ev_battle_log: EventReader<(ArenaId, BattleLogEvent)>,
+    mut replayed: ResMut<ReplayedBattleLog>,
+) {
+    for (_, ev) in ev_battle_log.iter() {
+        replayed.0.push(ev.clone());
+    }
+}
+
+fn read_records(args: &ReplayArgs) -> Vec<ReplayRecord> {
+    let file = match File::open(&args.path) {
+        Ok(file) => file,
+        Err(err) => {
+            println!(
+                "{} - replay - FAILED to read {}: {}",
+                format_time(),
+                args.path.display(),
+                err
+            );
+            return Vec::new();
+        }
+    };
+    let mut records = Vec::new();
+    for (idx, line) in BufReader::new(file).lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        match serde_json::from_str::<ReplayRecord>(&line) {
+            Ok(record) => records.push(record),
+            Err(err) => println!(
+                "{} - replay - FAILED to parse line {}: {}",
+                format_time(),
+                idx + 1,
+                err
+            ),
+        }
+    }
+    records
+}
+
+/// A strict battle readies the player when it queues their action, the log only has the action itself
+fn ready_player(app: &mut App, game_id: GameId, player: Id<UserMarker>) {
+    let entity = app
+        .world
+        .query_filtered::<(Entity, &UserIdComponent, &GameId), With<Player>>()
+        .iter(&app.world)
+        .find(|(_, user_id, player_game_id)| user_id.0 == player && **player_game_id == game_id)
+        .map(|(entity, _, _)| entity);
+    if let Some(entity) = entity {
+        app.world.entity_mut(entity).insert(Ready);
+    }
+}
+
+/// Plays the recorded inputs of the game again and prints what happened,
+/// true when the battle log came out the same as the recorded one
+fn replay_game(game_id: GameId, records: &[ReplayRecord], enemies: &EnemyDefinitions) -> bool {
+    let (arena, difficulty, enemy, seed, mode, turns) =
+        match records.iter().find_map(|record| match &record.event {
+            ReplayEvent::GameStart {
+                arena,
+                difficulty,
+                enemy,
+                seed,
+                mode,
+                turns,
+            } => Some((*arena, *difficulty, enemy.clone(), *seed, *mode, *turns)),
+            _ => None,
+        }) {
+            Some(start) => start,
+            None => {
+                println!(
+                    "Game {}: the log has no start for this game, skipping",
+                    game_id.0
+                );
+                return false;
+            }
+        };
+    println!(
+        "Game {} ({} {} {} against {}, seed {})",
+        game_id.0, difficulty, mode, turns, enemy, seed
+    );
+
+    let clock = Clock::manual();
+    let mut app = App::new();
+    app.insert_resource(HashMap::<ArenaId, Game>::new())
+        .insert_resource(clock)
+        .insert_resource(enemies.clone())
+        .init_resource::<ReplayedBattleLog>()
+        .add_plugin(EventsPlugin)
+        .add_plugin(BattlePlugin)
+        .add_system(collect_battle_log);
+    let localization = Localizations::new().get(Language::En).clone();
+
+    let mut replayed = Vec::new();
+    let mut run_frame = |app: &mut App, turn: usize| {
+        app.update();
+        let battle_log = std::mem::take(&mut app.world.resource_mut::<ReplayedBattleLog>().0);
+        replayed.extend(battle_log.into_iter().map(|ev| (turn, ev)));
+    };
+
+    let mut last_frame: Option<u64> = None;
+    let mut turn = 0;
+    for record in records.iter() {
+        if matches!(record.event, ReplayEvent::BattleLog(_)) {
+            continue;
+        }
+        if let Some(last_frame) = last_frame {
+            if record.frame != last_frame {
+                // Replay the quiet frames in between too, chained events take a few of them to settle
+                for _ in 0..(record.frame - last_frame).min(SETTLE_FRAMES + 1) {
+                    run_frame(&mut app, turn);
+                }
+            }
+        }
+        last_frame = Some(record.frame);
+        turn = record.turn;
+
+        match &record.event {
+            ReplayEvent::GameStart { .. } => {
+                app.world.resource_mut::<HashMap<ArenaId, Game>>().insert(
+                    arena,
+                    Game::new(
+                        game_id,
+                        Id::new(1),
+                        localization.clone(),
+                        enemy.clone(),
+                        seed,
+                        clock.now(),
+                    )
+                    .with_mode(mode, difficulty)
+                    .with_turns(turns),
+                );
+                app.world
+                    .send_event(BygoneSpawnEvent::new(enemy.clone(), difficulty, game_id));
+            }
+            ReplayEvent::PlayerJoin {
+                player,
+                player_name,
+                class,
+            } => app.world.send_event(PlayerJoinEvent::new(
+                *player,
+                player_name.clone(),
+                *class,
+                game_id,
+                arena,
+            )),
+            ReplayEvent::PlayerAttack {
+                player,
+                player_name,
+                enemy,
+                target,
+            } => {
+                if turns == TurnMode::Strict {
+                    ready_player(&mut app, game_id, *player);
+                }
+                app.world.send_event((
+                    game_id,
+                    PlayerAttackEvent::new(
+                        *player,
+                        player_name.clone(),
+                        arena,
+                        *enemy,
+                        target.clone(),
+                    ),
+                ))
+            }
+            ReplayEvent::PlayerAbility {
+                player,
+                player_name,
+                ability,
+                target,
+            } => {
+                if turns == TurnMode::Strict {
+                    ready_player(&mut app, game_id, *player);
+                }
+                app.world.send_event((
+                    game_id,
+                    PlayerAbilityEvent::new(
+                        *player,
+                        player_name.clone(),
+                        arena,
+                        *ability,
+                        target.clone(),
+                    ),
+                ))
+            }
+            ReplayEvent::EnemyAttack => app.world.send_event(EnemyAttackEvent::new(arena, game_id)),
+            ReplayEvent::TurnEnd => app.world.send_event(TurnEndEvent::new(game_id)),
+            ReplayEvent::BattleLog(_) => {}
+        }
+    }
+    for _ in 0..=SETTLE_FRAMES {
+        run_frame(&mut app, turn);
+    }
+
+    for (turn, ev) in replayed.iter() {
+        println!("  turn {}: {:?}", turn, ev);
+    }
+    let mut enemies: Vec<_> = app
+        .world
+        .query::<(&EnemySlot, &BygoneParts)>()
+        .iter(&app.world)
+        .collect();
+    enemies.sort_by_key(|(slot, _)| **slot);
+    for (slot, parts) in enemies {
+        println!("  final parts of enemy {}: {:?}", slot.0, parts.0);
+    }
+
+    let recorded: Vec<_> = records
+        .iter()
+        .filter_map(|record| match &record.event {
+            ReplayEvent::BattleLog(ev) => Some(ev),
+            _ => None,
+        })
+        .collect();
+    let mismatch = recorded
+        .iter()
+        .zip(replayed.iter())
+        .position(|(recorded, (_, replayed))| *recorded != replayed);
+    match mismatch {
+        Some(idx) => {
+            println!(
+                "Replay DIVERGES from the recorded battle log at event {}: recorded {:?}, replayed {:?}",
+                idx + 1,
+                recorded[idx],
+                replayed[idx].1
+            );
+            false
+        }
+        None if recorded.len() != replayed.len() => {
+            println!(
+                "Replay DIVERGES from the recorded battle log: recorded {} events, replayed {}",
+                recorded.len(),
+                replayed.len()
+            );
+            false
+        }
+        None => {
+            println!(
+                "Replay matches the recorded battle log ({} events)",
+                recorded.len()
+            );
+            true
+        }
+    }
+}
+
+pub fn replay(args: &ReplayArgs, enemies: EnemyDefinitions) {
+    disable_battle_traces();
+    let records = read_records(args);
+    let mut game_ids = Vec::new();
+    let mut records_by_game = HashMap::<GameId, Vec<ReplayRecord>>::new();
+    for record in records {
+        if matches!(args.game, Some(game) if game != record.game_id.0) {
+            continue;
+        }
+        if !records_by_game.contains_key(&record.game_id) {
+            game_ids.push(record.game_id);
+        }
+        records_by_game
+            .entry(record.game_id)
+            .or_default()
+            .push(record);
+    }
+    if game_ids.is_empty() {
+        println!("No recorded games to replay");
+    }
+    for game_id in game_ids {
+        replay_game(game_id, &records_by_game[&game_id], &enemies);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use bevy::prelude::*;
+    use crossbeam_channel::{unbounded, Receiver};
+
+    use super::{record_replay, replay_game, ReplayRecord};
+    use crate::{
+        components::{Ability, PlayerClass},
+        enemies::EnemyDefinitions,
+        game_helpers::Difficulty,
+        systems::BattleLabel,
+        test_support::*,
+    };
+
+    /// Records the battles of the game the way `main` does with a replay path
+    fn recording(game: &mut TestGame) -> Receiver<ReplayRecord> {
+        let (sender, receiver) = unbounded();
+        game.app
+            .add_system(record_replay(Mutex::new(sender)).after(BattleLabel::Input));
+        receiver
+    }
+
+    fn replays_the_same(records: &Receiver<ReplayRecord>) -> bool {
+        let records: Vec<_> = records.try_iter().collect();
+        let game_id = records[0].game_id;
+        replay_game(game_id, &records, &EnemyDefinitions::default())
+    }
+
+    #[test]
+    fn recorded_battle_replays_the_same() {
+        let mut game = TestGame::new();
+        let records = recording(&mut game);
+        game.start_game(1, Difficulty::Medium);
+        game.join(2, PlayerClass::Medic);
+        game.join(3, PlayerClass::Scout);
+        game.wait(DRAW_DELAY);
+
+        for _ in 0..5 {
+            game.attack(1, part("Gun"));
+            game.use_ability(2, Ability::Heal);
+            game.use_ability(3, Ability::Defend);
+            game.attack(3, part("Sensor"));
+            game.finish_turn();
+        }
+
+        assert!(replays_the_same(&records));
+    }
+
+    #[test]
+    fn strict_battle_replays_the_same() {
+        let mut game = TestGame::new();
+        let records = recording(&mut game);
+        game.start_strict(1, Difficulty::Medium);
+        game.join(2, PlayerClass::Fighter);
+        game.wait(DRAW_DELAY);
+
+        for _ in 0..5 {
+            game.attack(2, part("Sensor"));
+            game.attack(1, part("Gun"));
+            game.finish_turn();
+        }
+
+        assert!(replays_the_same(&records));
+    }
+}
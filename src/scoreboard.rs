@@ -0,0 +1,158 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use bevy::prelude::*;
+use crossbeam_channel::Sender;
+use serde::{Deserialize, Serialize};
+use twilight_model::id::{
+    marker::{GuildMarker, UserMarker},
+    Id,
+};
+
+use crate::{
+    components::{GameId, Player, PlayerName, UserIdComponent},
+    events::{BattleLogEvent, GameEndEvent},
+    game_helpers::{ArenaId, FinishedGameStatus},
+    logging::format_time,
+};
+
+pub const LEADERBOARD_SIZE: usize = 10;
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct PlayerStats {
+    pub name: PlayerName,
+    pub wins: usize,
+    pub losses: usize,
+    pub hits: usize,
+    pub misses: usize,
+    pub deaths: usize,
+    /// Most waves cleared in a survival battle
+    #[serde(default)]
+    pub best_waves: usize,
+}
+
+impl PlayerStats {
+    pub fn new(name: PlayerName) -> Self {
+        Self {
+            name,
+            wins: 0,
+            losses: 0,
+            hits: 0,
+            misses: 0,
+            deaths: 0,
+            best_waves: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Scoreboard(pub HashMap<Id<GuildMarker>, HashMap<Id<UserMarker>, PlayerStats>>);
+
+impl Scoreboard {
+    pub fn top(&self, guild_id: Id<GuildMarker>, count: usize) -> Vec<PlayerStats> {
+        let mut stats: Vec<_> = self
+            .0
+            .get(&guild_id)
+            .map(|players| players.values().cloned().collect())
+            .unwrap_or_default();
+        stats.sort_by(|a, b| {
+            b.wins
+                .cmp(&a.wins)
+                .then(b.hits.cmp(&a.hits))
+                .then(a.deaths.cmp(&b.deaths))
+                .then(a.name.cmp(&b.name))
+        });
+        stats.truncate(count);
+        stats
+    }
+
+    fn player_mut(
+        &mut self,
+        guild_id: Id<GuildMarker>,
+        user_id: Id<UserMarker>,
+        name: &PlayerName,
+    ) -> &mut PlayerStats {
+        let stats = self
+            .0
+            .entry(guild_id)
+            .or_default()
+            .entry(user_id)
+            .or_insert_with(|| PlayerStats::new(name.clone()));
+        // Keep the latest nickname so the leaderboard matches what people see in the battle
+        stats.name = name.clone();
+        stats
+    }
+}
+
+pub fn update_scoreboard(
+    mut scoreboard: ResMut<Scoreboard>,
+    mut ev_battle_log: EventReader<(ArenaId, BattleLogEvent)>,
+    mut ev_game_end: EventReader<GameEndEvent>,
+    players: Query<(&UserIdComponent, &PlayerName, &GameId), (With<Player>,)>,
+) {
+    for (arena, ev) in ev_battle_log.iter() {
+        let (user_id, name) = match ev {
+            BattleLogEvent::PlayerHit(user_id, name, _, _)
+            | BattleLogEvent::PlayerCrit(user_id, name, _, _)
+            | BattleLogEvent::PlayerMiss(user_id, name)
+            | BattleLogEvent::PlayerDead(user_id, name) => (*user_id, name),
+            BattleLogEvent::BygoneHit(_, _)
+            | BattleLogEvent::BygoneCrit(_, _)
+            | BattleLogEvent::BygoneTargets(_, _, _)
+            | BattleLogEvent::BygoneMiss(_)
+            | BattleLogEvent::BygoneDead(_)
+            | BattleLogEvent::PlayerHeal(_, _)
+            | BattleLogEvent::PlayerDefend(_)
+            | BattleLogEvent::PlayerFocus(_)
+            | BattleLogEvent::PlayerRevive(_, _)
+            | BattleLogEvent::EffectApplied(_, _)
+            | BattleLogEvent::EffectExpired(_, _)
+            | BattleLogEvent::BurnDamage(_)
+            | BattleLogEvent::BygoneTelegraph(_, _)
+            | BattleLogEvent::BygoneSpecial(_, _)
+            | BattleLogEvent::SpecialInterrupted(_, _)
+            | BattleLogEvent::PartShielded(_, _, _)
+            | BattleLogEvent::NextWave(_) => continue,
+        };
+        let stats = scoreboard.player_mut(arena.guild_id, user_id, name);
+        match ev {
+            BattleLogEvent::PlayerHit(..) | BattleLogEvent::PlayerCrit(..) => stats.hits += 1,
+            BattleLogEvent::PlayerMiss(..) => stats.misses += 1,
+            BattleLogEvent::PlayerDead(..) => stats.deaths += 1,
+            _ => {}
+        }
+    }
+
+    for ev in ev_game_end.iter() {
+        for (user_id, name, player_game_id) in players.iter() {
+            if *player_game_id != ev.game_id {
+                continue;
+            }
+            let stats = scoreboard.player_mut(ev.arena.guild_id, user_id.0, name);
+            match ev.status {
+                FinishedGameStatus::Won => stats.wins += 1,
+                FinishedGameStatus::Lost => stats.losses += 1,
+                FinishedGameStatus::Expired | FinishedGameStatus::Ended => {}
+                FinishedGameStatus::Survived(waves) => {
+                    stats.best_waves = stats.best_waves.max(waves)
+                }
+            }
+        }
+    }
+}
+
+pub fn save_scoreboard(sender: Mutex<Sender<Scoreboard>>) -> impl FnMut(Res<Scoreboard>) {
+    move |scoreboard| {
+        if !scoreboard.is_changed() {
+            return;
+        }
+        if let Ok(ref mut sender_lock) = sender.lock() {
+            if let Err(err) = sender_lock.send(scoreboard.clone()) {
+                println!(
+                    "{} - scoreboard - FAILED to send save scoreboard event: {}",
+                    format_time(),
+                    err
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,483 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bevy::prelude::*;
+use bevy_turborand::{GlobalRng, RngPlugin};
+use clap::ValueEnum;
+use serde::Serialize;
+use strum::IntoEnumIterator;
+use twilight_model::id::Id;
+
+use crate::{
+    bundles::BygoneParts,
+    cli::SimulateArgs,
+    components::{
+        Active, BygonePart, Enemy, EnemySlot, GameId, Player, PlayerName, Ready, UserIdComponent,
+    },
+    enemies::{EnemyDefinition, EnemyDefinitions, PartEffect},
+    events::*,
+    game_helpers::{ArenaId, Clock, Difficulty, FinishedGameStatus, Game, GameStatus},
+    localization::{Language, Localizations},
+    logging::disable_battle_traces,
+    systems::BattlePlugin,
+    timings::{TimingConfig, Timings},
+};
+
+/// Frames to run after injecting events, enough for every chain of events in the systems to resolve
+const SETTLE_FRAMES: usize = 3;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Strategy {
+    /// Shoot a random intact part
+    Random,
+    /// Always shoot the part that moves the enemy to its next stage, like the core of _03
+    CoreRush,
+    /// Take out the parts that aim first, the easiest to hit first, then go for the stage part
+    Disarm,
+}
+
+impl Strategy {
+    fn choose_target(
+        &self,
+        enemy: &EnemyDefinition,
+        parts: &BygoneParts,
+        rng: &mut GlobalRng,
+    ) -> BygonePart {
+        let stage_part = enemy.stage_part().unwrap_or(&enemy.parts[0].id).clone();
+        match self {
+            Strategy::Random => {
+                let intact: Vec<_> = parts
+                    .0
+                    .iter()
+                    .filter(|(_, vitality)| vitality.health().alive())
+                    .map(|(part, _)| part)
+                    .collect();
+                rng.sample(&intact)
+                    .map(|part| (*part).clone())
+                    .unwrap_or(stage_part)
+            }
+            Strategy::CoreRush => stage_part,
+            Strategy::Disarm => {
+                let mut aiming: Vec<_> = enemy
+                    .parts
+                    .iter()
+                    .filter(|part| {
+                        part.on_destroyed
+                            .iter()
+                            .any(|effect| matches!(effect, PartEffect::Accuracy(_)))
+                    })
+                    .collect();
+                aiming.sort_by_key(|part| part.dodge);
+                aiming
+                    .into_iter()
+                    .map(|part| &part.id)
+                    .find(|part| matches!(parts.get(part), Some(vitality) if vitality.health().alive()))
+                    .cloned()
+                    .unwrap_or(stage_part)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SimulationReport {
+    pub enemy: String,
+    pub difficulty: Difficulty,
+    pub players: usize,
+    pub battles: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub expired: usize,
+    pub win_rate: f32,
+    pub average_turns: f32,
+    /// Average turn a part of the leading enemy was first destroyed on, among the battles where it
+    /// was destroyed at all
+    pub average_kill_turns: BTreeMap<BygonePart, Option<f32>>,
+    pub common_kill_order: Vec<BygonePart>,
+    pub common_kill_order_rate: f32,
+}
+
+struct SimulatedBattle {
+    arena: ArenaId,
+    game_id: GameId,
+    /// In slot order
+    bygones: Vec<(EnemySlot, Entity)>,
+    players: Vec<Entity>,
+    kill_order: Vec<BygonePart>,
+    kill_turns: HashMap<BygonePart, usize>,
+    result: Option<(FinishedGameStatus, usize)>,
+}
+
+fn settle(app: &mut App) {
+    for _ in 0..SETTLE_FRAMES {
+        app.update();
+    }
+}
+
+/// Part deaths seen since the last time the simulation looked, events themselves only live for two frames
+#[derive(Default)]
+struct PartDeaths(Vec<(Entity, BygonePart)>);
+
+fn collect_part_deaths(
+    mut ev_part_death: EventReader<BygonePartDeathEvent>,
+    mut part_deaths: ResMut<PartDeaths>,
+) {
+    for ev in ev_part_death.iter() {
+        part_deaths.0.push((ev.entity, ev.part.clone()));
+    }
+}
+
+fn record_part_deaths(
+    app: &mut App,
+    battles: &mut [SimulatedBattle],
+    battle_by_bygone: &HashMap<Entity, usize>,
+    turn: usize,
+) {
+    let part_deaths = std::mem::take(&mut app.world.resource_mut::<PartDeaths>().0);
+    for (entity, part) in part_deaths {
+        if let Some(battle) = battle_by_bygone.get(&entity).map(|idx| &mut battles[*idx]) {
+            if !battle.kill_turns.contains_key(&part) {
+                battle.kill_turns.insert(part.clone(), turn);
+                battle.kill_order.push(part);
+            }
+        }
+    }
+}
+
+fn simulate_batch(
+    difficulty: Difficulty,
+    player_count: usize,
+    args: &SimulateArgs,
+    enemies: &EnemyDefinitions,
+    timings: &Timings,
+    seed: Option<u64>,
+) -> SimulationReport {
+    let max_turns = args.max_turns.unwrap_or_else(|| timings.max_game_turns());
+    let enemy = enemies.get(&args.enemy);
+    let clock = Clock::manual();
+    let mut app = App::new();
+    app.insert_resource(HashMap::<ArenaId, Game>::new())
+        .insert_resource(clock)
+        .insert_resource(enemies.clone())
+        .add_plugin(RngPlugin::new(seed))
+        .add_plugin(EventsPlugin)
+        .add_plugin(BattlePlugin)
+        .init_resource::<PartDeaths>()
+        .add_system(collect_part_deaths);
+    let mut strategy_rng = GlobalRng::new(Some(app.world.resource_mut::<GlobalRng>().u64(..)));
+    let localization = Localizations::new().get(Language::En).clone();
+
+    let mut battles: Vec<_> = (0..args.battles)
+        .map(|idx| SimulatedBattle {
+            arena: ArenaId::new(Id::new(idx as u64 + 1), None),
+            game_id: GameId(idx as u128),
+            bygones: Vec::new(),
+            players: Vec::with_capacity(player_count),
+            kill_order: Vec::new(),
+            kill_turns: HashMap::new(),
+            result: None,
+        })
+        .collect();
+
+    for battle in battles.iter() {
+        let battle_seed = app.world.resource_mut::<GlobalRng>().u64(..);
+        app.world.resource_mut::<HashMap<ArenaId, Game>>().insert(
+            battle.arena,
+            Game::new(
+                battle.game_id,
+                Id::new(1),
+                localization.clone(),
+                args.enemy.clone(),
+                battle_seed,
+                clock.now(),
+            ),
+        );
+        app.world.send_event(BygoneSpawnEvent::new(
+            args.enemy.clone(),
+            difficulty,
+            battle.game_id,
+        ));
+        for player in 0..player_count {
+            app.world.send_event(PlayerJoinEvent::new(
+                Id::new(player as u64 + 1),
+                PlayerName(format!("Player {}", player + 1)),
+                args.class,
+                battle.game_id,
+                battle.arena,
+            ));
+        }
+    }
+    settle(&mut app);
+
+    let battle_by_game: HashMap<_, _> = battles
+        .iter()
+        .enumerate()
+        .map(|(idx, battle)| (battle.game_id, idx))
+        .collect();
+    for (entity, game_id, slot) in app
+        .world
+        .query_filtered::<(Entity, &GameId, &EnemySlot), With<Enemy>>()
+        .iter(&app.world)
+    {
+        battles[battle_by_game[game_id]]
+            .bygones
+            .push((*slot, entity));
+    }
+    for battle in battles.iter_mut() {
+        battle.bygones.sort();
+    }
+    let mut players: Vec<_> = app
+        .world
+        .query_filtered::<(Entity, &GameId, &UserIdComponent), With<Player>>()
+        .iter(&app.world)
+        .map(|(entity, game_id, user_id)| (*game_id, user_id.0, entity))
+        .collect();
+    players.sort();
+    for (game_id, _, entity) in players {
+        battles[battle_by_game[&game_id]].players.push(entity);
+    }
+    // Kill stats follow the leading enemy, the escort is in the way of it
+    let battle_by_bygone: HashMap<_, _> = battles
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, battle)| battle.bygones.first().map(|(_, bygone)| (*bygone, idx)))
+        .collect();
+
+    for turn in 1..=max_turns {
+        if battles.iter().all(|battle| battle.result.is_some()) {
+            break;
+        }
+        // One player at a time, just like clicks coming in one after another
+        for slot in 0..player_count {
+            for battle in battles.iter().filter(|battle| battle.result.is_none()) {
+                // Scripted players take the enemies on one at a time. Once all of them are down
+                // they keep shooting the leader until the turn ends the battle.
+                let bygone = battle
+                    .bygones
+                    .iter()
+                    .find(|(_, bygone)| app.world.entity(*bygone).contains::<Active>())
+                    .or_else(|| battle.bygones.first());
+                let (player, (enemy_slot, bygone)) = match (battle.players.get(slot), bygone) {
+                    (Some(player), Some(bygone)) => (*player, *bygone),
+                    _ => continue,
+                };
+                let player_ref = app.world.entity(player);
+                if !player_ref.contains::<Active>() || !player_ref.contains::<Ready>() {
+                    continue;
+                }
+                let user_id = player_ref.get::<UserIdComponent>().unwrap().0;
+                let name = player_ref.get::<PlayerName>().unwrap().clone();
+                let target = match (
+                    enemies.in_slot(&args.enemy, enemy_slot),
+                    app.world.get::<BygoneParts>(bygone),
+                ) {
+                    (Some(enemy), Some(parts)) => {
+                        args.strategy.choose_target(enemy, parts, &mut strategy_rng)
+                    }
+                    _ => continue,
+                };
+                app.world.send_event((
+                    battle.game_id,
+                    PlayerAttackEvent::new(user_id, name, battle.arena, enemy_slot, target),
+                ));
+            }
+            settle(&mut app);
+            record_part_deaths(&mut app, &mut battles, &battle_by_bygone, turn);
+        }
+
+        for battle in battles.iter().filter(|battle| battle.result.is_none()) {
+            app.world
+                .send_event(EnemyAttackEvent::new(battle.arena, battle.game_id));
+        }
+        settle(&mut app);
+        for battle in battles.iter().filter(|battle| battle.result.is_none()) {
+            app.world.send_event(TurnEndEvent::new(battle.game_id));
+        }
+        settle(&mut app);
+        record_part_deaths(&mut app, &mut battles, &battle_by_bygone, turn);
+
+        app.world.resource_mut::<Clock>().advance(timings.turn());
+
+        let games = app.world.resource::<HashMap<ArenaId, Game>>();
+        for battle in battles.iter_mut().filter(|battle| battle.result.is_none()) {
+            if let Some(GameStatus::Finished(status)) =
+                games.get(&battle.arena).map(|game| game.status)
+            {
+                battle.result = Some((status, turn));
+            }
+        }
+    }
+
+    for battle in battles.iter_mut().filter(|battle| battle.result.is_none()) {
+        battle.result = Some((FinishedGameStatus::Expired, max_turns));
+    }
+
+    summarize(enemy, &args.enemy, difficulty, player_count, &battles)
+}
+
+fn summarize(
+    enemy: &EnemyDefinition,
+    enemy_id: &str,
+    difficulty: Difficulty,
+    player_count: usize,
+    battles: &[SimulatedBattle],
+) -> SimulationReport {
+    let count_status = |status| {
+        battles
+            .iter()
+            .filter(|battle| matches!(battle.result, Some((battle_status, _)) if battle_status == status))
+            .count()
+    };
+    let wins = count_status(FinishedGameStatus::Won);
+    let total_turns: usize = battles
+        .iter()
+        .filter_map(|battle| battle.result.map(|(_, turns)| turns))
+        .sum();
+
+    let mut average_kill_turns = BTreeMap::new();
+    for part in enemy.parts.iter() {
+        let kill_turns: Vec<_> = battles
+            .iter()
+            .filter_map(|battle| battle.kill_turns.get(&part.id))
+            .collect();
+        let average = if kill_turns.is_empty() {
+            None
+        } else {
+            Some(kill_turns.iter().copied().sum::<usize>() as f32 / kill_turns.len() as f32)
+        };
+        average_kill_turns.insert(part.id.clone(), average);
+    }
+
+    let mut kill_orders = HashMap::<_, usize>::new();
+    for battle in battles.iter() {
+        *kill_orders.entry(battle.kill_order.clone()).or_default() += 1;
+    }
+    let (common_kill_order, common_kill_order_count) = kill_orders
+        .into_iter()
+        .max_by(|(order_a, count_a), (order_b, count_b)| {
+            count_a.cmp(count_b).then(order_b.cmp(order_a))
+        })
+        .unwrap_or_default();
+
+    let battle_count = battles.len().max(1) as f32;
+    SimulationReport {
+        enemy: enemy_id.to_string(),
+        difficulty,
+        players: player_count,
+        battles: battles.len(),
+        wins,
+        losses: count_status(FinishedGameStatus::Lost),
+        expired: count_status(FinishedGameStatus::Expired),
+        win_rate: wins as f32 / battle_count,
+        average_turns: total_turns as f32 / battle_count,
+        average_kill_turns,
+        common_kill_order,
+        common_kill_order_rate: common_kill_order_count as f32 / battle_count,
+    }
+}
+
+fn print_table(enemy: &EnemyDefinition, reports: &[SimulationReport]) {
+    let localization = enemy.localization(Language::En);
+    // Parts no battle ever took down would only be a column of dashes
+    let parts: Vec<_> = enemy
+        .parts
+        .iter()
+        .map(|part| (&part.id, localization.part_name(&part.id)))
+        .filter(|(part, _)| {
+            reports
+                .iter()
+                .any(|report| matches!(report.average_kill_turns.get(*part), Some(Some(_))))
+        })
+        .collect();
+    let part_headers: Vec<_> = parts
+        .iter()
+        .map(|(_, name)| format!("{:>6}", name))
+        .collect();
+    println!(
+        "{:<12} {:>7} {:>8} {:>9} {}   most common kill order",
+        "difficulty",
+        "players",
+        "win rate",
+        "avg turns",
+        part_headers.join(" ")
+    );
+    for report in reports {
+        let kill_turns: Vec<_> = parts
+            .iter()
+            .map(|(part, name)| {
+                let width = name.chars().count().max(6);
+                match report.average_kill_turns.get(*part) {
+                    Some(Some(turn)) => format!("{:>width$.1}", turn),
+                    _ => format!("{:>width$}", "-"),
+                }
+            })
+            .collect();
+        // Most battles ended before anything was shot off, there is no order to show
+        let kill_order = if report.common_kill_order.is_empty() {
+            String::new()
+        } else {
+            let names: Vec<_> = report
+                .common_kill_order
+                .iter()
+                .map(|part| localization.part_name(part))
+                .collect();
+            format!(
+                "   {} ({:.1}%)",
+                names.join(" > "),
+                report.common_kill_order_rate * 100.0
+            )
+        };
+        println!(
+            "{:<12} {:>7} {:>7.1}% {:>9.1} {}{}",
+            report.difficulty.to_string(),
+            report.players,
+            report.win_rate * 100.0,
+            report.average_turns,
+            kill_turns.join(" "),
+            kill_order
+        );
+    }
+}
+
+/// Simulated battles don't belong to a guild, they run with the common timings
+pub fn simulate(args: &SimulateArgs, enemies: EnemyDefinitions, timings: TimingConfig) {
+    if !enemies.contains(&args.enemy) {
+        println!("Unknown enemy {}", args.enemy);
+        return;
+    }
+    disable_battle_traces();
+    let difficulties: Vec<_> = if args.difficulties.is_empty() {
+        Difficulty::iter().collect()
+    } else {
+        args.difficulties.clone()
+    };
+
+    let mut reports = Vec::new();
+    for (idx, (difficulty, player_count)) in difficulties
+        .iter()
+        .flat_map(|difficulty| {
+            args.players
+                .iter()
+                .map(move |players| (*difficulty, *players))
+        })
+        .enumerate()
+    {
+        let seed = args.seed.map(|seed| seed.wrapping_add(idx as u64));
+        reports.push(simulate_batch(
+            difficulty,
+            player_count,
+            args,
+            &enemies,
+            &timings.base,
+            seed,
+        ));
+    }
+
+    if args.json {
+        match serde_json::to_string_pretty(&reports) {
+            Ok(json) => println!("{}", json),
+            Err(err) => println!("Failed to serialize the simulation report: {}", err),
+        }
+    } else {
+        print_table(enemies.get(&args.enemy), &reports);
+    }
+}
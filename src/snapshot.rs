@@ -0,0 +1,370 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use bevy_turborand::RngComponent;
+use crossbeam_channel::Sender;
+use serde::{Deserialize, Serialize};
+use twilight_model::id::{marker::UserMarker, Id};
+
+use crate::{
+    bundles::{BygoneBundle, BygoneParts, PlayerBundle},
+    components::{
+        Active, Attack, BygoneStage, Cooldowns, Defending, Enemy, EnemyKind, EnemySlot, GameId,
+        Player, PlayerClass, PlayerName, Ready, SpecialMoves, StatusEffects, TargetingPolicy,
+        UserIdComponent, Vitality,
+    },
+    enemies::DEFAULT_ENEMY,
+    events::{GameDrawEvent, TurnEndEvent},
+    game_helpers::{ArenaId, Game, GameStatus},
+    logging::format_time,
+};
+
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BygoneSnapshot {
+    pub game_id: GameId,
+    /// Saves from before escorts only have the game's own enemy
+    #[serde(default)]
+    pub kind: Option<EnemyKind>,
+    #[serde(default)]
+    pub slot: EnemySlot,
+    pub parts: BygoneParts,
+    pub attack: Attack,
+    pub stage: BygoneStage,
+    pub active: bool,
+    #[serde(default)]
+    pub effects: StatusEffects,
+    #[serde(default)]
+    pub targeting: TargetingPolicy,
+    #[serde(default)]
+    pub specials: SpecialMoves,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PlayerSnapshot {
+    pub user_id: Id<UserMarker>,
+    pub name: PlayerName,
+    pub game_id: GameId,
+    #[serde(default)]
+    pub class: PlayerClass,
+    pub vitality: Vitality,
+    pub attack: Attack,
+    pub active: bool,
+    pub ready: bool,
+    #[serde(default)]
+    pub cooldowns: Cooldowns,
+    #[serde(default)]
+    pub defending: bool,
+    #[serde(default)]
+    pub effects: StatusEffects,
+}
+
+/// Everything needed to bring the running battles back after a restart
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct GamesSnapshot {
+    pub games: HashMap<ArenaId, Game>,
+    pub battle_log: HashMap<ArenaId, Vec<String>>,
+    pub bygones: Vec<BygoneSnapshot>,
+    pub players: Vec<PlayerSnapshot>,
+}
+
+#[allow(clippy::type_complexity)]
+pub fn save_games(
+    sender: Mutex<Sender<GamesSnapshot>>,
+) -> impl FnMut(
+    Local<Option<Instant>>,
+    Res<HashMap<ArenaId, Game>>,
+    Res<HashMap<ArenaId, Vec<String>>>,
+    Query<
+        (
+            &GameId,
+            &EnemyKind,
+            &EnemySlot,
+            &BygoneParts,
+            &Attack,
+            &BygoneStage,
+            &StatusEffects,
+            &TargetingPolicy,
+            &SpecialMoves,
+            Option<&Active>,
+        ),
+        (With<Enemy>,),
+    >,
+    Query<
+        (
+            &UserIdComponent,
+            &PlayerName,
+            &GameId,
+            &PlayerClass,
+            &Vitality,
+            &Attack,
+            &Cooldowns,
+            &StatusEffects,
+            Option<&Active>,
+            Option<&Ready>,
+            Option<&Defending>,
+        ),
+        (With<Player>,),
+    >,
+) {
+    move |mut last_save, games, battle_log, bygones, players| {
+        if matches!(*last_save, Some(last_save) if last_save.elapsed() < SNAPSHOT_INTERVAL) {
+            return;
+        }
+        *last_save = Some(Instant::now());
+
+        let snapshot = GamesSnapshot {
+            games: games.clone(),
+            battle_log: battle_log.clone(),
+            bygones: bygones
+                .iter()
+                .map(
+                    |(
+                        game_id,
+                        kind,
+                        slot,
+                        parts,
+                        attack,
+                        stage,
+                        effects,
+                        targeting,
+                        specials,
+                        active,
+                    )| {
+                        BygoneSnapshot {
+                            game_id: *game_id,
+                            kind: Some(kind.clone()),
+                            slot: *slot,
+                            parts: parts.clone(),
+                            attack: *attack,
+                            stage: stage.clone(),
+                            active: active.is_some(),
+                            effects: effects.clone(),
+                            targeting: *targeting,
+                            specials: specials.clone(),
+                        }
+                    },
+                )
+                .collect(),
+            players: players
+                .iter()
+                .map(
+                    |(
+                        user_id,
+                        name,
+                        game_id,
+                        class,
+                        vitality,
+                        attack,
+                        cooldowns,
+                        effects,
+                        active,
+                        ready,
+                        defending,
+                    )| {
+                        PlayerSnapshot {
+                            user_id: user_id.0,
+                            name: name.clone(),
+                            game_id: *game_id,
+                            class: *class,
+                            vitality: *vitality,
+                            attack: *attack,
+                            active: active.is_some(),
+                            ready: ready.is_some(),
+                            cooldowns: cooldowns.clone(),
+                            defending: defending.is_some(),
+                            effects: effects.clone(),
+                        }
+                    },
+                )
+                .collect(),
+        };
+        if let Ok(ref mut sender_lock) = sender.lock() {
+            if let Err(err) = sender_lock.send(snapshot) {
+                println!(
+                    "{} - snapshot - FAILED to send save games event: {}",
+                    format_time(),
+                    err
+                );
+            }
+        }
+    }
+}
+
+pub fn restore_games(
+    mut commands: Commands,
+    snapshot: Res<GamesSnapshot>,
+    mut games: ResMut<HashMap<ArenaId, Game>>,
+    mut battle_log: ResMut<HashMap<ArenaId, Vec<String>>>,
+    mut ev_turn_end: EventWriter<TurnEndEvent>,
+    mut ev_game_draw: EventWriter<GameDrawEvent>,
+) {
+    let games_by_id: HashMap<_, _> = snapshot
+        .games
+        .values()
+        .map(|game| (game.id, game))
+        .collect();
+    for bygone in snapshot.bygones.iter() {
+        // Rolls are not persisted, a restored enemy goes on with a fresh generator from the seed it
+        // spawned with
+        let game = games_by_id.get(&bygone.game_id);
+        let seed = game.map(|game| game.enemy_seed(bygone.slot));
+        let kind = match &bygone.kind {
+            Some(kind) => kind.clone(),
+            None => EnemyKind(
+                game.map(|game| game.enemy.clone())
+                    .unwrap_or_else(|| DEFAULT_ENEMY.to_string()),
+            ),
+        };
+        let mut entity = commands.spawn_bundle(BygoneBundle::restore(
+            bygone.game_id,
+            kind,
+            bygone.slot,
+            bygone.parts.clone(),
+            bygone.attack,
+            bygone.stage.clone(),
+            bygone.effects.clone(),
+            bygone.targeting,
+            bygone.specials.clone(),
+            RngComponent::new(seed),
+        ));
+        if !bygone.active {
+            entity.remove::<Active>();
+        }
+    }
+    for player in snapshot.players.iter() {
+        let mut entity = commands.spawn_bundle(PlayerBundle::restore(
+            player.user_id,
+            player.name.clone(),
+            player.game_id,
+            player.class,
+            player.vitality,
+            player.attack,
+            player.cooldowns.clone(),
+            player.effects.clone(),
+        ));
+        if !player.active {
+            entity.remove::<Active>();
+        }
+        if !player.ready {
+            entity.remove::<Ready>();
+        }
+        if player.defending {
+            entity.insert(Defending);
+        }
+    }
+
+    for (arena, game) in snapshot.games.iter() {
+        if game.status == GameStatus::Ongoing {
+            println!(
+                "{} - snapshot - Restoring game {} in arena {}",
+                format_time(),
+                game.id.0,
+                arena
+            );
+            // Turn timers are not persisted, so the turn that was running is over by now
+            ev_turn_end.send(TurnEndEvent::new(game.id));
+            ev_game_draw.send(GameDrawEvent::new(*arena));
+        }
+    }
+    games.extend(snapshot.games.clone());
+    battle_log.extend(snapshot.battle_log.clone());
+
+    commands.remove_resource::<GamesSnapshot>();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Mutex};
+
+    use bevy::prelude::*;
+    use bevy_turborand::RngComponent;
+    use crossbeam_channel::unbounded;
+
+    use super::{restore_games, save_games, GamesSnapshot};
+    use crate::{
+        components::{Attack, Enemy, EnemySlot, PlayerClass},
+        dice::Dice,
+        game_helpers::{ArenaId, Difficulty, Game, GameStatus},
+        test_support::*,
+    };
+
+    #[test]
+    fn running_battle_comes_back_from_its_snapshot() {
+        let mut game = TestGame::new();
+        game.start_game_against(TestGame::arena(), 1, Difficulty::Easy, "drone");
+        game.join(2, PlayerClass::Medic);
+        game.set_bygone_attack(Attack::new(0, 0));
+        game.set_player_attack(1, Attack::new(1, 200));
+        game.wait(DRAW_DELAY);
+        game.attack_enemy(1, 1, part("Hull"));
+        game.finish_turn();
+        game.knock_out(2);
+
+        let (sender, receiver) = unbounded();
+        game.app.add_system(save_games(Mutex::new(sender)));
+        game.step();
+        // Through JSON like the games file
+        let json = serde_json::to_string(&receiver.try_recv().unwrap()).unwrap();
+        let snapshot: GamesSnapshot = serde_json::from_str(&json).unwrap();
+
+        let mut restored = TestGame::new();
+        restored
+            .app
+            .insert_resource(snapshot)
+            .add_startup_system(restore_games);
+        restored.step();
+
+        let saved_game = game.game(TestGame::arena()).clone();
+        let restored_game = restored.game(TestGame::arena()).clone();
+        assert_eq!(restored_game.id, saved_game.id);
+        assert_eq!(restored_game.status, GameStatus::Ongoing);
+        assert_eq!(
+            restored
+                .app
+                .world
+                .resource::<HashMap<ArenaId, Game>>()
+                .len(),
+            1
+        );
+        for player in [1, 2] {
+            assert_eq!(restored.player_state(player), game.player_state(player));
+        }
+        assert!(!restored.player_state(2).1);
+        for slot in [0, 1] {
+            let (saved_parts, saved_stage) = game.enemy(slot);
+            let (restored_parts, restored_stage) = restored.enemy(slot);
+            assert_eq!(restored_parts.0, saved_parts.0);
+            assert_eq!(restored_stage, saved_stage);
+        }
+        assert!(restored
+            .enemy(1)
+            .0
+            .get(&part("Hull"))
+            .unwrap()
+            .health()
+            .wounded());
+
+        // The escort keeps rolling apart from the leader
+        let mut rolls: Vec<_> = restored
+            .app
+            .world
+            .query_filtered::<(&EnemySlot, &mut RngComponent), With<Enemy>>()
+            .iter_mut(&mut restored.app.world)
+            .map(|(slot, mut rng)| {
+                let mut spawned = RngComponent::new(Some(restored_game.enemy_seed(*slot)));
+                let rolls: Vec<_> = (0..8).map(|_| rng.d100()).collect();
+                assert_eq!(rolls, (0..8).map(|_| spawned.d100()).collect::<Vec<_>>());
+                (*slot, rolls)
+            })
+            .collect();
+        rolls.sort();
+        assert_eq!(rolls.len(), 2);
+        assert_ne!(rolls[0].1, rolls[1].1);
+    }
+}
@@ -1,57 +1,238 @@
 use std::{
+    cmp::Reverse,
     collections::{HashMap, HashSet, VecDeque},
-    ops::DerefMut,
     sync::Mutex,
-    time::{Duration, Instant},
+    time::{Duration, SystemTime},
 };
 
 use bevy::prelude::*;
-use bevy_turborand::GlobalRng;
+use bevy_turborand::{GlobalRng, RngComponent};
 use crossbeam_channel::{Receiver, Sender};
-use enum_map::EnumMap;
-use twilight_model::id::{marker::GuildMarker, Id};
+use twilight_model::id::{
+    marker::{InteractionMarker, UserMarker},
+    Id,
+};
 
 use crate::{
-    bundles::{Bygone03Bundle, BygoneParts, PlayerBundle},
+    bundles::{BygoneBundle, BygoneParts, PlayerBundle},
     components::{
-        Active, Attack, Bygone03Stage, BygonePart, Enemy, GameId, Player, PlayerName, Ready,
+        Ability, Active, Attack, AttackOutcome, BygonePart, BygoneStage, Cooldowns, Defending,
+        Enemy, EnemyKind, EnemySlot, GameId, Player, PlayerClass, PlayerName, Ready, SpecialMove,
+        SpecialMoves, StatusEffect, StatusEffects, TargetingPolicy, Threat, TimedEffect,
         UserIdComponent, Vitality,
     },
-    dice::{choose_mut, Dice},
+    dice::{choose_index, Dice},
+    enemies::{EnemyDefinitions, PartEffect, DEFAULT_ENEMY},
     events::*,
-    game_helpers::{EventDelay, FinishedGameStatus, Game, GameStatus, GameTimer},
-    localization::RenderText,
-    logging::format_time,
+    game_helpers::{
+        ArenaId, Clock, Difficulty, FinishedGameStatus, Game, GameScope, GameStatus, GameTimer,
+        StrictStep, StrictTurn, TurnMode,
+    },
+    guild_config::GuildConfigs,
+    localization::{Localization, Localizations},
+    logging::{battle_traces, format_time},
+    scoreboard::{Scoreboard, LEADERBOARD_SIZE},
+    timings::TimingConfig,
 };
 
-const INTERACTION_TOKEN_TTL_SECS: u64 = 15 * 60;
-const MAX_GAME_DURATION_SECS: u64 = INTERACTION_TOKEN_TTL_SECS - 10;
-const GAME_COOLDOWN_SECONDS: u64 = INTERACTION_TOKEN_TTL_SECS - 5;
+const REVIVE_HEALTH: usize = 2;
+const DEFEND_DODGE: isize = 30;
+const FOCUS_ACCURACY: isize = 40;
+
+/// Order of the systems in a frame. Inputs reach the battle on the frame they are sent and the rolls
+/// happen in a fixed order, otherwise a replay could not play the battle out the same way.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, SystemLabel)]
+pub enum BattleLabel {
+    /// Everything that turns the frontend's inputs into battle events
+    Input,
+    Listen,
+    DelayEvents,
+    Render,
+    DamageBygone,
+    /// All of the abilities, they write to the battle log and take turns so a replay logs them the same way
+    Abilities,
+    HealPlayers,
+    DefendPlayers,
+    RevivePlayers,
+    FocusedShot,
+    StatusEffects,
+    PartDeath,
+    DamagePlayers,
+    ReadyPlayers,
+}
+
+/// The battles themselves, the same in the live game, its replays and the simulations. The apps
+/// bring their own inputs and whatever they do with the outcome.
+pub struct BattlePlugin;
+
+impl Plugin for BattlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            spawn_bygones
+                .after(BattleLabel::Input)
+                .before(BattleLabel::Render),
+        )
+        .add_system(
+            spawn_players
+                .after(BattleLabel::Input)
+                .before(BattleLabel::Render),
+        )
+        .add_system(
+            damage_bygone
+                .label(BattleLabel::DamageBygone)
+                .after(BattleLabel::Input)
+                .before(BattleLabel::Render),
+        )
+        .add_system(
+            cool_down_abilities
+                .after(BattleLabel::Input)
+                .before(BattleLabel::Abilities)
+                .before(BattleLabel::Render),
+        )
+        .add_system(
+            heal_players
+                .label(BattleLabel::Abilities)
+                .label(BattleLabel::HealPlayers)
+                .after(BattleLabel::Input)
+                .after(BattleLabel::DamageBygone)
+                .before(BattleLabel::Render),
+        )
+        .add_system(
+            defend_players
+                .label(BattleLabel::Abilities)
+                .label(BattleLabel::DefendPlayers)
+                .after(BattleLabel::HealPlayers)
+                .before(BattleLabel::Render),
+        )
+        .add_system(
+            revive_players
+                .label(BattleLabel::Abilities)
+                .label(BattleLabel::RevivePlayers)
+                .after(BattleLabel::DefendPlayers)
+                .before(BattleLabel::Render),
+        )
+        .add_system(
+            focused_shot
+                .label(BattleLabel::Abilities)
+                .label(BattleLabel::FocusedShot)
+                .after(BattleLabel::RevivePlayers)
+                .before(BattleLabel::Render),
+        )
+        .add_system(
+            tick_status_effects
+                .label(BattleLabel::StatusEffects)
+                .after(BattleLabel::Input)
+                .after(BattleLabel::ReadyPlayers)
+                .before(BattleLabel::Render),
+        )
+        .add_system(
+            process_bygone_part_death
+                .label(BattleLabel::PartDeath)
+                .after(BattleLabel::DamageBygone)
+                .after(BattleLabel::FocusedShot)
+                .after(BattleLabel::StatusEffects)
+                .before(BattleLabel::Render),
+        )
+        .add_system(
+            damage_players
+                .label(BattleLabel::DamagePlayers)
+                .after(BattleLabel::Input)
+                .after(BattleLabel::PartDeath)
+                .after(BattleLabel::Abilities)
+                .before(BattleLabel::Render),
+        )
+        .add_system(
+            apply_status_effects
+                .after(BattleLabel::DamagePlayers)
+                .before(BattleLabel::Render),
+        )
+        .add_system(deactivate.before(BattleLabel::Render))
+        // Deaths of the last frame settle before the new inputs come in, whatever else reads the games
+        .add_system(update_game_status.before(BattleLabel::Input))
+        .add_system(
+            ready_players
+                .label(BattleLabel::ReadyPlayers)
+                .after(BattleLabel::Input),
+        );
+    }
+}
+
+/// Actions of a strict battle wait for the rest of the turn instead of resolving right away
+fn is_strict(games: &HashMap<ArenaId, Game>, game_id: GameId) -> bool {
+    games
+        .values()
+        .any(|game| game.id == game_id && game.turns == TurnMode::Strict)
+}
+
+/// Oneshots answer the user who asked, so they speak the user's language rather than the battle's
+fn send_oneshot(
+    game_render_sender: &Mutex<Sender<GameRenderEvent>>,
+    arena: ArenaId,
+    interaction: Id<InteractionMarker>,
+    loc: &Localization,
+    oneshot_type: OneshotType,
+) {
+    if let Ok(game_render_sender_lock) = game_render_sender.lock() {
+        if let Err(err) = game_render_sender_lock.send(GameRenderEvent::new(
+            arena,
+            interaction,
+            loc.clone(),
+            GameRenderPayload::OneshotMessage(oneshot_type),
+        )) {
+            println!(
+                "{} - systems - FAILED to send render oneshot event: {}",
+                format_time(),
+                err
+            );
+        }
+    }
+}
 
+#[allow(clippy::type_complexity)]
 pub fn listen(
     input_receiver: Mutex<Receiver<InputEvent>>,
     game_render_sender: Mutex<Sender<GameRenderEvent>>,
 ) -> impl FnMut(
-    ResMut<HashMap<Id<GuildMarker>, Game>>,
-    ResMut<HashMap<Id<GuildMarker>, Vec<String>>>,
+    ResMut<HashMap<ArenaId, Game>>,
+    ResMut<HashMap<ArenaId, Vec<String>>>,
+    ResMut<GlobalRng>,
+    Res<Clock>,
+    Res<GameScope>,
+    (
+        Res<EnemyDefinitions>,
+        Res<TimingConfig>,
+        Res<Localizations>,
+        ResMut<GuildConfigs>,
+    ),
     EventWriter<GameStartEvent>,
     EventWriter<(GameId, PlayerAttackEvent)>,
+    EventWriter<(GameId, PlayerAbilityEvent)>,
+    EventWriter<(GameId, PlayerAction)>,
     EventWriter<DelayedEvent>,
     EventWriter<PlayerJoinEvent>,
     EventWriter<BygoneSpawnEvent>,
-    EventWriter<DeallocateGameResourcesEvent>,
-    // BUG: Should add GameId here
-    // Otherwise collisions will occur when the same player joins several battles
-    Query<(&UserIdComponent, Option<&Active>), (With<Player>,)>,
+    (
+        EventWriter<DeallocateGameResourcesEvent>,
+        EventWriter<GameEndEvent>,
+    ),
+    EventWriter<LeaderboardRequestEvent>,
+    Query<(&UserIdComponent, &GameId, Option<&Active>), (With<Player>,)>,
 ) {
     move |mut games,
           mut battle_log,
+          mut global_rng,
+          clock,
+          scope,
+          (enemies, timings, localizations, mut configs),
           mut ev_game_start,
           mut ev_player_attack,
+          mut ev_player_ability,
+          mut ev_player_action,
           mut ev_delayed,
           mut ev_player_join,
           mut ev_bygone_spawn,
-          mut ev_deallocate_game_resources,
+          (mut ev_deallocate_game_resources, mut ev_game_end),
+          mut ev_leaderboard,
           players| {
         let mut events = Vec::new();
         if let Ok(ref mut receiver_lock) = input_receiver.try_lock() {
@@ -61,22 +242,57 @@ pub fn listen(
         }
         let events = events;
 
-        let players: HashMap<_, _> = players.iter().collect();
+        // A user has a separate player in every battle they joined
+        let players: HashMap<_, _> = players
+            .iter()
+            .map(|(user_id, game_id, active)| ((user_id.0, *game_id), active))
+            .collect();
 
         for (i, event) in events.into_iter().enumerate() {
             match event {
-                InputEvent::GameStart(ev) => {
-                    let oneshot_type = match games.get(&ev.guild_id) {
+                InputEvent::GameStart(mut ev) => {
+                    let config = configs.get(ev.arena.guild_id);
+                    ev.localization = localizations
+                        .get(ev.language.resolve(config.language))
+                        .clone();
+                    let personal = localizations.get(ev.language.personal(config.language));
+                    if !config.allows(ev.arena.channel_id) {
+                        send_oneshot(
+                            &game_render_sender,
+                            ev.arena,
+                            ev.interaction,
+                            personal,
+                            OneshotType::ChannelNotAllowed,
+                        );
+                        continue;
+                    }
+                    ev.arena = scope.arena(ev.arena);
+                    let difficulty = *ev
+                        .difficulty
+                        .get_or_insert(config.difficulty.unwrap_or(Difficulty::Medium));
+                    if !enemies.contains(&ev.enemy) {
+                        println!(
+                            "{} - systems - ERROR starting game: unknown enemy {}, fighting {} instead",
+                            format_time(),
+                            ev.enemy,
+                            DEFAULT_ENEMY,
+                        );
+                        ev.enemy = DEFAULT_ENEMY.to_owned();
+                    }
+                    let oneshot_type = match games.get(&ev.arena) {
+                        // A guild's cooldown can be shorter than a battle, that doesn't cut the battle short
+                        Some(game) if game.status == GameStatus::Ongoing => {
+                            Some(OneshotType::OtherGameInProgress)
+                        }
                         Some(game) => {
-                            let game_duration = game.duration_secs();
-                            if game_duration < GAME_COOLDOWN_SECONDS {
-                                if game.status == GameStatus::Ongoing {
-                                    Some(OneshotType::OtherGameInProgress)
-                                } else {
-                                    Some(OneshotType::Cooldown(Duration::from_secs(
-                                        GAME_COOLDOWN_SECONDS - game_duration,
-                                    )))
-                                }
+                            let game_duration = game.duration_secs(&clock);
+                            let cooldown = config.cooldown_secs.unwrap_or_else(|| {
+                                timings.guild(ev.arena.guild_id).game_cooldown_secs
+                            });
+                            if game_duration < cooldown {
+                                Some(OneshotType::Cooldown(Duration::from_secs(
+                                    cooldown - game_duration,
+                                )))
                             } else {
                                 None
                             }
@@ -84,45 +300,71 @@ pub fn listen(
                         None => None,
                     };
                     if let Some(oneshot_type) = oneshot_type {
-                        if let Ok(game_render_sender_lock) = game_render_sender.lock() {
-                            if let Err(err) = game_render_sender_lock.send(GameRenderEvent::new(
-                                ev.guild_id,
-                                ev.interaction,
-                                ev.localization.clone(),
-                                GameRenderPayload::OneshotMessage(oneshot_type),
-                            )) {
-                                println!(
-                                    "{} - systems - FAILED to send render oneshot event: {}",
-                                    format_time(),
-                                    err
-                                );
-                            }
-                        }
+                        send_oneshot(
+                            &game_render_sender,
+                            ev.arena,
+                            ev.interaction,
+                            personal,
+                            oneshot_type,
+                        );
                     } else {
                         let new_game_id = GameId::from_current_time(i as u128);
+                        let seed = global_rng.u64(..);
                         let old_game = games.insert(
-                            ev.guild_id,
-                            Game::new(new_game_id, ev.interaction, ev.localization.clone()),
+                            ev.arena,
+                            Game::new(
+                                new_game_id,
+                                ev.interaction,
+                                ev.localization.clone(),
+                                ev.enemy.clone(),
+                                seed,
+                                clock.now(),
+                            )
+                            .with_mode(ev.mode, difficulty)
+                            .with_turns(ev.turns)
+                            .with_starter(ev.initial_player),
                         );
                         if let Some(old_game) = old_game {
                             ev_deallocate_game_resources
                                 .send(DeallocateGameResourcesEvent::new(old_game.id));
                         }
-                        battle_log.remove(&ev.guild_id);
-                        ev_game_start.send(ev.clone());
+                        battle_log.remove(&ev.arena);
+                        ev_game_start.send((*ev).clone());
                         ev_player_join.send(PlayerJoinEvent::new(
                             ev.initial_player,
                             ev.initial_player_name,
+                            ev.class,
+                            new_game_id,
+                            ev.arena,
+                        ));
+                        ev_bygone_spawn.send(BygoneSpawnEvent::new(
+                            ev.enemy.clone(),
+                            difficulty,
                             new_game_id,
-                            ev.guild_id,
                         ));
-                        ev_bygone_spawn.send(BygoneSpawnEvent::new(ev.difficulty, new_game_id));
-                        ev_delayed.send(DelayedEvent::GameDraw(GameDrawEvent::new(ev.guild_id)));
+                        ev_delayed.send(DelayedEvent::GameDraw(GameDrawEvent::new(ev.arena)));
                     }
                 }
-                InputEvent::PlayerAttack(ev) => {
-                    if let Some(game) = games.get(&ev.guild_id) {
-                        match players.get(&UserIdComponent(ev.player)) {
+                InputEvent::PlayerAttack(mut ev) => {
+                    ev.arena = scope.arena(ev.arena);
+                    if let Some(game) = games.get(&ev.arena) {
+                        match enemies
+                            .in_slot(&game.enemy, ev.enemy)
+                            .and_then(|enemy| enemy.find_part(&ev.target.0))
+                        {
+                            Some(part) => ev.target = part.clone(),
+                            None => {
+                                println!(
+                                    "{} - systems - ERROR processing attack event for player id {}: unknown part {} of enemy {}",
+                                    format_time(),
+                                    ev.player,
+                                    ev.target,
+                                    ev.enemy.0,
+                                );
+                                continue;
+                            }
+                        }
+                        match players.get(&(ev.player, game.id)) {
                             Some(maybe_active) => {
                                 if let Some(_active) = maybe_active {
                                     println!(
@@ -130,7 +372,11 @@ pub fn listen(
                                         format_time(),
                                         ev.player,
                                     );
-                                    ev_player_attack.send((game.id, ev));
+                                    if is_strict(&games, game.id) {
+                                        ev_player_action.send((game.id, PlayerAction::Attack(ev)));
+                                    } else {
+                                        ev_player_attack.send((game.id, ev));
+                                    }
                                 } else {
                                     println!(
                                         "{} - systems - ERROR processing attack event for player id {}: player is not active",
@@ -146,261 +392,1283 @@ pub fn listen(
                                     ev.player,
                                 );
                                 ev_player_join.send(PlayerJoinEvent::new(
-                                    ev.player.clone(),
+                                    ev.player,
                                     ev.player_name.clone(),
+                                    PlayerClass::default(),
                                     game.id,
-                                    ev.guild_id,
+                                    ev.arena,
                                 ));
                                 ev_delayed.send(DelayedEvent::PlayerAttack((game.id, ev)));
                             }
                         }
                     } else {
                         println!(
-                            "{} - systems - ERROR processing attack event for player id {}: no game for guild id {}",
+                            "{} - systems - ERROR processing attack event for player id {}: no game in arena {}",
+                            format_time(),
+                            ev.player,
+                            ev.arena,
+                        );
+                    }
+                }
+                InputEvent::PlayerAbility(mut ev) => {
+                    ev.arena = scope.arena(ev.arena);
+                    if let Some(game) = games.get(&ev.arena) {
+                        if game.status != GameStatus::Ongoing {
+                            println!(
+                                "{} - systems - ERROR processing ability event for player id {}: the battle in arena {} is over",
+                                format_time(),
+                                ev.player,
+                                ev.arena,
+                            );
+                            continue;
+                        }
+                        if ev.ability == Ability::FocusedShot {
+                            let target = ev.target.as_ref().and_then(|(slot, part)| {
+                                enemies
+                                    .in_slot(&game.enemy, *slot)
+                                    .and_then(|enemy| enemy.find_part(&part.0))
+                                    .map(|part| (*slot, part.clone()))
+                            });
+                            match target {
+                                Some(target) => ev.target = Some(target),
+                                None => {
+                                    println!(
+                                        "{} - systems - ERROR processing ability event for player id {}: unknown target {:?}",
+                                        format_time(),
+                                        ev.player,
+                                        ev.target,
+                                    );
+                                    continue;
+                                }
+                            }
+                        }
+                        match players.get(&(ev.player, game.id)) {
+                            Some(Some(_active)) => {
+                                println!(
+                                    "{} - systems - Processing {:?} ability event for player id: {}",
+                                    format_time(),
+                                    ev.ability,
+                                    ev.player,
+                                );
+                                if is_strict(&games, game.id) {
+                                    ev_player_action.send((game.id, PlayerAction::Ability(ev)));
+                                } else {
+                                    ev_player_ability.send((game.id, ev));
+                                }
+                            }
+                            Some(None) => {
+                                println!(
+                                    "{} - systems - ERROR processing ability event for player id {}: player is not active",
+                                    format_time(),
+                                    ev.player,
+                                );
+                            }
+                            None => {
+                                println!(
+                                    "{} - systems - Processing join event for player id: {}",
+                                    format_time(),
+                                    ev.player,
+                                );
+                                ev_player_join.send(PlayerJoinEvent::new(
+                                    ev.player,
+                                    ev.player_name.clone(),
+                                    PlayerClass::default(),
+                                    game.id,
+                                    ev.arena,
+                                ));
+                                ev_delayed.send(DelayedEvent::PlayerAbility((game.id, ev)));
+                            }
+                        }
+                    } else {
+                        println!(
+                            "{} - systems - ERROR processing ability event for player id {}: no game in arena {}",
+                            format_time(),
+                            ev.player,
+                            ev.arena,
+                        );
+                    }
+                }
+                InputEvent::PlayerJoin(mut ev) => {
+                    ev.arena = scope.arena(ev.arena);
+                    if let Some(game) = games.get(&ev.arena) {
+                        if players.contains_key(&(ev.player, game.id)) {
+                            println!(
+                                "{} - systems - ERROR processing join event for player id {}: player is already in the battle",
+                                format_time(),
+                                ev.player,
+                            );
+                        } else {
+                            println!(
+                                "{} - systems - Processing join event for player id {} as {}",
+                                format_time(),
+                                ev.player,
+                                ev.class,
+                            );
+                            ev_player_join.send(PlayerJoinEvent::new(
+                                ev.player,
+                                ev.player_name,
+                                ev.class,
+                                game.id,
+                                ev.arena,
+                            ));
+                        }
+                    } else {
+                        println!(
+                            "{} - systems - ERROR processing join event for player id {}: no game in arena {}",
                             format_time(),
                             ev.player,
-                            ev.guild_id,
+                            ev.arena,
                         );
                     }
                 }
+                InputEvent::Leaderboard(mut ev) => {
+                    ev.arena = scope.arena(ev.arena);
+                    let config = configs.get(ev.arena.guild_id);
+                    ev.localization = localizations
+                        .get(ev.language.resolve(config.language))
+                        .clone();
+                    ev_leaderboard.send(*ev);
+                }
+                InputEvent::Config(ev) => {
+                    let oneshot_type = if ev.manage_guild {
+                        let config = configs.0.entry(ev.arena.guild_id).or_default();
+                        config.apply(&ev.update);
+                        println!(
+                            "{} - systems - Configured guild {}: {:?}",
+                            format_time(),
+                            ev.arena.guild_id,
+                            config,
+                        );
+                        OneshotType::Config(config.clone())
+                    } else {
+                        println!(
+                            "{} - systems - ERROR configuring guild {}: the member can't manage the guild",
+                            format_time(),
+                            ev.arena.guild_id,
+                        );
+                        OneshotType::NotPermitted
+                    };
+                    // Without the user's locale a new default language already speaks in the answer
+                    let config = configs.get(ev.arena.guild_id);
+                    send_oneshot(
+                        &game_render_sender,
+                        ev.arena,
+                        ev.interaction,
+                        localizations.get(ev.language.personal(config.language)),
+                        oneshot_type,
+                    );
+                }
+                InputEvent::GameEnd(mut ev) => {
+                    ev.arena = scope.arena(ev.arena);
+                    let config = configs.get(ev.arena.guild_id);
+                    let oneshot_type = match games.get_mut(&ev.arena) {
+                        Some(game) if game.status == GameStatus::Ongoing => {
+                            if config.can_end(game, ev.player) {
+                                println!(
+                                    "{} - systems - Ending game in arena {} for player id {}",
+                                    format_time(),
+                                    ev.arena,
+                                    ev.player,
+                                );
+                                let finished_status = game.ending(FinishedGameStatus::Ended);
+                                game.status = finished_status.into();
+                                ev_game_end.send(GameEndEvent::new(
+                                    ev.arena,
+                                    game.id,
+                                    finished_status,
+                                ));
+                                ev_delayed
+                                    .send(DelayedEvent::GameDraw(GameDrawEvent::new(ev.arena)));
+                                OneshotType::GameEnded
+                            } else {
+                                println!(
+                                    "{} - systems - ERROR ending game in arena {} for player id {}: only the starter can end it",
+                                    format_time(),
+                                    ev.arena,
+                                    ev.player,
+                                );
+                                OneshotType::NotAllowedToEnd
+                            }
+                        }
+                        _ => OneshotType::NoGameToEnd,
+                    };
+                    send_oneshot(
+                        &game_render_sender,
+                        ev.arena,
+                        ev.interaction,
+                        localizations.get(ev.language.personal(config.language)),
+                        oneshot_type,
+                    );
+                }
             }
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn delay_events(
-    delay: Res<EventDelay>,
-    mut buffer: Local<VecDeque<(Instant, DelayedEvent)>>,
+    timings: Res<TimingConfig>,
+    clock: Res<Clock>,
+    games: Res<HashMap<ArenaId, Game>>,
+    mut buffer: Local<VecDeque<(SystemTime, DelayedEvent)>>,
     mut ev_delayed: EventReader<DelayedEvent>,
     mut ev_game_draw: EventWriter<GameDrawEvent>,
     mut ev_player_attack: EventWriter<(GameId, PlayerAttackEvent)>,
+    mut ev_player_ability: EventWriter<(GameId, PlayerAbilityEvent)>,
+    mut ev_player_action: EventWriter<(GameId, PlayerAction)>,
 ) {
     let ready_count = buffer
         .iter()
-        .take_while(|(start, _)| start.elapsed() > delay.0)
+        .take_while(|(start, _)| clock.elapsed_since(*start) > timings.base.event_delay())
         .count();
     for _ in 0..ready_count {
         match buffer.pop_front().unwrap().1 {
             DelayedEvent::GameDraw(ev) => ev_game_draw.send(ev),
+            DelayedEvent::PlayerAttack((game_id, ev)) if is_strict(&games, game_id) => {
+                ev_player_action.send((game_id, PlayerAction::Attack(ev)))
+            }
+            DelayedEvent::PlayerAbility((game_id, ev)) if is_strict(&games, game_id) => {
+                ev_player_action.send((game_id, PlayerAction::Ability(ev)))
+            }
             DelayedEvent::PlayerAttack(ev) => ev_player_attack.send(ev),
+            DelayedEvent::PlayerAbility(ev) => ev_player_ability.send(ev),
         }
     }
 
     for ev in ev_delayed.iter() {
-        buffer.push_back((Instant::now(), ev.clone()));
+        buffer.push_back((clock.now(), ev.clone()));
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn turn_timer(
-    mut timers: Local<HashMap<(Id<GuildMarker>, GameId), GameTimer>>,
+    clock: Res<Clock>,
+    timings: Res<TimingConfig>,
+    games: Res<HashMap<ArenaId, Game>>,
+    mut timers: Local<HashMap<(ArenaId, GameId), GameTimer>>,
     mut ev_player_attack: EventReader<(GameId, PlayerAttackEvent)>,
+    mut ev_player_ability: EventReader<(GameId, PlayerAbilityEvent)>,
     mut ev_enemy_attack: EventWriter<EnemyAttackEvent>,
     mut ev_game_draw: EventWriter<GameDrawEvent>,
     mut ev_turn_end: EventWriter<TurnEndEvent>,
     mut ev_progress_bar_update: EventWriter<ProgressBarUpdateEvent>,
 ) {
-    for ((guild_id, game_id), timer) in timers.iter_mut() {
-        if timer.enemy_attack() {
-            ev_enemy_attack.send(EnemyAttackEvent::new(*guild_id, *game_id));
+    for ((arena, game_id), timer) in timers.iter_mut() {
+        if timer.enemy_attack(&clock) {
+            ev_enemy_attack.send(EnemyAttackEvent::new(*arena, *game_id));
         }
-        if timer.turn_end() {
+        if timer.turn_end(&clock) {
             ev_turn_end.send(TurnEndEvent::new(*game_id));
-            ev_game_draw.send(GameDrawEvent::new(*guild_id));
+            ev_game_draw.send(GameDrawEvent::new(*arena));
         }
-        if let Some(progress) = timer.progress_bar_update() {
-            ev_progress_bar_update.send(ProgressBarUpdateEvent::new(*guild_id, progress));
+        if let Some(progress) = timer.progress_bar_update(&clock) {
+            ev_progress_bar_update.send(ProgressBarUpdateEvent::new(*arena, progress));
         }
     }
 
     timers.retain(|_, timer| !timer.depleted());
 
-    for (game_id, ev) in ev_player_attack.iter() {
+    // Strict turns keep their own time
+    let actions = ev_player_attack
+        .iter()
+        .map(|(game_id, ev)| (ev.arena, *game_id))
+        .chain(
+            ev_player_ability
+                .iter()
+                .map(|(game_id, ev)| (ev.arena, *game_id)),
+        )
+        .filter(|(_, game_id)| !is_strict(&games, *game_id));
+    for (arena, game_id) in actions {
         timers
-            .entry((ev.guild_id, *game_id))
-            .or_insert(GameTimer::new());
+            .entry((arena, game_id))
+            .or_insert_with(|| GameTimer::new(&clock, timings.guild(arena.guild_id)));
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn strict_turns(
+    mut commands: Commands,
+    clock: Res<Clock>,
+    timings: Res<TimingConfig>,
+    games: Res<HashMap<ArenaId, Game>>,
+    mut turns: Local<HashMap<GameId, StrictTurn>>,
+    mut ev_player_action: EventReader<(GameId, PlayerAction)>,
+    mut ev_player_attack: EventWriter<(GameId, PlayerAttackEvent)>,
+    mut ev_player_ability: EventWriter<(GameId, PlayerAbilityEvent)>,
+    mut ev_enemy_attack: EventWriter<EnemyAttackEvent>,
+    mut ev_game_draw: EventWriter<GameDrawEvent>,
+    mut ev_turn_end: EventWriter<TurnEndEvent>,
+    players: Query<
+        (
+            Entity,
+            &UserIdComponent,
+            &GameId,
+            &PlayerClass,
+            &StatusEffects,
+        ),
+        (With<Player>, With<Active>),
+    >,
+) {
+    for (game_id, action) in ev_player_action.iter() {
+        let player = players.iter().find(|(_, user_id, player_game_id, _, _)| {
+            user_id.0 == action.player() && **player_game_id == *game_id
+        });
+        let (entity, class, effects) = match player {
+            Some((entity, _, _, class, effects)) => (entity, class, effects),
+            None => {
+                println!(
+                    "{} - systems - ERROR queueing action for player id {}: player is not active",
+                    format_time(),
+                    action.player(),
+                );
+                continue;
+            }
+        };
+        if effects.has(StatusEffect::Stunned) {
+            println!(
+                "{} - systems - ERROR queueing action for player id {}: player is stunned",
+                format_time(),
+                action.player(),
+            );
+            continue;
+        }
+        let turn = turns
+            .entry(*game_id)
+            .or_insert_with(|| StrictTurn::new(action.arena(), &clock));
+        if turn.submit(class.initiative(), action.player(), action.clone()) {
+            println!(
+                "{} - systems - Queueing action for player id: {}",
+                format_time(),
+                action.player(),
+            );
+            commands.entity(entity).insert(Ready);
+        } else {
+            println!(
+                "{} - systems - ERROR queueing action for player id {}: player has already acted this turn",
+                format_time(),
+                action.player(),
+            );
+        }
+    }
+
+    for (game_id, turn) in turns.iter_mut() {
+        // Stunned players sit the turn out, nobody waits for them
+        let everyone_submitted = players
+            .iter()
+            .filter(|(_, _, player_game_id, _, effects)| {
+                **player_game_id == *game_id && !effects.has(StatusEffect::Stunned)
+            })
+            .all(|(_, user_id, _, _, _)| turn.submitted(user_id.0));
+        let timeout = timings.guild(turn.arena.guild_id).turn_timeout();
+        match turn.step(&clock, timeout, everyone_submitted) {
+            StrictStep::Wait => {}
+            StrictStep::Act(PlayerAction::Attack(ev)) => ev_player_attack.send((*game_id, ev)),
+            StrictStep::Act(PlayerAction::Ability(ev)) => ev_player_ability.send((*game_id, ev)),
+            StrictStep::EnemyAttack => {
+                ev_enemy_attack.send(EnemyAttackEvent::new(turn.arena, *game_id))
+            }
+            StrictStep::TurnEnd => {
+                ev_turn_end.send(TurnEndEvent::new(*game_id));
+                ev_game_draw.send(GameDrawEvent::new(turn.arena));
+            }
+        }
     }
+
+    turns.retain(|game_id, turn| {
+        !turn.ended()
+            && matches!(games.get(&turn.arena), Some(game) if game.id == *game_id && game.status == GameStatus::Ongoing)
+    });
 }
 
 pub fn spawn_bygones(
     mut commands: Commands,
-    mut global_rng: ResMut<GlobalRng>,
+    enemies: Res<EnemyDefinitions>,
+    games: Res<HashMap<ArenaId, Game>>,
     mut ev_game_start: EventReader<BygoneSpawnEvent>,
 ) {
     for ev in ev_game_start.iter() {
-        commands.spawn_bundle(Bygone03Bundle::with_difficulty(
-            ev.game_id,
-            ev.difficulty,
-            &mut global_rng,
-        ));
+        let game = match games.values().find(|game| game.id == ev.game_id) {
+            Some(game) => game,
+            None => {
+                println!(
+                    "{} - systems - ERROR spawning enemies for game {}: no such game",
+                    format_time(),
+                    ev.game_id.0
+                );
+                continue;
+            }
+        };
+        for (slot, enemy) in enemies.encounter(&ev.enemy).into_iter().enumerate() {
+            commands.spawn_bundle(
+                BygoneBundle::with_difficulty(
+                    EnemyKind(enemy.to_owned()),
+                    EnemySlot(slot),
+                    enemies.get(enemy),
+                    ev.game_id,
+                    ev.difficulty,
+                    game.enemy_seed(EnemySlot(slot)),
+                )
+                .in_wave(ev.wave),
+            );
+        }
     }
 }
 
-pub fn spawn_players(mut commands: Commands, mut ev_player_join: EventReader<PlayerJoinEvent>) {
+pub fn spawn_players(
+    mut commands: Commands,
+    games: Res<HashMap<ArenaId, Game>>,
+    mut ev_player_join: EventReader<PlayerJoinEvent>,
+) {
     for ev in ev_player_join.iter() {
-        commands.spawn_bundle(PlayerBundle::new(
+        let mut entity = commands.spawn_bundle(PlayerBundle::new(
             ev.player,
             ev.player_name.clone(),
             ev.game_id,
+            ev.class,
         ));
+        // In a strict battle Ready means the player has acted this turn
+        if is_strict(&games, ev.game_id) {
+            entity.remove::<Ready>();
+        }
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn damage_bygone(
     mut commands: Commands,
-    mut rng: ResMut<GlobalRng>,
+    definitions: Res<EnemyDefinitions>,
     mut ev_player_attack: EventReader<(GameId, PlayerAttackEvent)>,
     mut ev_part_death: EventWriter<BygonePartDeathEvent>,
-    mut ev_battle_log: EventWriter<(Id<GuildMarker>, BattleLogEvent)>,
+    mut ev_battle_log: EventWriter<(ArenaId, BattleLogEvent)>,
     mut actors: ParamSet<(
         Query<
-            (Entity, &UserIdComponent, &PlayerName, &GameId, &Attack),
+            (
+                Entity,
+                &UserIdComponent,
+                &PlayerName,
+                &GameId,
+                &Attack,
+                &mut Threat,
+            ),
             (With<Player>, With<Active>, With<Ready>),
         >,
-        Query<(Entity, &GameId, &mut BygoneParts), (With<Enemy>, With<Active>)>,
+        Query<
+            (
+                Entity,
+                &GameId,
+                &EnemySlot,
+                &EnemyKind,
+                &BygoneStage,
+                &mut BygoneParts,
+                &mut SpecialMoves,
+                &mut RngComponent,
+            ),
+            (With<Enemy>, With<Active>),
+        >,
     )>,
 ) {
     let target_parts: HashMap<_, _> = ev_player_attack
         .iter()
-        .map(|(game_id, ev)| ((ev.player, *game_id), (ev.guild_id, ev.target)))
-        .collect();
-
-    let attacks: HashMap<_, _> = actors
-        .p0()
-        .iter()
-        .filter(|(_, user_id, _, game_id, _)| target_parts.contains_key(&(user_id.0, **game_id)))
-        .map(|(entity, user_id, user_name, game_id, attack)| {
-            (*game_id, (entity, *user_id, user_name.clone(), *attack))
+        .map(|(game_id, ev)| {
+            (
+                (ev.player, *game_id),
+                (ev.arena, ev.enemy, ev.target.clone()),
+            )
         })
         .collect();
 
-    for (bygone_entity, enemy_game_id, mut body_parts) in actors.p1().iter_mut() {
-        if let Some((user_entity, user_id, user_name, attack)) = attacks.get(enemy_game_id) {
-            if let Some((guild_id, part)) = target_parts.get(&(user_id.0, *enemy_game_id)) {
-                if !body_parts.0[*part].health().alive() {
+    let mut attacks: HashMap<GameId, Vec<_>> = HashMap::new();
+    for (entity, user_id, user_name, game_id, attack, _) in actors.p0().iter() {
+        if target_parts.contains_key(&(user_id.0, *game_id)) {
+            attacks.entry(*game_id).or_default().push((
+                entity,
+                *user_id,
+                user_name.clone(),
+                *attack,
+            ));
+        }
+    }
+    // Attacks of the same frame roll in a fixed order, a replay has to roll them the same way
+    for game_attacks in attacks.values_mut() {
+        game_attacks.sort_by_key(|(_, user_id, _, _)| user_id.0);
+    }
+
+    let mut dealt = Vec::new();
+    let mut bygones = actors.p1();
+    let mut bygones: Vec<_> = bygones.iter_mut().collect();
+    bygones.sort_by_key(|(_, game_id, slot, ..)| (**game_id, **slot));
+    for (bygone_entity, enemy_game_id, slot, kind, stage, body_parts, specials, rng) in
+        bygones.iter_mut()
+    {
+        let game_attacks = match attacks.get(enemy_game_id) {
+            Some(game_attacks) => game_attacks,
+            None => continue,
+        };
+        for (user_entity, user_id, user_name, attack) in game_attacks.iter() {
+            if let Some((arena, target_slot, part)) =
+                target_parts.get(&(user_id.0, **enemy_game_id))
+            {
+                if *target_slot != **slot {
+                    continue;
+                }
+                let target = match body_parts.get_mut(part) {
+                    Some(target) if target.health().alive() => target,
+                    _ => continue,
+                };
+                commands.entity(*user_entity).remove::<Ready>();
+                if specials.shielded.as_ref() == Some(part) {
+                    ev_battle_log.send((
+                        *arena,
+                        BattleLogEvent::PartShielded(user_name.clone(), **slot, part.clone()),
+                    ));
                     continue;
                 }
                 let dice_roll = rng.d100();
-                println!(
-                    "{} - systems - Attacking bygone part, dodge {}, acc {}, roll {}",
-                    format_time(),
-                    body_parts.0[*part].dodge(),
-                    attack.accuracy(),
-                    dice_roll
-                );
-                if attack.attack(&mut body_parts.0[*part], dice_roll) {
+                let damage = rng.damage(attack);
+                if battle_traces() {
+                    println!(
+                        "{} - systems - Attacking bygone part, dodge {}, acc {}, roll {}, damage {}",
+                        format_time(),
+                        target.dodge(),
+                        attack.accuracy(),
+                        dice_roll,
+                        damage
+                    );
+                }
+                let multiplier = damage_taken(&definitions, kind, stage, part);
+                let health = target.health().current();
+                let hit = attack.attack(target, dice_roll, damage, multiplier);
+                dealt.push((*user_entity, health - target.health().current()));
+                ev_battle_log.send((
+                    *arena,
+                    player_hit_log(hit, (user_id.0, user_name), **slot, part),
+                ));
+                if hit != AttackOutcome::Miss && interrupt_special(specials, part) {
                     ev_battle_log.send((
-                        *guild_id,
-                        BattleLogEvent::PlayerHit(user_name.clone(), *part),
+                        *arena,
+                        BattleLogEvent::SpecialInterrupted(user_name.clone(), **slot),
+                    ));
+                }
+                if hit != AttackOutcome::Miss && !target.health().alive() {
+                    ev_part_death.send(BygonePartDeathEvent::new(
+                        *bygone_entity,
+                        part.clone(),
+                        *arena,
                     ));
-                    if !body_parts.0[*part].health().alive() {
-                        ev_part_death.send(BygonePartDeathEvent::new(
-                            bygone_entity,
-                            *part,
-                            *guild_id,
-                        ));
-                    }
-                } else {
-                    ev_battle_log.send((*guild_id, BattleLogEvent::PlayerMiss(user_name.clone())));
                 }
-                commands.entity(*user_entity).remove::<Ready>();
             }
         }
     }
+
+    let mut players = actors.p0();
+    for (entity, damage) in dealt {
+        if let Ok(mut threat) = players.get_component_mut::<Threat>(entity) {
+            threat.0 += damage;
+        }
+    }
 }
 
+/// A hit on the part that calls off an announced charged shot does it, returns whether it did
+fn interrupt_special(specials: &mut SpecialMoves, part: &BygonePart) -> bool {
+    match &specials.telegraph {
+        Some(SpecialMove::ChargedShot { interrupted_by, .. }) if interrupted_by == part => {
+            specials.telegraph = None;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Percent of the damage the part takes in the enemy's current stage
+fn damage_taken(
+    definitions: &EnemyDefinitions,
+    kind: &EnemyKind,
+    stage: &BygoneStage,
+    part: &BygonePart,
+) -> usize {
+    definitions
+        .get(&kind.0)
+        .part(part)
+        .map(|part_definition| part_definition.damage_taken(stage))
+        .unwrap_or(100)
+}
+
+fn player_hit_log(
+    hit: AttackOutcome,
+    (user_id, name): (Id<UserMarker>, &PlayerName),
+    slot: EnemySlot,
+    part: &BygonePart,
+) -> BattleLogEvent {
+    match hit {
+        AttackOutcome::Miss => BattleLogEvent::PlayerMiss(user_id, name.clone()),
+        AttackOutcome::Hit => BattleLogEvent::PlayerHit(user_id, name.clone(), slot, part.clone()),
+        AttackOutcome::Crit => {
+            BattleLogEvent::PlayerCrit(user_id, name.clone(), slot, part.clone())
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
 pub fn process_bygone_part_death(
+    enemies: Res<EnemyDefinitions>,
     mut ev_part_death: EventReader<BygonePartDeathEvent>,
-    mut ev_battle_log: EventWriter<(Id<GuildMarker>, BattleLogEvent)>,
+    mut ev_battle_log: EventWriter<(ArenaId, BattleLogEvent)>,
     mut ev_deactivate: EventWriter<DeactivateEvent>,
+    mut ev_status_effect: EventWriter<StatusEffectEvent>,
     mut bygones: Query<
-        (Entity, &mut BygoneParts, &mut Attack, &mut Bygone03Stage),
+        (
+            Entity,
+            &EnemyKind,
+            &EnemySlot,
+            &mut BygoneParts,
+            &mut Attack,
+            &mut BygoneStage,
+        ),
         (With<Enemy>, With<Active>),
     >,
 ) {
     for BygonePartDeathEvent {
         entity,
         part,
-        guild_id,
+        arena,
     } in ev_part_death.iter()
     {
-        for (bygone_entity, ref mut parts, ref mut attack, ref mut stage) in bygones.iter_mut() {
+        for (bygone_entity, kind, slot, ref mut parts, ref mut attack, ref mut stage) in
+            bygones.iter_mut()
+        {
             if bygone_entity != *entity {
                 continue;
             }
-            match part {
-                BygonePart::Core => {
-                    **stage = stage.next();
-                    if stage.terminal() {
-                        ev_deactivate.send(DeactivateEvent(bygone_entity));
-                        ev_battle_log.send((*guild_id, BattleLogEvent::BygoneDead));
-                    } else {
-                        let core_max_health = parts.0[BygonePart::Core].health().max();
-                        let core_dodge = parts.0[BygonePart::Core].dodge();
-                        parts.0[BygonePart::Core] = Vitality::new(core_max_health, core_dodge);
+            let definition = enemies.get(&kind.0);
+            let effects = match definition.part(part) {
+                Some(part_definition) => &part_definition.on_destroyed,
+                None => continue,
+            };
+            for effect in effects.iter() {
+                match effect {
+                    PartEffect::NextStage => {
+                        **stage = definition.next_stage(stage);
+                        if definition.terminal(stage) {
+                            ev_deactivate.send(DeactivateEvent(bygone_entity));
+                            ev_battle_log.send((*arena, BattleLogEvent::BygoneDead(*slot)));
+                        } else {
+                            if let Some(vitality) = parts.get_mut(part) {
+                                *vitality =
+                                    Vitality::new(vitality.health().max(), vitality.dodge());
+                            }
+                            for effect in definition.stage_effects(stage) {
+                                ev_status_effect.send(StatusEffectEvent::new(
+                                    bygone_entity,
+                                    *arena,
+                                    *effect,
+                                ));
+                            }
+                        }
+                    }
+                    PartEffect::Accuracy(modifier) => {
+                        attack.modify_accuracy(*modifier);
+                    }
+                    PartEffect::Dodge(modifier) => {
+                        parts
+                            .values_mut()
+                            .for_each(|vitality| vitality.modify_dodge(*modifier));
+                    }
+                    PartEffect::Status(effect) => {
+                        ev_status_effect.send(StatusEffectEvent::new(
+                            bygone_entity,
+                            *arena,
+                            *effect,
+                        ));
                     }
-                }
-                BygonePart::Sensor => {
-                    attack.modify_accuracy(-40);
-                }
-                BygonePart::Gun => {
-                    attack.modify_accuracy(-25);
-                }
-                BygonePart::LeftWing | BygonePart::RightWing => {
-                    parts
-                        .0
-                        .values_mut()
-                        .for_each(|vitality| vitality.modify_dodge(-10));
                 }
             }
         }
     }
 }
 
-pub fn damage_players(
-    mut rng: ResMut<GlobalRng>,
-    mut ev_enemy_attack: EventReader<EnemyAttackEvent>,
-    mut ev_battle_log: EventWriter<(Id<GuildMarker>, BattleLogEvent)>,
-    mut ev_deactivate: EventWriter<DeactivateEvent>,
-    mut players: Query<(Entity, &GameId, &PlayerName, &mut Vitality), (With<Player>, With<Active>)>,
-    enemies: Query<(&GameId, &Attack), (With<Enemy>, With<Active>)>,
+/// Uses of one ability this frame in the order they resolve, a replay has to resolve them the same way
+fn ability_uses(
+    ev_player_ability: &mut EventReader<(GameId, PlayerAbilityEvent)>,
+    ability: Ability,
+) -> Vec<(GameId, PlayerAbilityEvent)> {
+    let mut uses: Vec<_> = ev_player_ability
+        .iter()
+        .filter(|(_, ev)| ev.ability == ability)
+        .cloned()
+        .collect();
+    uses.sort_by_key(|(game_id, ev)| (*game_id, ev.player));
+    uses
+}
+
+fn can_use(ready: Option<&Ready>, cooldowns: &Cooldowns, ev: &PlayerAbilityEvent) -> bool {
+    if ready.is_none() {
+        println!(
+            "{} - systems - ERROR processing {:?} ability for player id {}: player has already acted this turn",
+            format_time(),
+            ev.ability,
+            ev.player,
+        );
+        false
+    } else if !cooldowns.available(ev.ability) {
+        println!(
+            "{} - systems - ERROR processing {:?} ability for player id {}: ability is on cooldown",
+            format_time(),
+            ev.ability,
+            ev.player,
+        );
+        false
+    } else {
+        true
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn heal_players(
+    mut commands: Commands,
+    mut ev_player_ability: EventReader<(GameId, PlayerAbilityEvent)>,
+    mut ev_battle_log: EventWriter<(ArenaId, BattleLogEvent)>,
+    mut players: Query<
+        (
+            Entity,
+            &UserIdComponent,
+            &GameId,
+            &PlayerName,
+            &PlayerClass,
+            &mut Vitality,
+            &mut Cooldowns,
+            Option<&Ready>,
+        ),
+        (With<Player>, With<Active>),
+    >,
 ) {
-    for EnemyAttackEvent { guild_id, game_id } in ev_enemy_attack.iter() {
-        let mut players: Vec<_> = players
-            .iter_mut()
-            .filter(|(_, player_game_id, _, _)| *player_game_id == game_id)
-            .map(|(entity, _, name, vitality)| (entity, name, vitality))
-            .collect();
-        let enemies = enemies
-            .iter()
-            .filter(|(enemy_game_id, _)| *enemy_game_id == game_id);
-
-        for (_game_id, attack) in enemies {
-            if let Some((entity, name, target)) = choose_mut(&mut rng, &mut players) {
-                if attack.attack(target.deref_mut(), rng.d100()) {
-                    ev_battle_log.send((*guild_id, BattleLogEvent::BygoneHit(name.clone())));
-                    if !target.health().alive() {
-                        ev_deactivate.send(DeactivateEvent(*entity));
-                        ev_battle_log.send((*guild_id, BattleLogEvent::PlayerDead(name.clone())));
-                    }
-                } else {
-                    ev_battle_log.send((*guild_id, BattleLogEvent::BygoneMiss));
+    for (game_id, ev) in ability_uses(&mut ev_player_ability, Ability::Heal) {
+        let (healer, heal_amount) =
+            match players
+                .iter()
+                .find(|(_, user_id, player_game_id, _, _, _, _, _)| {
+                    user_id.0 == ev.player && **player_game_id == game_id
+                }) {
+                Some((entity, _, _, _, class, _, cooldowns, ready))
+                    if can_use(ready, cooldowns, &ev) =>
+                {
+                    (entity, class.heal_amount())
                 }
+                _ => continue,
+            };
+        // The one with the least health left gets healed, the healer included
+        let patient = players
+            .iter()
+            .filter(|(_, _, player_game_id, _, _, vitality, _, _)| {
+                **player_game_id == game_id && vitality.health().wounded()
+            })
+            .min_by_key(|(_, user_id, _, _, _, vitality, _, _)| {
+                (vitality.health().current(), user_id.0)
+            })
+            .map(|(entity, _, _, _, _, _, _, _)| entity);
+        let patient_name = match patient.and_then(|patient| players.get_mut(patient).ok()) {
+            Some((_, _, _, name, _, mut vitality, _, _)) => {
+                vitality.heal(heal_amount);
+                name.clone()
+            }
+            None => {
+                println!(
+                    "{} - systems - ERROR processing heal ability for player id {}: nobody is wounded",
+                    format_time(),
+                    ev.player,
+                );
+                continue;
             }
+        };
+        if let Ok((_, _, _, _, _, _, mut cooldowns, _)) = players.get_mut(healer) {
+            cooldowns.start(Ability::Heal);
         }
+        commands.entity(healer).remove::<Ready>();
+        ev_battle_log.send((
+            ev.arena,
+            BattleLogEvent::PlayerHeal(ev.player_name.clone(), patient_name),
+        ));
     }
 }
 
-pub fn deactivate(mut commands: Commands, mut ev_deactivate: EventReader<DeactivateEvent>) {
-    for ev in ev_deactivate.iter() {
-        commands.entity(ev.0).remove::<Active>();
+#[allow(clippy::type_complexity)]
+pub fn defend_players(
+    mut commands: Commands,
+    mut ev_player_ability: EventReader<(GameId, PlayerAbilityEvent)>,
+    mut ev_battle_log: EventWriter<(ArenaId, BattleLogEvent)>,
+    mut players: Query<
+        (
+            Entity,
+            &UserIdComponent,
+            &GameId,
+            &mut Cooldowns,
+            Option<&Ready>,
+        ),
+        (With<Player>, With<Active>),
+    >,
+) {
+    for (game_id, ev) in ability_uses(&mut ev_player_ability, Ability::Defend) {
+        if let Some((entity, _, _, mut cooldowns, ready)) =
+            players
+                .iter_mut()
+                .find(|(_, user_id, player_game_id, _, _)| {
+                    user_id.0 == ev.player && **player_game_id == game_id
+                })
+        {
+            if !can_use(ready, &cooldowns, &ev) {
+                continue;
+            }
+            cooldowns.start(Ability::Defend);
+            commands.entity(entity).insert(Defending).remove::<Ready>();
+            ev_battle_log.send((
+                ev.arena,
+                BattleLogEvent::PlayerDefend(ev.player_name.clone()),
+            ));
+        }
     }
 }
 
-pub fn update_game_status(
-    mut games: ResMut<HashMap<Id<GuildMarker>, Game>>,
-    mut ev_deactivate: EventReader<DeactivateEvent>,
+#[allow(clippy::type_complexity)]
+pub fn focused_shot(
+    mut commands: Commands,
+    definitions: Res<EnemyDefinitions>,
+    mut ev_player_ability: EventReader<(GameId, PlayerAbilityEvent)>,
+    mut ev_part_death: EventWriter<BygonePartDeathEvent>,
+    mut ev_battle_log: EventWriter<(ArenaId, BattleLogEvent)>,
+    mut players: Query<
+        (
+            Entity,
+            &UserIdComponent,
+            &GameId,
+            &Attack,
+            &mut Cooldowns,
+            &mut Threat,
+            Option<&Ready>,
+        ),
+        (With<Player>, With<Active>),
+    >,
+    mut bygones: Query<
+        (
+            Entity,
+            &GameId,
+            &EnemySlot,
+            &EnemyKind,
+            &BygoneStage,
+            &mut BygoneParts,
+            &mut SpecialMoves,
+            &mut RngComponent,
+        ),
+        (With<Enemy>, With<Active>),
+    >,
+) {
+    for (game_id, ev) in ability_uses(&mut ev_player_ability, Ability::FocusedShot) {
+        let (slot, part) = match &ev.target {
+            Some(target) => target.clone(),
+            None => continue,
+        };
+        let (shooter, attack, mut cooldowns, mut threat) =
+            match players
+                .iter_mut()
+                .find(|(_, user_id, player_game_id, _, _, _, _)| {
+                    user_id.0 == ev.player && **player_game_id == game_id
+                }) {
+                Some((entity, _, _, attack, cooldowns, threat, ready))
+                    if can_use(ready, &cooldowns, &ev) =>
+                {
+                    (entity, *attack, cooldowns, threat)
+                }
+                _ => continue,
+            };
+        let (bygone_entity, _, _, kind, stage, mut body_parts, mut specials, mut rng) =
+            match bygones
+                .iter_mut()
+                .find(|(_, enemy_game_id, enemy_slot, ..)| {
+                    **enemy_game_id == game_id && **enemy_slot == slot
+                }) {
+                Some(bygone) => bygone,
+                None => continue,
+            };
+        let target = match body_parts.get_mut(&part) {
+            Some(target) if target.health().alive() => target,
+            _ => continue,
+        };
+        cooldowns.start(Ability::FocusedShot);
+        commands.entity(shooter).remove::<Ready>();
+        ev_battle_log.send((
+            ev.arena,
+            BattleLogEvent::PlayerFocus(ev.player_name.clone()),
+        ));
+        if specials.shielded.as_ref() == Some(&part) {
+            ev_battle_log.send((
+                ev.arena,
+                BattleLogEvent::PartShielded(ev.player_name.clone(), slot, part),
+            ));
+            continue;
+        }
+        let mut attack = attack;
+        attack.modify_accuracy(FOCUS_ACCURACY);
+        let dice_roll = rng.d100();
+        let damage = rng.damage(&attack);
+        if battle_traces() {
+            println!(
+                "{} - systems - Focused shot at bygone part, dodge {}, acc {}, roll {}, damage {}",
+                format_time(),
+                target.dodge(),
+                attack.accuracy(),
+                dice_roll,
+                damage
+            );
+        }
+        let multiplier = damage_taken(&definitions, kind, stage, &part);
+        let health = target.health().current();
+        let hit = attack.attack(target, dice_roll, damage, multiplier);
+        threat.0 += health - target.health().current();
+        ev_battle_log.send((
+            ev.arena,
+            player_hit_log(hit, (ev.player, &ev.player_name), slot, &part),
+        ));
+        if hit != AttackOutcome::Miss && interrupt_special(&mut specials, &part) {
+            ev_battle_log.send((
+                ev.arena,
+                BattleLogEvent::SpecialInterrupted(ev.player_name.clone(), slot),
+            ));
+        }
+        if hit != AttackOutcome::Miss && !target.health().alive() {
+            ev_part_death.send(BygonePartDeathEvent::new(bygone_entity, part, ev.arena));
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn revive_players(
+    mut commands: Commands,
+    mut ev_player_ability: EventReader<(GameId, PlayerAbilityEvent)>,
+    mut ev_battle_log: EventWriter<(ArenaId, BattleLogEvent)>,
+    mut revivers: Query<
+        (
+            Entity,
+            &UserIdComponent,
+            &GameId,
+            &mut Cooldowns,
+            Option<&Ready>,
+        ),
+        (With<Player>, With<Active>),
+    >,
+    mut downed: Query<
+        (
+            Entity,
+            &UserIdComponent,
+            &GameId,
+            &PlayerName,
+            &mut Vitality,
+        ),
+        (With<Player>, Without<Active>),
+    >,
+) {
+    // Activation waits for the end of the frame, a knocked out player is only revived once
+    let mut revived = HashSet::new();
+    for (game_id, ev) in ability_uses(&mut ev_player_ability, Ability::Revive) {
+        let (reviver, mut cooldowns) =
+            match revivers
+                .iter_mut()
+                .find(|(_, user_id, player_game_id, _, _)| {
+                    user_id.0 == ev.player && **player_game_id == game_id
+                }) {
+                Some((entity, _, _, cooldowns, ready)) if can_use(ready, &cooldowns, &ev) => {
+                    (entity, cooldowns)
+                }
+                _ => continue,
+            };
+        let target = downed
+            .iter_mut()
+            .filter(|(entity, _, player_game_id, _, _)| {
+                **player_game_id == game_id && !revived.contains(entity)
+            })
+            .min_by_key(|(_, user_id, _, _, _)| user_id.0);
+        let (target, _, _, name, mut vitality) = match target {
+            Some(target) => target,
+            None => {
+                println!(
+                    "{} - systems - ERROR processing revive ability for player id {}: nobody is knocked out",
+                    format_time(),
+                    ev.player,
+                );
+                continue;
+            }
+        };
+        vitality.heal(REVIVE_HEALTH);
+        revived.insert(target);
+        commands.entity(target).insert(Active);
+        cooldowns.start(Ability::Revive);
+        commands.entity(reviver).remove::<Ready>();
+        ev_battle_log.send((
+            ev.arena,
+            BattleLogEvent::PlayerRevive(ev.player_name.clone(), name.clone()),
+        ));
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn damage_players(
+    mut commands: Commands,
+    definitions: Res<EnemyDefinitions>,
+    mut ev_enemy_attack: EventReader<EnemyAttackEvent>,
+    mut ev_battle_log: EventWriter<(ArenaId, BattleLogEvent)>,
+    mut ev_deactivate: EventWriter<DeactivateEvent>,
+    mut ev_status_effect: EventWriter<StatusEffectEvent>,
+    mut players: Query<
+        (
+            Entity,
+            &UserIdComponent,
+            &GameId,
+            &PlayerName,
+            &PlayerClass,
+            &mut Vitality,
+            &mut Threat,
+            Option<&Defending>,
+        ),
+        (With<Player>, With<Active>),
+    >,
+    mut enemies: Query<
+        (
+            &GameId,
+            &EnemySlot,
+            &EnemyKind,
+            &BygoneStage,
+            &Attack,
+            &StatusEffects,
+            &TargetingPolicy,
+            &mut SpecialMoves,
+            &mut RngComponent,
+        ),
+        (With<Enemy>, With<Active>),
+    >,
+) {
+    for EnemyAttackEvent { arena, game_id } in ev_enemy_attack.iter() {
+        let mut players: Vec<_> = players
+            .iter_mut()
+            .filter(|(_, _, player_game_id, _, _, _, _, _)| *player_game_id == game_id)
+            .map(
+                |(entity, user_id, _, name, class, vitality, threat, defending)| {
+                    (
+                        entity,
+                        user_id.0,
+                        name,
+                        *class,
+                        vitality,
+                        threat,
+                        defending.is_some(),
+                    )
+                },
+            )
+            .collect();
+        // Query order shifts as components move between tables, the target roll must not depend on it
+        players.sort_by_key(|(_, user_id, _, _, _, _, _)| *user_id);
+        let mut enemies: Vec<_> = enemies
+            .iter_mut()
+            .filter(|(enemy_game_id, ..)| *enemy_game_id == game_id)
+            .collect();
+        enemies.sort_by_key(|(_, slot, ..)| **slot);
+
+        for (_game_id, slot, kind, stage, attack, effects, policy, specials, rng) in
+            enemies.iter_mut()
+        {
+            // A shield lasts until the enemy attacks again, stunned or not
+            specials.shielded = None;
+            if effects.has(StatusEffect::Stunned) {
+                continue;
+            }
+            let definition = definitions.get(&kind.0);
+            let special = specials.telegraph.take();
+            if let Some(special) = special.as_ref() {
+                ev_battle_log.send((
+                    *arena,
+                    BattleLogEvent::BygoneSpecial(**slot, special.clone()),
+                ));
+            }
+            match special {
+                Some(SpecialMove::Barrage) => {
+                    for (entity, user_id, name, _, target, _, defending) in players
+                        .iter_mut()
+                        .filter(|(_, _, _, _, vitality, _, _)| vitality.health().alive())
+                    {
+                        bygone_shot(
+                            *arena,
+                            **slot,
+                            attack,
+                            &definition.on_hit,
+                            100,
+                            rng,
+                            (*entity, *user_id, name, target, *defending),
+                            &mut ev_battle_log,
+                            &mut ev_deactivate,
+                            &mut ev_status_effect,
+                        );
+                    }
+                }
+                Some(SpecialMove::Shield(part)) => specials.shielded = Some(part),
+                _ => {
+                    let multiplier = match special {
+                        Some(SpecialMove::ChargedShot { multiplier, .. }) => multiplier * 100,
+                        _ => 100,
+                    };
+                    let candidates: Vec<_> = players
+                        .iter()
+                        .map(|(_, _, _, class, vitality, threat, _)| {
+                            (vitality.health().current(), *class, threat.0)
+                        })
+                        .collect();
+                    if let Some((idx, reasoned)) = choose_target(**policy, rng, &candidates) {
+                        let (entity, user_id, name, _, target, _, defending) = &mut players[idx];
+                        if reasoned {
+                            ev_battle_log.send((
+                                *arena,
+                                BattleLogEvent::BygoneTargets(**slot, name.clone(), **policy),
+                            ));
+                        }
+                        bygone_shot(
+                            *arena,
+                            **slot,
+                            attack,
+                            &definition.on_hit,
+                            multiplier,
+                            rng,
+                            (*entity, *user_id, name, target, *defending),
+                            &mut ev_battle_log,
+                            &mut ev_deactivate,
+                            &mut ev_status_effect,
+                        );
+                    }
+                }
+            }
+
+            specials.attacks += 1;
+            if let Some(next) = definition
+                .special(stage)
+                .filter(|next| specials.attacks % next.every == 0)
+            {
+                specials.telegraph = Some(next.special.clone());
+                ev_battle_log.send((
+                    *arena,
+                    BattleLogEvent::BygoneTelegraph(**slot, next.special.clone()),
+                ));
+            }
+        }
+
+        // The defense holds for the whole attack of the encounter and drops afterwards,
+        // threat counts again from here
+        for (entity, _, _, _, _, threat, defending) in players.iter_mut() {
+            if *defending {
+                commands.entity(*entity).remove::<Defending>();
+            }
+            threat.0 = 0;
+        }
+    }
+}
+
+/// One shot of an enemy at a player, `multiplier` is in percent of the rolled damage
+#[allow(clippy::too_many_arguments)]
+fn bygone_shot(
+    arena: ArenaId,
+    slot: EnemySlot,
+    attack: &Attack,
+    on_hit: &[TimedEffect],
+    multiplier: usize,
+    rng: &mut RngComponent,
+    (entity, user_id, name, target, defending): (
+        Entity,
+        Id<UserMarker>,
+        &PlayerName,
+        &mut Vitality,
+        bool,
+    ),
+    ev_battle_log: &mut EventWriter<(ArenaId, BattleLogEvent)>,
+    ev_deactivate: &mut EventWriter<DeactivateEvent>,
+    ev_status_effect: &mut EventWriter<StatusEffectEvent>,
+) {
+    let dodge_bonus = if defending { DEFEND_DODGE } else { 0 };
+    target.modify_dodge(dodge_bonus);
+    let dice_roll = rng.d100();
+    let damage = rng.damage(attack);
+    let hit = attack.attack(target, dice_roll, damage, multiplier);
+    target.modify_dodge(-dodge_bonus);
+    if hit == AttackOutcome::Miss {
+        ev_battle_log.send((arena, BattleLogEvent::BygoneMiss(slot)));
+        return;
+    }
+    let log = match hit {
+        AttackOutcome::Crit => BattleLogEvent::BygoneCrit(slot, name.clone()),
+        _ => BattleLogEvent::BygoneHit(slot, name.clone()),
+    };
+    ev_battle_log.send((arena, log));
+    for effect in on_hit.iter() {
+        ev_status_effect.send(StatusEffectEvent::new(entity, arena, *effect));
+    }
+    if !target.health().alive() {
+        ev_deactivate.send(DeactivateEvent(entity));
+        ev_battle_log.send((arena, BattleLogEvent::PlayerDead(user_id, name.clone())));
+    }
+}
+
+/// Index of the player the enemy attacks among `(health, class, threat)` of each in user id order,
+/// and whether the policy made the pick rather than chance
+fn choose_target(
+    policy: TargetingPolicy,
+    rng: &mut RngComponent,
+    candidates: &[(usize, PlayerClass, usize)],
+) -> Option<(usize, bool)> {
+    // Players an earlier enemy of the encounter took down this frame are still in the list,
+    // nobody shoots at them. While everyone stands Random rolls the same as it always did
+    let alive = candidates
+        .iter()
+        .enumerate()
+        .filter(|(_, (health, _, _))| *health > 0);
+    let standing: Vec<_> = alive.clone().map(|(idx, _)| idx).collect();
+    let random = |rng: &mut RngComponent| {
+        choose_index(rng, standing.len()).map(|idx| (standing[idx], false))
+    };
+    let chosen = match policy {
+        TargetingPolicy::Random => None,
+        TargetingPolicy::LowestHealth => alive
+            .min_by_key(|(_, (health, _, _))| *health)
+            .map(|(idx, _)| idx),
+        TargetingPolicy::Threat => alive
+            .filter(|(_, (_, _, threat))| *threat > 0)
+            .min_by_key(|(_, (_, _, threat))| Reverse(*threat))
+            .map(|(idx, _)| idx),
+        TargetingPolicy::Spread => alive
+            .min_by_key(|(_, (health, _, _))| Reverse(*health))
+            .map(|(idx, _)| idx),
+        TargetingPolicy::Healers => {
+            let medics: Vec<_> = alive
+                .filter(|(_, (_, class, _))| *class == PlayerClass::Medic)
+                .map(|(idx, _)| idx)
+                .collect();
+            choose_index(rng, medics.len()).map(|medic| medics[medic])
+        }
+    };
+    match chosen {
+        Some(idx) => Some((idx, true)),
+        None => random(rng),
+    }
+}
+
+pub fn deactivate(mut commands: Commands, mut ev_deactivate: EventReader<DeactivateEvent>) {
+    for ev in ev_deactivate.iter() {
+        commands.entity(ev.0).remove::<Active>();
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn update_game_status(
+    mut commands: Commands,
+    mut games: ResMut<HashMap<ArenaId, Game>>,
+    mut ev_deactivate: EventReader<DeactivateEvent>,
+    mut ev_game_end: EventWriter<GameEndEvent>,
+    mut ev_bygone_spawn: EventWriter<BygoneSpawnEvent>,
+    mut ev_battle_log: EventWriter<(ArenaId, BattleLogEvent)>,
     active_players: Query<(Entity, &GameId), (With<Player>, With<Active>)>,
     active_enemies: Query<(Entity, &GameId), (With<Enemy>, With<Active>)>,
+    enemies: Query<(Entity, &GameId), (With<Enemy>,)>,
     entities: Query<(Entity, &GameId), (Or<(With<Enemy>, With<Player>)>,)>,
 ) {
     let deactivated: HashSet<_> = ev_deactivate
@@ -409,110 +1677,263 @@ pub fn update_game_status(
         .map(|(entity, _)| entity)
         .collect();
 
-    for game in games
-        .values_mut()
-        .filter(|game| game.status == GameStatus::Ongoing)
+    for (arena, game) in games
+        .iter_mut()
+        .filter(|(_, game)| game.status == GameStatus::Ongoing)
     {
-        let initialized = entities
+        // No enemies yet, or the next wave of a survival battle is still on its way
+        let initialized = enemies
             .iter()
             .any(|(_, enemy_game_id)| *enemy_game_id == game.id);
         if !initialized {
             continue;
         }
-        if active_enemies.iter().all(|(entity, enemy_game_id)| {
+        let finished_status = if active_enemies.iter().all(|(entity, enemy_game_id)| {
             *enemy_game_id != game.id || deactivated.contains(&entity)
         }) {
-            game.status = FinishedGameStatus::Won.into();
+            if let Some(survival) = game.survival.as_mut() {
+                survival.waves += 1;
+                for (entity, _) in enemies
+                    .iter()
+                    .filter(|(_, enemy_game_id)| **enemy_game_id == game.id)
+                {
+                    commands.entity(entity).despawn();
+                }
+                ev_bygone_spawn.send(
+                    BygoneSpawnEvent::new(game.enemy.clone(), survival.difficulty, game.id)
+                        .in_wave(survival.waves),
+                );
+                ev_battle_log.send((*arena, BattleLogEvent::NextWave(survival.waves + 1)));
+                continue;
+            }
+            FinishedGameStatus::Won
         } else if active_players.iter().all(|(entity, player_game_id)| {
             *player_game_id != game.id || deactivated.contains(&entity)
         }) {
-            game.status = FinishedGameStatus::Lost.into();
-        }
+            game.ending(FinishedGameStatus::Lost)
+        } else {
+            continue;
+        };
+        game.status = finished_status.into();
+        ev_game_end.send(GameEndEvent::new(*arena, game.id, finished_status));
     }
 }
 
 pub fn log_battle(
-    games: Res<HashMap<Id<GuildMarker>, Game>>,
+    games: Res<HashMap<ArenaId, Game>>,
+    enemies: Res<EnemyDefinitions>,
     mut rng: ResMut<GlobalRng>,
-    mut battle_log: ResMut<HashMap<Id<GuildMarker>, Vec<String>>>,
-    mut ev_battle_log: EventReader<(Id<GuildMarker>, BattleLogEvent)>,
+    mut battle_log: ResMut<HashMap<ArenaId, Vec<String>>>,
+    mut ev_battle_log: EventReader<(ArenaId, BattleLogEvent)>,
     mut ev_player_join: EventReader<PlayerJoinEvent>,
 ) {
-    for (guild_id, ev) in ev_battle_log.iter() {
-        if let Some(game) = games.get(guild_id) {
+    for (arena, ev) in ev_battle_log.iter() {
+        if let Some(game) = games.get(arena) {
             let loc = &game.localization;
+            let encounter_size = enemies.encounter(&game.enemy).len();
+            let enemy_loc = |slot: EnemySlot| {
+                enemies
+                    .in_slot(&game.enemy, slot)
+                    .unwrap_or_else(|| enemies.get(&game.enemy))
+                    .localization(loc.language)
+            };
+            let combatant_name = |combatant: &Combatant| match combatant {
+                Combatant::Player(name) => name.0.clone(),
+                Combatant::Enemy(slot) => enemy_loc(*slot).name_in(*slot, encounter_size),
+            };
+            let special_part_name = |slot: EnemySlot, special: &SpecialMove| {
+                special
+                    .part()
+                    .map(|part| enemy_loc(slot).part_name(part))
+                    .unwrap_or_default()
+            };
             let log_line = match ev {
-                BattleLogEvent::PlayerDead(name) => rng
+                BattleLogEvent::PlayerDead(_, name) => rng
                     .sample(&loc.player_dead)
                     .unwrap()
                     .insert_player_name(name),
-                BattleLogEvent::PlayerHit(name, part) => rng
+                BattleLogEvent::PlayerHit(_, name, slot, part) => rng
                     .sample(&loc.player_hit)
                     .unwrap()
                     .insert_player_name(name)
-                    .insert_bygone_part_name(&part.render_text(loc)),
-                BattleLogEvent::PlayerMiss(name) => rng
+                    .insert_part_name(&enemy_loc(*slot).part_name(part)),
+                BattleLogEvent::PlayerMiss(_, name) => rng
                     .sample(&loc.player_miss)
                     .unwrap()
                     .insert_player_name(name),
-                BattleLogEvent::BygoneHit(name) => rng
-                    .sample(&loc.bygone03_hit)
+                BattleLogEvent::PlayerCrit(_, name, slot, part) => rng
+                    .sample(&loc.crits.player_crit)
+                    .unwrap()
+                    .insert_player_name(name)
+                    .insert_part_name(&enemy_loc(*slot).part_name(part)),
+                BattleLogEvent::BygoneCrit(slot, name) => rng
+                    .sample(&loc.crits.bygone_crit)
+                    .unwrap()
+                    .insert_player_name(name)
+                    .insert_enemy_name(&enemy_loc(*slot).name_in(*slot, encounter_size)),
+                BattleLogEvent::BygoneTargets(slot, name, policy) => rng
+                    .sample(loc.targeting.lines(*policy))
+                    .unwrap()
+                    .insert_player_name(name)
+                    .insert_enemy_name(&enemy_loc(*slot).name_in(*slot, encounter_size)),
+                BattleLogEvent::BygoneHit(slot, name) => rng
+                    .sample(&enemy_loc(*slot).hit)
+                    .unwrap()
+                    .insert_player_name(name)
+                    .insert_enemy_name(&enemy_loc(*slot).name_in(*slot, encounter_size)),
+                BattleLogEvent::BygoneMiss(slot) => rng
+                    .sample(&enemy_loc(*slot).miss)
+                    .unwrap()
+                    .insert_enemy_name(&enemy_loc(*slot).name_in(*slot, encounter_size)),
+                BattleLogEvent::BygoneDead(slot) => rng
+                    .sample(&enemy_loc(*slot).dead)
+                    .unwrap()
+                    .insert_enemy_name(&enemy_loc(*slot).name_in(*slot, encounter_size)),
+                BattleLogEvent::PlayerHeal(name, ally) => rng
+                    .sample(&loc.abilities.player_heal)
+                    .unwrap()
+                    .insert_player_name(name)
+                    .insert_ally_name(ally),
+                BattleLogEvent::PlayerDefend(name) => rng
+                    .sample(&loc.abilities.player_defend)
+                    .unwrap()
+                    .insert_player_name(name),
+                BattleLogEvent::PlayerFocus(name) => rng
+                    .sample(&loc.abilities.player_focus)
+                    .unwrap()
+                    .insert_player_name(name),
+                BattleLogEvent::PlayerRevive(name, ally) => rng
+                    .sample(&loc.abilities.player_revive)
                     .unwrap()
                     .insert_player_name(name)
-                    .insert_enemy_name("_03"),
-                BattleLogEvent::BygoneMiss => rng
-                    .sample(&loc.bygone03_miss)
+                    .insert_ally_name(ally),
+                BattleLogEvent::EffectApplied(combatant, effect) => rng
+                    .sample(&loc.effects.applied)
+                    .unwrap()
+                    .insert_target_name(&combatant_name(combatant))
+                    .insert_effect_name(&loc.effects.name(*effect)),
+                BattleLogEvent::EffectExpired(combatant, effect) => rng
+                    .sample(&loc.effects.worn_off)
+                    .unwrap()
+                    .insert_target_name(&combatant_name(combatant))
+                    .insert_effect_name(&loc.effects.name(*effect)),
+                BattleLogEvent::BurnDamage(combatant) => rng
+                    .sample(&loc.effects.burn)
+                    .unwrap()
+                    .insert_target_name(&combatant_name(combatant)),
+                BattleLogEvent::BygoneTelegraph(slot, special) => rng
+                    .sample(&loc.specials.get(special).telegraph)
+                    .unwrap()
+                    .insert_enemy_name(&enemy_loc(*slot).name_in(*slot, encounter_size))
+                    .insert_part_name(&special_part_name(*slot, special)),
+                BattleLogEvent::BygoneSpecial(slot, special) => rng
+                    .sample(&loc.specials.get(special).unleash)
                     .unwrap()
-                    .insert_enemy_name("_03"),
-                BattleLogEvent::BygoneDead => rng
-                    .sample(&loc.bygone03_dead)
+                    .insert_enemy_name(&enemy_loc(*slot).name_in(*slot, encounter_size))
+                    .insert_part_name(&special_part_name(*slot, special)),
+                BattleLogEvent::SpecialInterrupted(name, slot) => rng
+                    .sample(&loc.specials.interrupted)
                     .unwrap()
-                    .insert_enemy_name("_03"),
+                    .insert_player_name(name)
+                    .insert_enemy_name(&enemy_loc(*slot).name_in(*slot, encounter_size)),
+                BattleLogEvent::NextWave(wave) => rng
+                    .sample(&loc.survival.next_wave)
+                    .unwrap()
+                    .insert_wave(*wave)
+                    .insert_enemy_name(
+                        &enemy_loc(EnemySlot::default())
+                            .name_in(EnemySlot::default(), encounter_size),
+                    ),
+                BattleLogEvent::PartShielded(name, slot, part) => rng
+                    .sample(&loc.specials.blocked)
+                    .unwrap()
+                    .insert_player_name(name)
+                    .insert_part_name(&enemy_loc(*slot).part_name(part)),
             };
-            battle_log
-                .entry(*guild_id)
-                .or_insert(Vec::new())
-                .push(log_line.0);
+            battle_log.entry(*arena).or_default().push(log_line.0);
         }
     }
     for ev in ev_player_join.iter() {
-        if let Some(game) = games.get(&ev.guild_id) {
+        if let Some(game) = games.get(&ev.arena) {
             let loc = &game.localization;
             let log_line = rng
                 .sample(&loc.player_join)
                 .unwrap()
                 .insert_player_name(&ev.player_name);
-            battle_log
-                .entry(ev.guild_id)
-                .or_insert(Vec::new())
-                .push(log_line.0);
+            battle_log.entry(ev.arena).or_default().push(log_line.0);
         }
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn render(
     sender: Mutex<Sender<GameRenderEvent>>,
 ) -> impl FnMut(
-    Res<HashMap<Id<GuildMarker>, Game>>,
-    ResMut<HashMap<Id<GuildMarker>, Vec<String>>>,
+    Res<HashMap<ArenaId, Game>>,
+    Res<EnemyDefinitions>,
+    ResMut<HashMap<ArenaId, Vec<String>>>,
+    Res<Scoreboard>,
     ResMut<GlobalRng>,
     EventReader<GameDrawEvent>,
     EventReader<ProgressBarUpdateEvent>,
-    Query<(&PlayerName, &GameId, &Vitality), (With<Player>,)>,
-    Query<(&GameId, &BygoneParts, &Attack, &Bygone03Stage), (With<Enemy>,)>,
+    EventReader<LeaderboardRequestEvent>,
+    Query<
+        (
+            &PlayerName,
+            &GameId,
+            &PlayerClass,
+            &Vitality,
+            &StatusEffects,
+        ),
+        (With<Player>,),
+    >,
+    Query<
+        (
+            &GameId,
+            &EnemySlot,
+            &EnemyKind,
+            &BygoneParts,
+            &Attack,
+            &BygoneStage,
+            &StatusEffects,
+            &SpecialMoves,
+        ),
+        (With<Enemy>,),
+    >,
 ) {
     move |games,
+          definitions,
           mut battle_log,
+          scoreboard,
           _rng,
           mut ev_game_draw,
           mut ev_progress_bar_update,
+          mut ev_leaderboard,
           all_players,
           enemies| {
-        for ProgressBarUpdateEvent { guild_id, progress } in ev_progress_bar_update.iter() {
-            if let Some(game) = games.get(guild_id) {
+        for ev in ev_leaderboard.iter() {
+            if let Ok(ref mut sender_lock) = sender.lock() {
+                if let Err(err) = sender_lock.send(GameRenderEvent {
+                    arena: ev.arena,
+                    interaction_id: ev.interaction,
+                    loc: ev.localization.clone(),
+                    payload: GameRenderPayload::Leaderboard(
+                        scoreboard.top(ev.arena.guild_id, LEADERBOARD_SIZE),
+                    ),
+                }) {
+                    println!(
+                        "{} - systems - FAILED to send render leaderboard event: {}",
+                        format_time(),
+                        err
+                    );
+                }
+            }
+        }
+        for ProgressBarUpdateEvent { arena, progress } in ev_progress_bar_update.iter() {
+            if let Some(game) = games.get(arena) {
                 if let Ok(ref mut sender_lock) = sender.lock() {
                     if let Err(err) = sender_lock.send(GameRenderEvent {
-                        guild_id: *guild_id,
+                        arena: *arena,
                         interaction_id: game.interaction_id,
                         loc: game.localization.clone(),
                         payload: GameRenderPayload::TurnProgress(*progress),
@@ -526,49 +1947,55 @@ pub fn render(
                 }
             }
         }
-        for GameDrawEvent { guild_id } in ev_game_draw.iter() {
-            if let Some(game) = games.get(guild_id) {
+        for GameDrawEvent { arena } in ev_game_draw.iter() {
+            if let Some(game) = games.get(arena) {
                 let game_render_ev = if let GameStatus::Finished(finished_status) = game.status {
                     GameRenderEvent {
-                        guild_id: *guild_id,
+                        arena: *arena,
                         interaction_id: game.interaction_id,
                         loc: game.localization.clone(),
                         payload: GameRenderPayload::FinishedGame(finished_status),
                     }
                 } else {
-                    let mut bygone_attack = Attack::default();
-                    let mut bygone_parts = EnumMap::<BygonePart, Vitality>::default();
-                    let mut bygone_stage = Bygone03Stage::Armored;
-                    for (enemy_game_id, BygoneParts(parts), attack, stage) in enemies.iter() {
+                    let mut game_enemies = Vec::new();
+                    for (enemy_game_id, slot, kind, parts, attack, stage, effects, specials) in
+                        enemies.iter()
+                    {
                         if *enemy_game_id != game.id {
                             continue;
                         }
-                        bygone_attack = *attack;
-                        bygone_parts = *parts;
-                        bygone_stage = *stage;
+                        game_enemies.push(EnemyStatus {
+                            slot: *slot,
+                            definition: definitions.get(&kind.0).clone(),
+                            parts: parts.clone(),
+                            attack: *attack,
+                            stage: stage.clone(),
+                            effects: effects.clone(),
+                            specials: specials.clone(),
+                        });
                     }
+                    game_enemies.sort_by_key(|enemy| enemy.slot);
 
-                    let battle_log_lines = battle_log.remove(guild_id).unwrap_or_default();
+                    let battle_log_lines = battle_log.remove(arena).unwrap_or_default();
 
                     let mut players = Vec::new();
-                    for (name, player_game_id, vitality) in all_players.iter() {
+                    for (name, player_game_id, class, vitality, effects) in all_players.iter() {
                         if *player_game_id != game.id {
                             continue;
                         }
-                        players.push((name.clone(), *vitality));
+                        players.push((name.clone(), *class, *vitality, effects.clone()));
                     }
 
                     GameRenderEvent {
-                        guild_id: *guild_id,
+                        arena: *arena,
                         interaction_id: game.interaction_id,
                         loc: game.localization.clone(),
-                        payload: GameRenderPayload::OngoingGame(OngoingGamePayload {
-                            bygone_parts,
-                            bygone_attack,
-                            bygone_stage,
+                        payload: GameRenderPayload::OngoingGame(Box::new(OngoingGamePayload {
+                            enemies: game_enemies,
                             battle_log_lines,
                             players,
-                        }),
+                            wave: game.survival.map(|survival| survival.waves + 1),
+                        })),
                     }
                 };
 
@@ -586,31 +2013,227 @@ pub fn render(
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn ready_players(
     mut commands: Commands,
+    games: Res<HashMap<ArenaId, Game>>,
     mut ev_turn_end: EventReader<TurnEndEvent>,
-    players: Query<(Entity, &GameId), (With<Player>, With<Active>, Without<Ready>)>,
+    players: Query<(Entity, &GameId, &StatusEffects, Option<&Ready>), (With<Player>, With<Active>)>,
 ) {
     for ev in ev_turn_end.iter() {
-        for (entity, game_id) in players.iter() {
-            if *game_id == ev.game_id {
+        let strict = is_strict(&games, ev.game_id);
+        for (entity, game_id, effects, ready) in players.iter() {
+            if *game_id != ev.game_id {
+                continue;
+            }
+            if strict {
+                // Whoever acted but didn't get to resolve starts the next strict turn over
+                if ready.is_some() {
+                    commands.entity(entity).remove::<Ready>();
+                }
+            } else if ready.is_none() && !effects.has(StatusEffect::Stunned) {
+                // Stunned players sit the next turn out
                 commands.entity(entity).insert(Ready);
             }
         }
     }
 }
 
+pub fn cool_down_abilities(
+    mut ev_turn_end: EventReader<TurnEndEvent>,
+    mut players: Query<(&GameId, &mut Cooldowns), (With<Player>,)>,
+) {
+    for ev in ev_turn_end.iter() {
+        for (game_id, mut cooldowns) in players.iter_mut() {
+            if *game_id == ev.game_id {
+                cooldowns.tick();
+            }
+        }
+    }
+}
+
+/// Puts the effect's modifiers on while it lasts, `sign` of -1 takes them off again
+fn modify_by_effect(
+    effect: StatusEffect,
+    sign: isize,
+    attack: &mut Attack,
+    vitality: Option<&mut Vitality>,
+    parts: Option<&mut BygoneParts>,
+) {
+    attack.modify_accuracy(sign * effect.accuracy());
+    if let Some(vitality) = vitality {
+        vitality.modify_dodge(sign * effect.dodge());
+    }
+    if let Some(parts) = parts {
+        parts
+            .values_mut()
+            .for_each(|vitality| vitality.modify_dodge(sign * effect.dodge()));
+    }
+}
+
+fn combatant(name: Option<&PlayerName>, slot: Option<&EnemySlot>) -> Option<Combatant> {
+    match (name, slot) {
+        (Some(name), _) => Some(Combatant::Player(name.clone())),
+        (None, Some(slot)) => Some(Combatant::Enemy(*slot)),
+        (None, None) => None,
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn apply_status_effects(
+    mut ev_status_effect: EventReader<StatusEffectEvent>,
+    mut ev_battle_log: EventWriter<(ArenaId, BattleLogEvent)>,
+    mut affected: Query<
+        (
+            Option<&PlayerName>,
+            Option<&EnemySlot>,
+            &mut StatusEffects,
+            &mut Attack,
+            Option<&mut Vitality>,
+            Option<&mut BygoneParts>,
+        ),
+        (With<Active>,),
+    >,
+) {
+    for ev in ev_status_effect.iter() {
+        let (name, slot, mut effects, mut attack, mut vitality, mut parts) =
+            match affected.get_mut(ev.entity) {
+                Ok(affected) => affected,
+                Err(_) => continue,
+            };
+        if !effects.apply(ev.effect) {
+            continue;
+        }
+        modify_by_effect(
+            ev.effect.effect,
+            1,
+            &mut attack,
+            vitality.as_deref_mut(),
+            parts.as_deref_mut(),
+        );
+        if let Some(combatant) = combatant(name, slot) {
+            ev_battle_log.send((
+                ev.arena,
+                BattleLogEvent::EffectApplied(combatant, ev.effect.effect),
+            ));
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn tick_status_effects(
+    games: Res<HashMap<ArenaId, Game>>,
+    definitions: Res<EnemyDefinitions>,
+    mut ev_turn_end: EventReader<TurnEndEvent>,
+    mut ev_battle_log: EventWriter<(ArenaId, BattleLogEvent)>,
+    mut ev_deactivate: EventWriter<DeactivateEvent>,
+    mut ev_part_death: EventWriter<BygonePartDeathEvent>,
+    mut affected: Query<
+        (
+            Entity,
+            &GameId,
+            Option<&PlayerName>,
+            Option<&UserIdComponent>,
+            Option<&EnemySlot>,
+            Option<&EnemyKind>,
+            &mut StatusEffects,
+            &mut Attack,
+            Option<&mut Vitality>,
+            Option<&mut BygoneParts>,
+        ),
+        (With<Active>,),
+    >,
+) {
+    for ev in ev_turn_end.iter() {
+        let arena = match games.iter().find(|(_, game)| game.id == ev.game_id) {
+            Some((arena, _)) => *arena,
+            None => continue,
+        };
+        let mut game_affected: Vec<_> = affected
+            .iter_mut()
+            .filter(|(_, game_id, ..)| **game_id == ev.game_id)
+            .collect();
+        // Enemies first, then players, the log reads the same in a replay
+        game_affected.sort_by_key(|(_, _, _, user_id, slot, ..)| {
+            (user_id.map(|user_id| user_id.0), slot.copied())
+        });
+        for (entity, _, name, user_id, slot, kind, effects, attack, vitality, parts) in
+            game_affected.iter_mut()
+        {
+            let combatant = match combatant(*name, *slot) {
+                Some(combatant) => combatant,
+                None => continue,
+            };
+            let damage: usize = effects.iter().map(|(effect, _)| effect.damage()).sum();
+            if damage > 0 {
+                if let Some(vitality) = vitality.as_deref_mut() {
+                    if vitality.health().alive() {
+                        vitality.suffer(damage);
+                        ev_battle_log.send((arena, BattleLogEvent::BurnDamage(combatant.clone())));
+                        if !vitality.health().alive() {
+                            ev_deactivate.send(DeactivateEvent(*entity));
+                            if let (Some(name), Some(user_id)) = (*name, *user_id) {
+                                ev_battle_log.send((
+                                    arena,
+                                    BattleLogEvent::PlayerDead(user_id.0, name.clone()),
+                                ));
+                            }
+                        }
+                    }
+                }
+                let stage_part = kind
+                    .and_then(|kind| definitions.get(&kind.0).stage_part())
+                    .cloned();
+                if let (Some(parts), Some(stage_part)) = (parts.as_deref_mut(), stage_part) {
+                    if let Some(part_vitality) = parts.get_mut(&stage_part) {
+                        if part_vitality.health().alive() {
+                            part_vitality.suffer(damage);
+                            ev_battle_log
+                                .send((arena, BattleLogEvent::BurnDamage(combatant.clone())));
+                            if !part_vitality.health().alive() {
+                                ev_part_death
+                                    .send(BygonePartDeathEvent::new(*entity, stage_part, arena));
+                            }
+                        }
+                    }
+                }
+            }
+            for effect in effects.tick() {
+                modify_by_effect(
+                    effect,
+                    -1,
+                    attack,
+                    vitality.as_deref_mut(),
+                    parts.as_deref_mut(),
+                );
+                ev_battle_log.send((
+                    arena,
+                    BattleLogEvent::EffectExpired(combatant.clone(), effect),
+                ));
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn cleanup(
     mut commands: Commands,
-    mut games: ResMut<HashMap<Id<GuildMarker>, Game>>,
+    clock: Res<Clock>,
+    timings: Res<TimingConfig>,
+    mut games: ResMut<HashMap<ArenaId, Game>>,
     mut ev_deallocate_game_resources: EventReader<DeallocateGameResourcesEvent>,
     mut ev_delayed: EventWriter<DelayedEvent>,
+    mut ev_game_end: EventWriter<GameEndEvent>,
     entities: Query<(Entity, &GameId)>,
 ) {
-    for (guild_id, game) in games.iter_mut() {
-        if game.status == GameStatus::Ongoing && game.duration_secs() >= MAX_GAME_DURATION_SECS {
-            game.status = FinishedGameStatus::Expired.into();
-            ev_delayed.send(DelayedEvent::GameDraw(GameDrawEvent::new(*guild_id)));
+    for (arena, game) in games.iter_mut() {
+        if game.status == GameStatus::Ongoing
+            && game.duration_secs(&clock) >= timings.guild(arena.guild_id).max_game_duration_secs
+        {
+            let finished_status = game.ending(FinishedGameStatus::Expired);
+            game.status = finished_status.into();
+            ev_game_end.send(GameEndEvent::new(*arena, game.id, finished_status));
+            ev_delayed.send(DelayedEvent::GameDraw(GameDrawEvent::new(*arena)));
         }
     }
     for ev in ev_deallocate_game_resources.iter() {
@@ -622,28 +2245,1352 @@ pub fn cleanup(
     }
 }
 
-// pub fn save_games(
-//     sender: Mutex<Sender<HashMap<Id<GuildMarker>, Game>>>,
-// ) -> impl FnMut(Res<HashMap<Id<GuildMarker>, Game>>) {
-//     move |games| {
-//         if let Ok(ref mut sender_lock) = sender.lock() {
-//             if let Err(err) = sender_lock.send(games.clone()) {
-//                 println!(
-//                     "{} - systems - FAILED to send save games event: {}",
-//                     format_time(),
-//                     err
-//                 );
-//             }
-//         }
-//     }
-// }
-
-// pub fn save_scoreboard(sender: Mutex<Sender<HashMap::<GuildId, HashMap<Id<UserMarker>, usize>>>>) -> impl FnMut(
-//     Res<HashMap::<GuildId, HashMap<Id<UserMarker>, usize>>>,
-// ) {
-//     move |scoreboard| {
-//         if let Ok(ref mut sender_lock) = sender.lock() {
-//             sender_lock.send(scoreboard.clone());
-//         }
-//     }
-// }
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use crate::{
+        components::{
+            Ability, Attack, AttackOutcome, BygoneStage, PlayerClass, PlayerName, SpecialMove,
+            StatusEffect, TargetingPolicy, TimedEffect, Vitality,
+        },
+        enemies::{EnemyDefinitions, DEFAULT_ENEMY},
+        events::{GameRenderEvent, GameRenderPayload, InputEvent, JoinRequestEvent, OneshotType},
+        game_helpers::{ArenaId, Difficulty, FinishedGameStatus, Game, GameStatus},
+        guild_config::{GameEnders, GuildConfigUpdate},
+        localization::{Language, LanguageChoice, LocalizedLine},
+        scoreboard::{PlayerStats, Scoreboard},
+        test_support::*,
+        timings::{TimingConfig, TimingOverrides, Timings},
+    };
+
+    fn started_game() -> TestGame {
+        let mut game = TestGame::new();
+        game.start_game(1, Difficulty::Easy);
+        game.set_bygone_attack(Attack::new(0, 0));
+        game.wait(DRAW_DELAY);
+        game
+    }
+
+    #[test]
+    fn starting_player_joins() {
+        let mut game = TestGame::new();
+        game.start_game(1, Difficulty::Easy);
+        game.wait(DRAW_DELAY);
+
+        let renders = game.renders();
+        let games = ongoing_games(&renders);
+        assert_eq!(games.len(), 1);
+        let players: Vec<_> = games[0].players.iter().map(|(name, ..)| name).collect();
+        assert_eq!(players, vec![&PlayerName("Player 1".to_string())]);
+        let (_, name) = TestGame::player(1);
+        let joined = log_lines(&game.loc().player_join, |line| {
+            line.insert_player_name(&name)
+        });
+        assert!(has_line(games[0], &joined));
+    }
+
+    #[test]
+    fn first_attack_joins_the_game() {
+        let mut game = started_game();
+        game.renders();
+
+        game.attack(2, part("Sensor"));
+        game.finish_turn();
+
+        let renders = game.renders();
+        let last_game = *ongoing_games(&renders).last().unwrap();
+        let players: Vec<_> = last_game.players.iter().map(|(name, ..)| &name.0).collect();
+        assert_eq!(players.len(), 2);
+        assert!(players.contains(&&"Player 2".to_string()));
+        let (_, name) = TestGame::player(2);
+        let joined = log_lines(&game.loc().player_join, |line| {
+            line.insert_player_name(&name)
+        });
+        assert!(has_line(last_game, &joined));
+    }
+
+    #[test]
+    fn hit_damages_the_part() {
+        let mut game = started_game();
+        game.set_part(part("Gun"), Vitality::new(2, 0));
+        game.renders();
+
+        game.attack(1, part("Gun"));
+        game.finish_turn();
+
+        let renders = game.renders();
+        let last_game = *ongoing_games(&renders).last().unwrap();
+        assert_eq!(
+            last_game.enemies[0]
+                .parts
+                .get(&part("Gun"))
+                .unwrap()
+                .health()
+                .current(),
+            1
+        );
+        let (_, name) = TestGame::player(1);
+        let part_name = game.part_name(&part("Gun"));
+        let hit = log_lines(&game.loc().player_hit, |line| {
+            line.insert_player_name(&name).insert_part_name(&part_name)
+        });
+        assert!(has_line(last_game, &hit));
+    }
+
+    #[test]
+    fn miss_leaves_the_part_intact() {
+        let mut game = started_game();
+        game.set_part(part("Sensor"), Vitality::new(1, 100));
+        game.renders();
+
+        game.attack(1, part("Sensor"));
+        game.finish_turn();
+
+        let renders = game.renders();
+        let last_game = *ongoing_games(&renders).last().unwrap();
+        assert!(last_game.enemies[0]
+            .parts
+            .get(&part("Sensor"))
+            .unwrap()
+            .health()
+            .alive());
+        let (_, name) = TestGame::player(1);
+        let miss = log_lines(&game.loc().player_miss, |line| {
+            line.insert_player_name(&name)
+        });
+        assert!(has_line(last_game, &miss));
+    }
+
+    #[test]
+    fn destroyed_core_moves_to_next_stage() {
+        let mut game = started_game();
+        game.set_part(part("Core"), Vitality::new(2, 0));
+
+        game.attack(1, part("Core"));
+        game.attack(1, part("Core"));
+        // Only one attack per turn
+        assert_eq!(game.bygone().1, BygoneStage::from("Armored"));
+        game.finish_turn();
+        game.attack(1, part("Core"));
+        game.step();
+
+        let (parts, stage) = game.bygone();
+        assert_eq!(stage, BygoneStage::from("Exposed"));
+        assert_eq!(parts.get(&part("Core")).unwrap().health().current(), 2);
+
+        game.finish_turn();
+        let renders = game.renders();
+        let last_game = *ongoing_games(&renders).last().unwrap();
+        assert_eq!(last_game.enemies[0].stage, BygoneStage::from("Exposed"));
+    }
+
+    #[test]
+    fn destroying_core_three_times_wins() {
+        let mut game = started_game();
+        game.set_part(part("Core"), Vitality::new(1, 0));
+
+        for _ in 0..3 {
+            game.attack(1, part("Core"));
+            game.finish_turn();
+        }
+
+        assert_eq!(game.bygone().1, BygoneStage::from("Defeated"));
+        assert_eq!(
+            finished_games(&game.renders()),
+            vec![FinishedGameStatus::Won]
+        );
+    }
+
+    #[test]
+    fn dead_players_lose() {
+        let mut game = started_game();
+        game.set_bygone_attack(Attack::new(6, 100));
+
+        game.attack(1, part("Sensor"));
+        game.finish_turn();
+
+        assert_eq!(
+            finished_games(&game.renders()),
+            vec![FinishedGameStatus::Lost]
+        );
+    }
+
+    #[test]
+    fn long_game_expires() {
+        let mut game = started_game();
+        game.renders();
+
+        let max_duration = Timings::default().max_game_duration_secs;
+        game.skip(Duration::from_secs(max_duration - 1));
+        assert!(finished_games(&game.renders()).is_empty());
+        game.skip(Duration::from_secs(1));
+        game.wait(DRAW_DELAY);
+
+        assert_eq!(
+            finished_games(&game.renders()),
+            vec![FinishedGameStatus::Expired]
+        );
+    }
+
+    #[test]
+    fn new_game_waits_for_cooldown() {
+        let mut game = started_game();
+
+        game.start_game(2, Difficulty::Hard);
+        assert!(matches!(
+            oneshots(&game.renders())[..],
+            [OneshotType::OtherGameInProgress]
+        ));
+
+        let timings = Timings::default();
+        game.skip(Duration::from_secs(timings.max_game_duration_secs));
+        game.start_game(2, Difficulty::Hard);
+        let cooldown =
+            Duration::from_secs(timings.game_cooldown_secs - timings.max_game_duration_secs);
+        assert!(matches!(
+            oneshots(&game.renders())[..],
+            [OneshotType::Cooldown(duration)] if duration == cooldown
+        ));
+
+        game.skip(cooldown);
+        game.start_game(2, Difficulty::Hard);
+        game.wait(DRAW_DELAY);
+        let renders = game.renders();
+        assert!(oneshots(&renders).is_empty());
+        let games = ongoing_games(&renders);
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].players[0].0, PlayerName("Player 2".to_string()));
+    }
+
+    #[test]
+    fn guild_timings_change_the_pace_of_its_battles() {
+        let mut timings = TimingConfig::default();
+        timings.guilds.insert(
+            TestGame::arena().guild_id,
+            TimingOverrides {
+                turn_secs: Some(3),
+                game_cooldown_secs: Some(Timings::default().max_game_duration_secs + 60),
+                ..Default::default()
+            },
+        );
+        let mut game = TestGame::with_timings(timings);
+        game.start_game(1, Difficulty::Easy);
+        game.set_bygone_attack(Attack::new(0, 0));
+        game.wait(DRAW_DELAY);
+        game.renders();
+
+        game.attack(1, part("Core"));
+        game.wait(Duration::from_secs(4));
+        assert_eq!(ongoing_games(&game.renders()).len(), 1);
+
+        game.skip(Duration::from_secs(
+            Timings::default().max_game_duration_secs,
+        ));
+        game.start_game(2, Difficulty::Hard);
+        // The turn took 4 of the extra 60 seconds
+        assert!(matches!(
+            oneshots(&game.renders())[..],
+            [OneshotType::Cooldown(duration)] if duration == Duration::from_secs(56)
+        ));
+    }
+
+    #[test]
+    fn guild_config_sets_the_defaults_of_its_battles() {
+        let mut game = TestGame::new();
+        let update = GuildConfigUpdate {
+            language: Some(Language::Ru),
+            difficulty: Some(Difficulty::Hard),
+            cooldown_secs: Some(30),
+            ..Default::default()
+        };
+        game.configure(update.clone(), false);
+        assert!(matches!(
+            oneshots(&game.renders())[..],
+            [OneshotType::NotPermitted]
+        ));
+
+        game.configure(update, true);
+        assert!(matches!(
+            &oneshots(&game.renders())[..],
+            [OneshotType::Config(config)] if config.language == Some(Language::Ru)
+        ));
+        game.start_default_game_in(TestGame::arena(), 1);
+        game.wait(DRAW_DELAY);
+        assert_eq!(
+            game.game(TestGame::arena()).localization.language,
+            Language::Ru
+        );
+        let mut hard = TestGame::new();
+        hard.start_game(1, Difficulty::Hard);
+        let mut medium = TestGame::new();
+        medium.start_game(1, Difficulty::Medium);
+        assert_eq!(game.bygone(), hard.bygone());
+        assert_ne!(game.bygone(), medium.bygone());
+
+        game.end_game(1);
+        game.start_game(2, Difficulty::Easy);
+        assert!(matches!(
+            oneshots(&game.renders())[..],
+            [OneshotType::GameEnded, OneshotType::Cooldown(_)]
+        ));
+        // Far below the cooldown of the timings
+        game.skip(Duration::from_secs(30));
+        game.start_game(2, Difficulty::Easy);
+        game.wait(DRAW_DELAY);
+        let renders = game.renders();
+        assert!(oneshots(&renders).is_empty());
+        assert_eq!(ongoing_games(&renders).len(), 1);
+    }
+
+    #[test]
+    fn battle_language_falls_back_from_the_command_to_the_guild_and_the_locales() {
+        let mut game = TestGame::new();
+        let language_in = |game: &TestGame, channel: u64| {
+            game.game(TestGame::channel(channel)).localization.language
+        };
+        // Locales the bot doesn't speak count for nothing
+        game.start_game_speaking(
+            TestGame::channel(1),
+            1,
+            LanguageChoice::new(None, Some("pt-BR"), Some("ru")),
+        );
+        assert_eq!(language_in(&game, 1), Language::Ru);
+        game.start_game_speaking(
+            TestGame::channel(2),
+            1,
+            LanguageChoice::new(None, Some("de"), None),
+        );
+        assert_eq!(language_in(&game, 2), Language::En);
+        game.start_game_speaking(
+            TestGame::channel(3),
+            1,
+            LanguageChoice::new(None, Some("en-US"), Some("ru")),
+        );
+        assert_eq!(language_in(&game, 3), Language::En);
+
+        game.configure(
+            GuildConfigUpdate {
+                language: Some(Language::Ru),
+                ..Default::default()
+            },
+            true,
+        );
+        game.start_game_speaking(
+            TestGame::channel(4),
+            1,
+            LanguageChoice::new(None, Some("en-US"), Some("en-GB")),
+        );
+        assert_eq!(language_in(&game, 4), Language::Ru);
+        game.start_game_speaking(
+            TestGame::channel(5),
+            1,
+            LanguageChoice::new(Some(Language::En), Some("ru"), Some("ru")),
+        );
+        assert_eq!(language_in(&game, 5), Language::En);
+    }
+
+    #[test]
+    fn notices_speak_the_language_of_the_user_who_asked() {
+        let mut game = TestGame::new();
+        let arena = TestGame::arena();
+        game.start_game_speaking(
+            arena,
+            1,
+            LanguageChoice::new(Some(Language::En), Some("ru"), Some("ru")),
+        );
+        game.wait(DRAW_DELAY);
+        game.renders();
+
+        game.start_game_speaking(
+            arena,
+            2,
+            LanguageChoice::new(None, Some("en-US"), Some("ru")),
+        );
+        game.start_game_speaking(
+            arena,
+            3,
+            LanguageChoice::new(Some(Language::Ru), None, Some("en-GB")),
+        );
+        // Without a locale of their own the user gets the language the guild speaks
+        game.start_game_speaking(arena, 3, LanguageChoice::new(None, Some("ru"), None));
+        game.end_game_speaking(1, LanguageChoice::new(None, None, Some("ru")));
+        game.wait(DRAW_DELAY);
+        let (notices, battle): (Vec<_>, Vec<_>) = game
+            .render_events()
+            .into_iter()
+            .partition(|ev| matches!(ev.payload, GameRenderPayload::OneshotMessage(_)));
+        let languages = |renders: Vec<GameRenderEvent>| -> Vec<_> {
+            renders.into_iter().map(|ev| ev.loc.language).collect()
+        };
+        assert_eq!(
+            languages(notices),
+            vec![Language::Ru, Language::En, Language::Ru, Language::Ru]
+        );
+        // The battle message keeps the language of the battle
+        assert_eq!(languages(battle), vec![Language::En]);
+    }
+
+    #[test]
+    fn battles_start_only_in_the_allowed_channels() {
+        let mut game = TestGame::new();
+        game.configure(
+            GuildConfigUpdate {
+                allow_channel: Some(TestGame::channel(2).channel_id.unwrap()),
+                ..Default::default()
+            },
+            true,
+        );
+        game.renders();
+
+        game.start_game(1, Difficulty::Easy);
+        game.wait(DRAW_DELAY);
+        let renders = game.renders();
+        assert!(matches!(
+            oneshots(&renders)[..],
+            [OneshotType::ChannelNotAllowed]
+        ));
+        assert!(ongoing_games(&renders).is_empty());
+
+        game.start_game_in(TestGame::channel(2), 1, Difficulty::Easy);
+        game.wait(DRAW_DELAY);
+        let renders = game.renders();
+        assert!(oneshots(&renders).is_empty());
+        assert_eq!(ongoing_games(&renders).len(), 1);
+
+        // With no battle channels left every channel is one
+        game.configure(
+            GuildConfigUpdate {
+                disallow_channel: Some(TestGame::channel(2).channel_id.unwrap()),
+                ..Default::default()
+            },
+            true,
+        );
+        game.start_game(1, Difficulty::Easy);
+        game.wait(DRAW_DELAY);
+        let renders = game.renders();
+        assert!(matches!(oneshots(&renders)[..], [OneshotType::Config(_)]));
+        assert_eq!(ongoing_games(&renders).len(), 1);
+    }
+
+    #[test]
+    fn only_the_starter_ends_the_battle_unless_anyone_may() {
+        let mut game = started_game();
+        game.join(2, PlayerClass::Fighter);
+        game.renders();
+
+        game.end_game(2);
+        assert!(matches!(
+            oneshots(&game.renders())[..],
+            [OneshotType::NotAllowedToEnd]
+        ));
+        assert_eq!(game.game(TestGame::arena()).status, GameStatus::Ongoing);
+
+        game.configure(
+            GuildConfigUpdate {
+                enders: Some(GameEnders::Anyone),
+                ..Default::default()
+            },
+            true,
+        );
+        game.end_game(2);
+        game.wait(DRAW_DELAY);
+        let renders = game.renders();
+        assert!(matches!(
+            oneshots(&renders)[..],
+            [OneshotType::Config(_), OneshotType::GameEnded]
+        ));
+        assert_eq!(finished_games(&renders), vec![FinishedGameStatus::Ended]);
+
+        game.end_game(1);
+        assert!(matches!(
+            oneshots(&game.renders())[..],
+            [OneshotType::NoGameToEnd]
+        ));
+    }
+
+    #[test]
+    fn battles_without_a_starter_can_be_ended_by_anyone() {
+        let mut game = started_game();
+        game.join(2, PlayerClass::Fighter);
+        game.app
+            .world
+            .resource_mut::<HashMap<ArenaId, Game>>()
+            .get_mut(&TestGame::arena())
+            .unwrap()
+            .starter = None;
+        game.renders();
+
+        game.end_game(2);
+        game.wait(DRAW_DELAY);
+        let renders = game.renders();
+        assert!(matches!(oneshots(&renders)[..], [OneshotType::GameEnded]));
+        assert_eq!(finished_games(&renders), vec![FinishedGameStatus::Ended]);
+    }
+
+    #[test]
+    fn scoreboard_counts_every_player_by_user() {
+        let mut game = started_game();
+        game.configure(
+            GuildConfigUpdate {
+                cooldown_secs: Some(0),
+                ..Default::default()
+            },
+            true,
+        );
+        // Player 2 goes by the same name as player 1 in the first battle
+        let (user_id, _) = TestGame::player(2);
+        let (_, name) = TestGame::player(1);
+        game.send(InputEvent::PlayerJoin(JoinRequestEvent::new(
+            user_id,
+            name,
+            TestGame::arena(),
+            PlayerClass::Fighter,
+        )));
+        game.set_part(part("Core"), Vitality::new(1, 0));
+        game.set_player_attack(1, Attack::new(1, 200));
+        for _ in 0..3 {
+            game.attack(1, part("Core"));
+            game.finish_turn();
+        }
+        assert_eq!(
+            finished_games(&game.renders()),
+            vec![FinishedGameStatus::Won]
+        );
+
+        game.start_game(2, Difficulty::Easy);
+        game.wait(DRAW_DELAY);
+        game.set_bygone_attack(Attack::new(6, 200));
+        game.set_player(2, Vitality::new(6, 0));
+        game.set_part(part("Core"), Vitality::new(6, 200));
+        game.attack(2, part("Core"));
+        game.finish_turn();
+        assert_eq!(
+            finished_games(&game.renders()),
+            vec![FinishedGameStatus::Lost]
+        );
+
+        let leaderboard = game.leaderboard();
+        let stats: Vec<_> = leaderboard
+            .iter()
+            .map(|stats| {
+                (
+                    stats.name.0.as_str(),
+                    stats.wins,
+                    stats.losses,
+                    stats.misses,
+                    stats.deaths,
+                )
+            })
+            .collect();
+        // Player 2 keeps one history under their latest name
+        assert_eq!(
+            stats,
+            vec![("Player 1", 1, 0, 0, 0), ("Player 2", 1, 1, 1, 1)]
+        );
+        // None of the hits of player 1 went to the other player called the same
+        assert!(leaderboard[0].hits > 0);
+        assert_eq!(leaderboard[1].hits, 0);
+    }
+
+    #[test]
+    fn leaderboard_ranks_by_wins_hits_and_deaths() {
+        let mut game = TestGame::new();
+        let mut scoreboard = game.app.world.resource_mut::<Scoreboard>();
+        let guild = scoreboard.0.entry(TestGame::arena().guild_id).or_default();
+        for (player, wins, hits, deaths) in [(1, 1, 5, 2), (2, 2, 0, 0), (3, 1, 5, 1), (4, 1, 7, 3)]
+        {
+            let (user_id, name) = TestGame::player(player);
+            guild.insert(
+                user_id,
+                PlayerStats {
+                    wins,
+                    hits,
+                    deaths,
+                    ..PlayerStats::new(name)
+                },
+            );
+        }
+
+        let names: Vec<_> = game
+            .leaderboard()
+            .into_iter()
+            .map(|stats| stats.name.0)
+            .collect();
+        assert_eq!(names, vec!["Player 2", "Player 4", "Player 3", "Player 1"]);
+    }
+
+    #[test]
+    fn channels_battle_independently() {
+        let mut game = started_game();
+        game.renders();
+
+        game.start_game_in(TestGame::channel(2), 2, Difficulty::Hard);
+        game.wait(DRAW_DELAY);
+        let renders = game.renders();
+        assert!(oneshots(&renders).is_empty());
+        assert_eq!(ongoing_games(&renders).len(), 1);
+
+        game.skip(Duration::from_secs(
+            Timings::default().max_game_duration_secs,
+        ));
+        game.start_game_in(TestGame::channel(3), 3, Difficulty::Hard);
+        game.start_game(3, Difficulty::Hard);
+        game.wait(DRAW_DELAY);
+        let renders = game.renders();
+        assert!(matches!(oneshots(&renders)[..], [OneshotType::Cooldown(_)]));
+        assert_eq!(ongoing_games(&renders).len(), 1);
+    }
+
+    #[test]
+    fn player_fights_in_several_guilds() {
+        let mut game = started_game();
+        game.start_game_in(TestGame::guild(2), 2, Difficulty::Easy);
+        game.set_bygone_attack(Attack::new(0, 0));
+        game.set_part(part("Sensor"), Vitality::new(1, 100));
+        game.wait(DRAW_DELAY);
+        game.renders();
+
+        game.attack_in(TestGame::guild(2), 1, part("Sensor"));
+        game.wait(DRAW_DELAY);
+        game.attack(1, part("Sensor"));
+        game.finish_turn();
+
+        let renders = game.renders();
+        let games = ongoing_games(&renders);
+        let (_, name) = TestGame::player(1);
+        let miss = log_lines(&game.loc().player_miss, |line| {
+            line.insert_player_name(&name)
+        });
+        let guild_1 = games
+            .iter()
+            .rev()
+            .find(|payload| payload.players.len() == 1)
+            .unwrap();
+        assert_eq!(guild_1.players[0].0, name);
+        assert!(has_line(guild_1, &miss));
+        let guild_2 = games
+            .iter()
+            .rev()
+            .find(|payload| payload.players.len() == 2)
+            .unwrap();
+        assert!(guild_2.players.iter().any(|(player, ..)| *player == name));
+        assert!(has_line(guild_2, &miss));
+    }
+
+    #[test]
+    fn attacks_in_the_same_frame_all_land() {
+        let mut game = started_game();
+        game.attack(2, part("Sensor"));
+        game.finish_turn();
+        game.set_part(part("Gun"), Vitality::new(3, 0));
+        game.renders();
+
+        game.queue(TestGame::attack_event(TestGame::arena(), 1, part("Gun")));
+        game.queue(TestGame::attack_event(TestGame::arena(), 2, part("Gun")));
+        game.step();
+        game.finish_turn();
+
+        let renders = game.renders();
+        let last_game = *ongoing_games(&renders).last().unwrap();
+        assert_eq!(
+            last_game.enemies[0]
+                .parts
+                .get(&part("Gun"))
+                .unwrap()
+                .health()
+                .current(),
+            1
+        );
+        let part_name = game.part_name(&part("Gun"));
+        for player in [1, 2] {
+            let (_, name) = TestGame::player(player);
+            let hit = log_lines(&game.loc().player_hit, |line| {
+                line.insert_player_name(&name).insert_part_name(&part_name)
+            });
+            assert!(has_line(last_game, &hit));
+        }
+    }
+
+    #[test]
+    fn chosen_enemy_fights_with_its_own_parts() {
+        let mut game = TestGame::new();
+        let mut drone = game
+            .app
+            .world
+            .resource::<EnemyDefinitions>()
+            .get(DEFAULT_ENEMY)
+            .clone();
+        drone
+            .parts
+            .retain(|definition| definition.id == part("Core"));
+        drone.parts[0].id = part("Hull");
+        drone.parts[0].emoji = "🇭".to_string();
+        drone.stages = vec![BygoneStage::from("Flying"), BygoneStage::from("Down")];
+        let localization = drone.localizations.get_mut(&Language::En).unwrap();
+        localization.name = LocalizedLine("Drone".to_string());
+        localization.dead = vec![LocalizedLine("*{ENEMY_NAME}* crashes".to_string())];
+        game.app
+            .world
+            .resource_mut::<EnemyDefinitions>()
+            .0
+            .insert("scout".to_string(), drone);
+
+        game.start_game_against(TestGame::arena(), 1, Difficulty::Easy, "scout");
+        game.set_bygone_attack(Attack::new(0, 0));
+        game.set_part(part("Hull"), Vitality::new(1, 0));
+        game.wait(DRAW_DELAY);
+        let renders = game.renders();
+        let started = ongoing_games(&renders)[0];
+        assert_eq!(started.enemies[0].parts.0.len(), 1);
+        assert_eq!(started.enemies[0].stage, BygoneStage::from("Flying"));
+
+        // Parts of other enemies are not there to be shot
+        game.attack(1, part("Core"));
+        game.finish_turn();
+        assert_eq!(game.bygone().1, BygoneStage::from("Flying"));
+
+        game.attack(1, part("🇭"));
+        game.finish_turn();
+
+        assert_eq!(game.bygone().1, BygoneStage::from("Down"));
+        assert_eq!(
+            finished_games(&game.renders()),
+            vec![FinishedGameStatus::Won]
+        );
+        // A finished game is drawn without its log, the line stays behind in the resource
+        let battle_log = game.app.world.resource::<HashMap<ArenaId, Vec<String>>>();
+        assert!(battle_log[&TestGame::arena()].contains(&"*Drone* crashes".to_string()));
+    }
+
+    #[test]
+    fn escort_has_to_be_beaten_too() {
+        let mut game = TestGame::new();
+        game.start_game_against(TestGame::arena(), 1, Difficulty::Easy, "drone");
+        game.set_bygone_attack(Attack::new(0, 0));
+        game.set_part(part("Hull"), Vitality::new(1, 0));
+        game.wait(DRAW_DELAY);
+        let renders = game.renders();
+        let slots: Vec<_> = ongoing_games(&renders)[0]
+            .enemies
+            .iter()
+            .map(|enemy| enemy.slot.0)
+            .collect();
+        assert_eq!(slots, vec![0, 1]);
+
+        game.attack_enemy(1, 1, part("Hull"));
+        game.finish_turn();
+
+        assert_eq!(game.enemy(0).1, BygoneStage::from("Flying"));
+        assert_eq!(game.enemy(1).1, BygoneStage::from("Downed"));
+        let renders = game.renders();
+        assert!(finished_games(&renders).is_empty());
+        let last_game = *ongoing_games(&renders).last().unwrap();
+        assert!(has_line(
+            last_game,
+            &["*Drone 2* crashes to the floor".to_string()]
+        ));
+
+        game.attack_enemy(1, 0, part("Hull"));
+        game.finish_turn();
+
+        assert_eq!(
+            finished_games(&game.renders()),
+            vec![FinishedGameStatus::Won]
+        );
+    }
+
+    #[test]
+    fn enemies_dont_shoot_a_player_already_down() {
+        let mut game = TestGame::new();
+        game.start_game_against(TestGame::arena(), 1, Difficulty::Easy, "drone");
+        game.set_targeting(TargetingPolicy::Random);
+        game.set_bygone_attack(Attack::new(6, 100));
+        game.set_player(1, Vitality::new(6, 0));
+        game.set_player_attack(1, Attack::new(0, 0));
+        game.wait(DRAW_DELAY);
+
+        game.attack_enemy(1, 0, part("Hull"));
+        game.finish_turn();
+
+        let (_, name) = TestGame::player(1);
+        let dead = log_lines(&game.loc().player_dead, |line| {
+            line.insert_player_name(&name)
+        });
+        let battle_log = game.app.world.resource::<HashMap<ArenaId, Vec<String>>>();
+        let deaths = battle_log[&TestGame::arena()]
+            .iter()
+            .filter(|line| dead.contains(line))
+            .count();
+        assert_eq!(deaths, 1);
+    }
+
+    /// Health that is `lost` short of the player's full 6
+    fn wounded(lost: usize) -> Vitality {
+        let mut vitality = Vitality::new(6, 100);
+        vitality.take_attack(lost, 100);
+        vitality
+    }
+
+    #[test]
+    fn heal_restores_the_most_wounded_player() {
+        let mut game = started_game();
+        game.attack(2, part("Sensor"));
+        game.finish_turn();
+        game.set_player(1, wounded(1));
+        game.set_player(2, wounded(3));
+        game.renders();
+
+        game.use_ability(1, Ability::Heal);
+        game.finish_turn();
+
+        assert_eq!(game.player_state(1).0.health().current(), 5);
+        assert_eq!(game.player_state(2).0.health().current(), 5);
+        let renders = game.renders();
+        let last_game = *ongoing_games(&renders).last().unwrap();
+        let (_, healer) = TestGame::player(1);
+        let (_, patient) = TestGame::player(2);
+        let healed = log_lines(&game.loc().abilities.player_heal, |line| {
+            line.insert_player_name(&healer).insert_ally_name(&patient)
+        });
+        assert!(has_line(last_game, &healed));
+    }
+
+    #[test]
+    fn abilities_are_dropped_once_the_battle_is_over() {
+        let mut game = started_game();
+        game.set_player(1, wounded(3));
+        game.app
+            .world
+            .resource_mut::<HashMap<ArenaId, Game>>()
+            .get_mut(&TestGame::arena())
+            .unwrap()
+            .status = FinishedGameStatus::Won.into();
+
+        game.use_ability(1, Ability::Heal);
+        game.step();
+
+        assert_eq!(game.player_state(1).0.health().current(), 3);
+    }
+
+    #[test]
+    fn defending_player_dodges_the_next_attack() {
+        let mut game = started_game();
+        // Lands on a roll of 70 or more without the defense, never with it
+        game.set_bygone_attack(Attack::new(1, 30));
+
+        game.use_ability(1, Ability::Defend);
+        game.step();
+        assert!(game.player_state(1).2);
+        game.finish_turn();
+
+        let (vitality, _, defending) = game.player_state(1);
+        assert_eq!(vitality.health().current(), 6);
+        assert!(!defending);
+    }
+
+    #[test]
+    fn focused_shot_waits_for_its_cooldown() {
+        let mut game = started_game();
+        game.set_part(part("Gun"), Vitality::new(5, 30));
+        game.renders();
+
+        game.focus(1, part("Gun"));
+        game.finish_turn();
+        let renders = game.renders();
+        let last_game = *ongoing_games(&renders).last().unwrap();
+        let (_, name) = TestGame::player(1);
+        let focused = log_lines(&game.loc().abilities.player_focus, |line| {
+            line.insert_player_name(&name)
+        });
+        assert!(has_line(last_game, &focused));
+        assert_eq!(
+            game.bygone()
+                .0
+                .get(&part("Gun"))
+                .unwrap()
+                .health()
+                .current(),
+            4
+        );
+
+        for _ in 0..Ability::FocusedShot.cooldown() - 1 {
+            game.focus(1, part("Gun"));
+            game.finish_turn();
+            assert_eq!(
+                game.bygone()
+                    .0
+                    .get(&part("Gun"))
+                    .unwrap()
+                    .health()
+                    .current(),
+                4
+            );
+        }
+
+        game.focus(1, part("Gun"));
+        game.step();
+        assert_eq!(
+            game.bygone()
+                .0
+                .get(&part("Gun"))
+                .unwrap()
+                .health()
+                .current(),
+            3
+        );
+    }
+
+    #[test]
+    fn revive_brings_a_knocked_out_player_back() {
+        let mut game = started_game();
+        game.attack(2, part("Sensor"));
+        game.finish_turn();
+        game.knock_out(2);
+        game.renders();
+
+        game.use_ability(1, Ability::Revive);
+        game.finish_turn();
+
+        let (vitality, active, _) = game.player_state(2);
+        assert!(active);
+        assert_eq!(vitality.health().current(), 2);
+        let renders = game.renders();
+        let last_game = *ongoing_games(&renders).last().unwrap();
+        let (_, reviver) = TestGame::player(1);
+        let (_, revived) = TestGame::player(2);
+        let revive = log_lines(&game.loc().abilities.player_revive, |line| {
+            line.insert_player_name(&reviver).insert_ally_name(&revived)
+        });
+        assert!(has_line(last_game, &revive));
+    }
+
+    #[test]
+    fn joining_with_a_class_sets_the_stats() {
+        let mut game = started_game();
+        game.renders();
+
+        game.join(2, PlayerClass::Heavy);
+        game.join(2, PlayerClass::Scout);
+        game.wait(DRAW_DELAY);
+
+        let (vitality, _, _) = game.player_state(2);
+        assert_eq!(vitality, PlayerClass::Heavy.vitality());
+        assert_eq!(game.player_state(1).0, PlayerClass::Fighter.vitality());
+        game.attack(1, part("Sensor"));
+        game.finish_turn();
+        let renders = game.renders();
+        let last_game = *ongoing_games(&renders).last().unwrap();
+        let (_, name) = TestGame::player(2);
+        assert!(last_game
+            .players
+            .iter()
+            .any(|(player, class, ..)| *player == name && *class == PlayerClass::Heavy));
+    }
+
+    #[test]
+    fn medic_heals_more() {
+        let mut game = started_game();
+        game.join(2, PlayerClass::Medic);
+        game.step();
+        game.set_player(1, wounded(5));
+
+        game.use_ability(2, Ability::Heal);
+        game.step();
+
+        assert_eq!(
+            game.player_state(1).0.health().current(),
+            1 + PlayerClass::Medic.heal_amount()
+        );
+    }
+
+    #[test]
+    fn burning_stage_burns_the_core() {
+        let mut game = started_game();
+        game.set_part(part("Core"), Vitality::new(1, 0));
+        game.attack(1, part("Core"));
+        game.finish_turn();
+        game.attack(1, part("Core"));
+        game.step();
+        assert_eq!(game.bygone().1, BygoneStage::from("Burning"));
+        game.set_part(part("Core"), Vitality::new(3, 100));
+        game.renders();
+
+        game.finish_turn();
+        let core_health = |game: &mut TestGame| {
+            game.bygone()
+                .0
+                .get(&part("Core"))
+                .unwrap()
+                .health()
+                .current()
+        };
+        assert_eq!(core_health(&mut game), 2);
+        let renders = game.renders();
+        let games = ongoing_games(&renders);
+        let burns = log_lines(&game.loc().effects.burn, |line| {
+            line.insert_target_name("_03")
+        });
+        assert!(games.iter().any(|payload| has_line(payload, &burns)));
+        assert!(games.last().unwrap().enemies[0]
+            .effects
+            .has(StatusEffect::Burning));
+
+        game.attack(1, part("Gun"));
+        game.finish_turn();
+        assert_eq!(core_health(&mut game), 1);
+        let renders = game.renders();
+        let last_game = *ongoing_games(&renders).last().unwrap();
+        assert!(last_game.enemies[0].effects.is_empty());
+    }
+
+    #[test]
+    fn stunned_enemy_skips_its_attack() {
+        let mut game = started_game();
+        game.set_bygone_attack(Attack::new(1, 200));
+        game.afflict_enemy(0, TimedEffect::new(StatusEffect::Stunned, 1));
+
+        game.attack(1, part("Gun"));
+        game.finish_turn();
+        assert_eq!(game.player_state(1).0.health().current(), 6);
+
+        game.attack(1, part("Gun"));
+        game.finish_turn();
+        assert_eq!(game.player_state(1).0.health().current(), 5);
+    }
+
+    #[test]
+    fn shield_lasts_its_turns() {
+        let mut game = started_game();
+        game.afflict_player(1, TimedEffect::new(StatusEffect::Shielded, 1));
+        assert_eq!(
+            game.player_state(1).0.dodge(),
+            100 + StatusEffect::Shielded.dodge()
+        );
+        // Putting it on again renews the turns instead of stacking the dodge
+        game.afflict_player(1, TimedEffect::new(StatusEffect::Shielded, 1));
+        assert_eq!(
+            game.player_state(1).0.dodge(),
+            100 + StatusEffect::Shielded.dodge()
+        );
+
+        game.attack(1, part("Gun"));
+        game.finish_turn();
+        assert_eq!(game.player_state(1).0.dodge(), 100);
+    }
+
+    #[test]
+    fn crits_multiply_the_damage() {
+        let attack = Attack::new(1, 0).with_crit(10, 3);
+        let mut target = Vitality::new(6, 50);
+
+        assert_eq!(attack.attack(&mut target, 89, 1, 100), AttackOutcome::Hit);
+        assert_eq!(target.health().current(), 5);
+        assert_eq!(attack.attack(&mut target, 90, 1, 100), AttackOutcome::Crit);
+        assert_eq!(target.health().current(), 2);
+        // A roll high enough to crit still has to get past the dodge
+        let mut target = Vitality::new(6, 200);
+        assert_eq!(attack.attack(&mut target, 99, 1, 100), AttackOutcome::Miss);
+        assert_eq!(target.health().current(), 6);
+    }
+
+    #[test]
+    fn armor_rounds_the_damage_up() {
+        let attack = Attack::new(3, 100);
+        let mut target = Vitality::new(10, 0);
+
+        attack.attack(&mut target, 0, 3, 50);
+        assert_eq!(target.health().current(), 8);
+        attack.attack(&mut target, 0, 1, 50);
+        assert_eq!(target.health().current(), 7);
+    }
+
+    #[test]
+    fn armored_core_takes_half_damage() {
+        let mut game = started_game();
+        game.set_player_attack(1, Attack::new(4, 200));
+        game.set_part(part("Core"), Vitality::new(10, 0));
+        game.set_part(part("Gun"), Vitality::new(10, 0));
+
+        game.attack(1, part("Core"));
+        game.finish_turn();
+        game.attack(1, part("Gun"));
+        game.finish_turn();
+
+        let (parts, stage) = game.bygone();
+        assert_eq!(stage, BygoneStage::from("Armored"));
+        assert_eq!(parts.get(&part("Core")).unwrap().health().current(), 8);
+        assert_eq!(parts.get(&part("Gun")).unwrap().health().current(), 6);
+    }
+
+    /// Health left of the first two players after the enemy attacked with the given policy
+    fn targeted(game: &mut TestGame, policy: TargetingPolicy) -> (usize, usize) {
+        game.set_targeting(policy);
+        game.set_bygone_attack(Attack::new(1, 200));
+        game.attack(1, part("Gun"));
+        game.attack(2, part("Gun"));
+        game.finish_turn();
+        (
+            game.player_state(1).0.health().current(),
+            game.player_state(2).0.health().current(),
+        )
+    }
+
+    #[test]
+    fn lowest_health_targeting_finishes_the_weakest() {
+        let mut game = started_game();
+        game.join(2, PlayerClass::Fighter);
+        game.step();
+        game.set_player(2, wounded(3));
+        game.renders();
+
+        assert_eq!(targeted(&mut game, TargetingPolicy::LowestHealth), (6, 2));
+        let renders = game.renders();
+        let (_, name) = TestGame::player(2);
+        let locked = log_lines(&game.loc().targeting.lowest_health, |line| {
+            line.insert_player_name(&name).insert_enemy_name("_03")
+        });
+        assert!(ongoing_games(&renders)
+            .iter()
+            .any(|payload| has_line(payload, &locked)));
+    }
+
+    #[test]
+    fn threat_targeting_goes_after_the_biggest_hitter() {
+        let mut game = started_game();
+        game.join(2, PlayerClass::Fighter);
+        game.step();
+        game.set_part(part("Gun"), Vitality::new(10, 0));
+        game.set_player_attack(1, Attack::new(1, 200));
+        game.set_player_attack(2, Attack::new(3, 200));
+
+        assert_eq!(targeted(&mut game, TargetingPolicy::Threat), (6, 5));
+    }
+
+    #[test]
+    fn healer_targeting_goes_for_medics() {
+        let mut game = started_game();
+        game.join(2, PlayerClass::Medic);
+        game.step();
+
+        let medic_health = PlayerClass::Medic.vitality().health().current();
+        assert_eq!(
+            targeted(&mut game, TargetingPolicy::Healers),
+            (6, medic_health - 1)
+        );
+    }
+
+    #[test]
+    fn barrage_hits_every_player() {
+        let mut game = started_game();
+        game.join(2, PlayerClass::Fighter);
+        game.step();
+        game.set_bygone_attack(Attack::new(1, 200));
+        game.telegraph(SpecialMove::Barrage);
+        game.renders();
+
+        game.attack(1, part("Gun"));
+        game.attack(2, part("Gun"));
+        game.finish_turn();
+
+        assert_eq!(game.player_state(1).0.health().current(), 5);
+        assert_eq!(game.player_state(2).0.health().current(), 5);
+        assert_eq!(game.specials().telegraph, None);
+        let renders = game.renders();
+        let barrage = log_lines(&game.loc().specials.barrage.unleash, |line| {
+            line.insert_enemy_name("_03")
+        });
+        assert!(ongoing_games(&renders)
+            .iter()
+            .any(|payload| has_line(payload, &barrage)));
+    }
+
+    #[test]
+    fn charged_shot_hits_harder_unless_the_sensor_is_hit() {
+        let charge = SpecialMove::ChargedShot {
+            multiplier: 2,
+            interrupted_by: part("Sensor"),
+        };
+        let mut game = started_game();
+        game.set_bygone_attack(Attack::new(1, 200));
+        game.set_player_attack(1, Attack::new(1, 200));
+        game.set_part(part("Gun"), Vitality::new(10, 0));
+        game.set_part(part("Sensor"), Vitality::new(10, 0));
+        game.telegraph(charge.clone());
+        game.attack(1, part("Gun"));
+        game.finish_turn();
+        assert_eq!(game.player_state(1).0.health().current(), 4);
+
+        game.telegraph(charge);
+        game.renders();
+        game.attack(1, part("Sensor"));
+        game.finish_turn();
+        assert_eq!(game.player_state(1).0.health().current(), 3);
+        let renders = game.renders();
+        let (_, name) = TestGame::player(1);
+        let interrupted = log_lines(&game.loc().specials.interrupted, |line| {
+            line.insert_player_name(&name).insert_enemy_name("_03")
+        });
+        assert!(ongoing_games(&renders)
+            .iter()
+            .any(|payload| has_line(payload, &interrupted)));
+    }
+
+    #[test]
+    fn armored_stage_shields_the_core_after_announcing_it() {
+        let mut game = started_game();
+        game.set_bygone_attack(Attack::new(1, 200));
+        game.set_player_attack(1, Attack::new(1, 200));
+        game.set_part(part("Gun"), Vitality::new(10, 0));
+        game.set_part(part("Core"), Vitality::new(10, 0));
+        for _ in 0..3 {
+            game.attack(1, part("Gun"));
+            game.finish_turn();
+        }
+        assert_eq!(
+            game.specials().telegraph,
+            Some(SpecialMove::Shield(part("Core")))
+        );
+
+        game.attack(1, part("Gun"));
+        game.finish_turn();
+        let specials = game.specials();
+        assert_eq!(specials.telegraph, None);
+        assert_eq!(specials.shielded, Some(part("Core")));
+        let renders = game.renders();
+        let last_game = *ongoing_games(&renders).last().unwrap();
+        assert!(last_game
+            .enemies
+            .iter()
+            .any(|enemy| enemy.specials.shielded == Some(part("Core"))));
+
+        game.attack(1, part("Core"));
+        game.finish_turn();
+        let (parts, _) = game.bygone();
+        assert_eq!(parts.get(&part("Core")).unwrap().health().current(), 10);
+        assert_eq!(game.specials().shielded, None);
+        let renders = game.renders();
+        let (_, name) = TestGame::player(1);
+        let blocked = log_lines(&game.loc().specials.blocked, |line| {
+            line.insert_player_name(&name)
+                .insert_part_name(&game.part_name(&part("Core")))
+        });
+        assert!(ongoing_games(&renders)
+            .iter()
+            .any(|payload| has_line(payload, &blocked)));
+    }
+
+    #[test]
+    fn survival_sends_a_stronger_wave_until_the_players_fall() {
+        let mut game = TestGame::new();
+        game.start_survival(1, Difficulty::Easy);
+        game.set_bygone_attack(Attack::new(0, 0));
+        game.wait(DRAW_DELAY);
+        game.set_part(part("Core"), Vitality::new(1, 0));
+        game.renders();
+
+        for _ in 0..3 {
+            game.attack(1, part("Core"));
+            game.finish_turn();
+        }
+        game.wait(DRAW_DELAY);
+
+        let renders = game.renders();
+        assert!(finished_games(&renders).is_empty());
+        let (parts, stage) = game.bygone();
+        assert_eq!(stage, BygoneStage::from("Armored"));
+        assert_eq!(parts.get(&part("Core")).unwrap().health().max(), 2);
+        let last_game = *ongoing_games(&renders).last().unwrap();
+        assert_eq!(last_game.wave, Some(2));
+        let next_wave = log_lines(&game.loc().survival.next_wave, |line| {
+            line.insert_wave(2).insert_enemy_name("_03")
+        });
+        assert!(ongoing_games(&renders)
+            .iter()
+            .any(|payload| has_line(payload, &next_wave)));
+
+        game.set_bygone_attack(Attack::new(6, 100));
+        game.attack(1, part("Sensor"));
+        game.finish_turn();
+        assert_eq!(
+            finished_games(&game.renders()),
+            vec![FinishedGameStatus::Survived(1)]
+        );
+    }
+
+    fn strict_game() -> TestGame {
+        let mut game = TestGame::new();
+        game.start_strict(1, Difficulty::Easy);
+        game.join(2, PlayerClass::Scout);
+        game.set_bygone_attack(Attack::new(0, 0));
+        game.wait(DRAW_DELAY);
+        game.set_part(part("Gun"), Vitality::new(5, 0));
+        for player in [1, 2] {
+            game.set_player_attack(player, Attack::new(1, 200));
+        }
+        game.renders();
+        game
+    }
+
+    fn hit_lines(game: &TestGame, player: u64) -> Vec<String> {
+        let (_, name) = TestGame::player(player);
+        let part_name = game.part_name(&part("Gun"));
+        log_lines(&game.loc().player_hit, |line| {
+            line.insert_player_name(&name).insert_part_name(&part_name)
+        })
+    }
+
+    #[test]
+    fn strict_turn_waits_for_every_player() {
+        let mut game = strict_game();
+
+        game.attack(1, part("Gun"));
+        game.finish_turn();
+        let (parts, _) = game.bygone();
+        assert_eq!(parts.get(&part("Gun")).unwrap().health().current(), 5);
+        assert!(ongoing_games(&game.renders()).is_empty());
+
+        game.attack(2, part("Gun"));
+        game.wait(DRAW_DELAY);
+        let renders = game.renders();
+        let last_game = *ongoing_games(&renders).last().unwrap();
+        assert!(has_line(last_game, &hit_lines(&game, 1)));
+        assert!(has_line(last_game, &hit_lines(&game, 2)));
+    }
+
+    #[test]
+    fn strict_turn_resolves_without_the_idle_after_the_timeout() {
+        let mut game = strict_game();
+
+        game.attack(1, part("Gun"));
+        game.skip(Timings::default().turn_timeout());
+        game.wait(DRAW_DELAY);
+        let renders = game.renders();
+        let last_game = *ongoing_games(&renders).last().unwrap();
+        assert!(has_line(last_game, &hit_lines(&game, 1)));
+        assert!(!has_line(last_game, &hit_lines(&game, 2)));
+
+        // The idle player gets to act in the next turn
+        game.attack(2, part("Gun"));
+        game.attack(1, part("Gun"));
+        game.wait(DRAW_DELAY);
+        let renders = game.renders();
+        let last_game = *ongoing_games(&renders).last().unwrap();
+        assert!(has_line(last_game, &hit_lines(&game, 2)));
+    }
+
+    #[test]
+    fn strict_turn_resolves_in_initiative_order() {
+        let mut game = strict_game();
+        game.set_part(part("Gun"), Vitality::new(1, 0));
+
+        // The fighter acts first but the scout is quicker and takes the gun out before them
+        game.attack(1, part("Gun"));
+        game.attack(2, part("Gun"));
+        game.wait(DRAW_DELAY);
+        let renders = game.renders();
+        let last_game = *ongoing_games(&renders).last().unwrap();
+        assert!(has_line(last_game, &hit_lines(&game, 2)));
+        assert!(!has_line(last_game, &hit_lines(&game, 1)));
+    }
+}
@@ -0,0 +1,588 @@
+use std::{collections::HashMap, fs, path::PathBuf, time::Duration};
+
+use bevy::prelude::*;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use twilight_model::id::{
+    marker::{InteractionMarker, UserMarker},
+    Id,
+};
+
+use crate::{
+    bundles::BygoneParts,
+    components::{
+        Ability, Active, Attack, BygonePart, BygoneStage, Defending, Enemy, EnemySlot, Player,
+        PlayerClass, PlayerName, SpecialMove, SpecialMoves, TargetingPolicy, TimedEffect,
+        UserIdComponent, Vitality,
+    },
+    enemies::{EnemyDefinitions, DEFAULT_ENEMY},
+    events::*,
+    game_app,
+    game_helpers::{
+        ArenaId, Clock, Difficulty, FinishedGameStatus, Game, GameMode, GameScope, TurnMode,
+    },
+    guild_config::GuildConfigUpdate,
+    localization::{Language, LanguageChoice, Localization, Localizations, LocalizedLine},
+    scoreboard::{PlayerStats, Scoreboard},
+    timings::TimingConfig,
+};
+
+pub const TEST_SEED: u64 = 3;
+pub const TEST_GUILD_ID: u64 = 1;
+pub const TEST_CHANNEL_ID: u64 = 1;
+/// Same pace as the schedule runner in `main`
+pub const FRAME: Duration = Duration::from_millis(100);
+/// Long enough for a battle to be drawn after it starts
+pub const DRAW_DELAY: Duration = Duration::from_millis(600);
+
+/// The game app without Discord: inputs go in through the channel a chat transport would feed,
+/// the render events that come out of `render` are collected for the test to look at
+pub struct TestGame {
+    pub app: App,
+    inputs: Sender<InputEvent>,
+    renders: Receiver<GameRenderEvent>,
+    next_interaction_id: u64,
+    localization: Localization,
+}
+
+impl TestGame {
+    pub fn new() -> Self {
+        Self::with_timings(TimingConfig::default())
+    }
+
+    pub fn with_timings(timings: TimingConfig) -> Self {
+        let (input_sender, input_receiver) = unbounded();
+        let (output_sender, output_receiver) = unbounded();
+        Self {
+            app: game_app(
+                input_receiver,
+                output_sender,
+                Scoreboard::default(),
+                Clock::manual(),
+                GameScope::Channel,
+                EnemyDefinitions::default(),
+                timings,
+                Some(TEST_SEED),
+            ),
+            inputs: input_sender,
+            renders: output_receiver,
+            next_interaction_id: 1,
+            localization: Localizations::new().get(Language::En).clone(),
+        }
+    }
+
+    pub fn loc(&self) -> &Localization {
+        &self.localization
+    }
+
+    pub fn arena() -> ArenaId {
+        Self::channel(TEST_CHANNEL_ID)
+    }
+
+    pub fn channel(channel_id: u64) -> ArenaId {
+        ArenaId::new(Id::new(TEST_GUILD_ID), Some(Id::new(channel_id)))
+    }
+
+    pub fn guild(guild_id: u64) -> ArenaId {
+        ArenaId::new(Id::new(guild_id), Some(Id::new(TEST_CHANNEL_ID)))
+    }
+
+    pub fn player(number: u64) -> (Id<UserMarker>, PlayerName) {
+        (Id::new(number), PlayerName(format!("Player {}", number)))
+    }
+
+    pub fn send(&mut self, ev: InputEvent) {
+        self.queue(ev);
+        self.step();
+    }
+
+    /// Sends an input without running a frame, everything queued is picked up together by the next one
+    pub fn queue(&mut self, ev: InputEvent) {
+        self.inputs.send(ev).unwrap();
+    }
+
+    pub fn start_game(&mut self, player: u64, difficulty: Difficulty) {
+        self.start_game_in(Self::arena(), player, difficulty);
+    }
+
+    pub fn start_game_in(&mut self, arena: ArenaId, player: u64, difficulty: Difficulty) {
+        self.start_game_against(arena, player, difficulty, DEFAULT_ENEMY);
+    }
+
+    pub fn start_game_against(
+        &mut self,
+        arena: ArenaId,
+        player: u64,
+        difficulty: Difficulty,
+        enemy: &str,
+    ) {
+        self.start_game_in_mode(
+            arena,
+            player,
+            difficulty,
+            enemy,
+            GameMode::Classic,
+            TurnMode::RealTime,
+        );
+    }
+
+    pub fn start_survival(&mut self, player: u64, difficulty: Difficulty) {
+        self.start_game_in_mode(
+            Self::arena(),
+            player,
+            difficulty,
+            DEFAULT_ENEMY,
+            GameMode::Survival,
+            TurnMode::RealTime,
+        );
+    }
+
+    pub fn start_strict(&mut self, player: u64, difficulty: Difficulty) {
+        self.start_game_in_mode(
+            Self::arena(),
+            player,
+            difficulty,
+            DEFAULT_ENEMY,
+            GameMode::Classic,
+            TurnMode::Strict,
+        );
+    }
+
+    /// Starts a battle without a difficulty or a language, like a bare `/battle` would
+    pub fn start_default_game_in(&mut self, arena: ArenaId, player: u64) {
+        self.start_game_speaking(arena, player, LanguageChoice::default());
+    }
+
+    /// Starts a battle without a difficulty, from a command with these language hints
+    pub fn start_game_speaking(&mut self, arena: ArenaId, player: u64, language: LanguageChoice) {
+        self.send_game_start(
+            arena,
+            player,
+            None,
+            DEFAULT_ENEMY,
+            GameMode::Classic,
+            TurnMode::RealTime,
+            language,
+        );
+    }
+
+    fn start_game_in_mode(
+        &mut self,
+        arena: ArenaId,
+        player: u64,
+        difficulty: Difficulty,
+        enemy: &str,
+        mode: GameMode,
+        turns: TurnMode,
+    ) {
+        self.send_game_start(
+            arena,
+            player,
+            Some(difficulty),
+            enemy,
+            mode,
+            turns,
+            LanguageChoice::default(),
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn send_game_start(
+        &mut self,
+        arena: ArenaId,
+        player: u64,
+        difficulty: Option<Difficulty>,
+        enemy: &str,
+        mode: GameMode,
+        turns: TurnMode,
+        language: LanguageChoice,
+    ) {
+        let (user_id, name) = Self::player(player);
+        let interaction = self.next_interaction();
+        let localization = self.localization.clone();
+        self.send(InputEvent::GameStart(Box::new(GameStartEvent::new(
+            user_id,
+            name,
+            difficulty,
+            enemy.to_string(),
+            PlayerClass::default(),
+            mode,
+            turns,
+            arena,
+            interaction,
+            language,
+            localization,
+        ))));
+    }
+
+    pub fn configure(&mut self, update: GuildConfigUpdate, manage_guild: bool) {
+        self.configure_in(Self::arena(), update, manage_guild);
+    }
+
+    pub fn configure_in(&mut self, arena: ArenaId, update: GuildConfigUpdate, manage_guild: bool) {
+        let interaction = self.next_interaction();
+        self.send(InputEvent::Config(Box::new(ConfigRequestEvent::new(
+            arena,
+            interaction,
+            LanguageChoice::default(),
+            update,
+            manage_guild,
+        ))));
+    }
+
+    pub fn end_game(&mut self, player: u64) {
+        self.end_game_speaking(player, LanguageChoice::default());
+    }
+
+    pub fn end_game_speaking(&mut self, player: u64, language: LanguageChoice) {
+        let (user_id, _) = Self::player(player);
+        let interaction = self.next_interaction();
+        self.send(InputEvent::GameEnd(Box::new(EndRequestEvent::new(
+            user_id,
+            Self::arena(),
+            interaction,
+            language,
+        ))));
+    }
+
+    /// What `/leaderboard` shows for the test guild, the renders before it are dropped
+    pub fn leaderboard(&mut self) -> Vec<PlayerStats> {
+        let interaction = self.next_interaction();
+        self.send(InputEvent::Leaderboard(Box::new(
+            LeaderboardRequestEvent::new(
+                Self::arena(),
+                interaction,
+                LanguageChoice::default(),
+                self.localization.clone(),
+            ),
+        )));
+        self.renders()
+            .into_iter()
+            .find_map(|payload| match payload {
+                GameRenderPayload::Leaderboard(stats) => Some(stats),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    fn next_interaction(&mut self) -> Id<InteractionMarker> {
+        let interaction = Id::new(self.next_interaction_id);
+        self.next_interaction_id += 1;
+        interaction
+    }
+
+    /// The battle in the arena as the game keeps it
+    pub fn game(&self, arena: ArenaId) -> &Game {
+        &self.app.world.resource::<HashMap<ArenaId, Game>>()[&arena]
+    }
+
+    pub fn attack(&mut self, player: u64, target: BygonePart) {
+        self.attack_in(Self::arena(), player, target);
+    }
+
+    pub fn attack_in(&mut self, arena: ArenaId, player: u64, target: BygonePart) {
+        self.send(Self::attack_event(arena, player, target));
+    }
+
+    pub fn attack_enemy(&mut self, player: u64, enemy: usize, target: BygonePart) {
+        let (user_id, name) = Self::player(player);
+        self.send(InputEvent::PlayerAttack(PlayerAttackEvent::new(
+            user_id,
+            name,
+            Self::arena(),
+            EnemySlot(enemy),
+            target,
+        )));
+    }
+
+    pub fn attack_event(arena: ArenaId, player: u64, target: BygonePart) -> InputEvent {
+        let (user_id, name) = Self::player(player);
+        InputEvent::PlayerAttack(PlayerAttackEvent::new(
+            user_id,
+            name,
+            arena,
+            EnemySlot::default(),
+            target,
+        ))
+    }
+
+    pub fn join(&mut self, player: u64, class: PlayerClass) {
+        let (user_id, name) = Self::player(player);
+        self.send(InputEvent::PlayerJoin(JoinRequestEvent::new(
+            user_id,
+            name,
+            Self::arena(),
+            class,
+        )));
+    }
+
+    pub fn use_ability(&mut self, player: u64, ability: Ability) {
+        let (user_id, name) = Self::player(player);
+        self.send(InputEvent::PlayerAbility(PlayerAbilityEvent::new(
+            user_id,
+            name,
+            Self::arena(),
+            ability,
+            None,
+        )));
+    }
+
+    pub fn focus(&mut self, player: u64, target: BygonePart) {
+        let (user_id, name) = Self::player(player);
+        self.send(InputEvent::PlayerAbility(PlayerAbilityEvent::new(
+            user_id,
+            name,
+            Self::arena(),
+            Ability::FocusedShot,
+            Some((EnemySlot::default(), target)),
+        )));
+    }
+
+    pub fn step(&mut self) {
+        self.app.update();
+    }
+
+    /// Lets time pass frame by frame, like the schedule runner would
+    pub fn wait(&mut self, duration: Duration) {
+        for _ in 0..(duration.as_millis() / FRAME.as_millis()) {
+            self.app.world.resource_mut::<Clock>().advance(FRAME);
+            self.step();
+        }
+    }
+
+    /// Jumps over a long stretch of time at once, only a couple of frames run
+    pub fn skip(&mut self, duration: Duration) {
+        self.app.world.resource_mut::<Clock>().advance(duration);
+        self.step();
+        self.step();
+    }
+
+    /// Waits out the rest of the current turn and a bit more for the delayed draws
+    pub fn finish_turn(&mut self) {
+        self.wait(Duration::from_secs(11));
+    }
+
+    pub fn renders(&self) -> Vec<GameRenderPayload> {
+        self.render_events()
+            .into_iter()
+            .map(|ev| ev.payload)
+            .collect()
+    }
+
+    /// The renders along with the lines they are drawn with
+    pub fn render_events(&self) -> Vec<GameRenderEvent> {
+        self.renders.try_iter().collect()
+    }
+
+    pub fn set_part(&mut self, part: BygonePart, vitality: Vitality) {
+        let mut query = self
+            .app
+            .world
+            .query_filtered::<&mut BygoneParts, With<Enemy>>();
+        for mut parts in query.iter_mut(&mut self.app.world) {
+            if let Some(part_vitality) = parts.get_mut(&part) {
+                *part_vitality = vitality;
+            }
+        }
+    }
+
+    pub fn set_bygone_attack(&mut self, attack: Attack) {
+        let mut query = self.app.world.query_filtered::<&mut Attack, With<Enemy>>();
+        for mut bygone_attack in query.iter_mut(&mut self.app.world) {
+            *bygone_attack = attack;
+        }
+    }
+
+    pub fn set_targeting(&mut self, policy: TargetingPolicy) {
+        let mut query = self
+            .app
+            .world
+            .query_filtered::<&mut TargetingPolicy, With<Enemy>>();
+        for mut targeting in query.iter_mut(&mut self.app.world) {
+            *targeting = policy;
+        }
+    }
+
+    pub fn telegraph(&mut self, special: SpecialMove) {
+        let mut query = self
+            .app
+            .world
+            .query_filtered::<&mut SpecialMoves, With<Enemy>>();
+        for mut specials in query.iter_mut(&mut self.app.world) {
+            specials.telegraph = Some(special.clone());
+        }
+    }
+
+    pub fn specials(&mut self) -> SpecialMoves {
+        let mut query = self
+            .app
+            .world
+            .query_filtered::<&SpecialMoves, With<Enemy>>();
+        query.single(&self.app.world).clone()
+    }
+
+    fn player_entity(&mut self, player: u64) -> Entity {
+        let (user_id, _) = Self::player(player);
+        let mut query = self
+            .app
+            .world
+            .query_filtered::<(Entity, &UserIdComponent), With<Player>>();
+        let (entity, _) = query
+            .iter(&self.app.world)
+            .find(|(_, player_id)| player_id.0 == user_id)
+            .unwrap();
+        entity
+    }
+
+    pub fn set_player(&mut self, player: u64, vitality: Vitality) {
+        let entity = self.player_entity(player);
+        *self.app.world.get_mut::<Vitality>(entity).unwrap() = vitality;
+    }
+
+    pub fn set_player_attack(&mut self, player: u64, attack: Attack) {
+        let entity = self.player_entity(player);
+        *self.app.world.get_mut::<Attack>(entity).unwrap() = attack;
+    }
+
+    /// Takes all the player's health, the way the enemy would
+    pub fn knock_out(&mut self, player: u64) {
+        let entity = self.player_entity(player);
+        let mut vitality = Vitality::new(6, 0);
+        vitality.take_attack(6, 0);
+        *self.app.world.get_mut::<Vitality>(entity).unwrap() = vitality;
+        self.app.world.entity_mut(entity).remove::<Active>();
+    }
+
+    /// The player's vitality, whether they are still in the fight and whether they are defending
+    pub fn player_state(&mut self, player: u64) -> (Vitality, bool, bool) {
+        let entity = self.player_entity(player);
+        let world = &self.app.world;
+        (
+            *world.get::<Vitality>(entity).unwrap(),
+            world.get::<Active>(entity).is_some(),
+            world.get::<Defending>(entity).is_some(),
+        )
+    }
+
+    /// Puts an effect on the player the way the enemies do
+    pub fn afflict_player(&mut self, player: u64, effect: TimedEffect) {
+        let entity = self.player_entity(player);
+        self.app
+            .world
+            .send_event(StatusEffectEvent::new(entity, Self::arena(), effect));
+        self.step();
+    }
+
+    pub fn afflict_enemy(&mut self, slot: usize, effect: TimedEffect) {
+        let mut query = self
+            .app
+            .world
+            .query_filtered::<(Entity, &EnemySlot), With<Enemy>>();
+        let (entity, _) = query
+            .iter(&self.app.world)
+            .find(|(_, enemy_slot)| enemy_slot.0 == slot)
+            .unwrap();
+        self.app
+            .world
+            .send_event(StatusEffectEvent::new(entity, Self::arena(), effect));
+        self.step();
+    }
+
+    /// The leading enemy
+    pub fn bygone(&mut self) -> (BygoneParts, BygoneStage) {
+        self.enemy(0)
+    }
+
+    pub fn enemy(&mut self, slot: usize) -> (BygoneParts, BygoneStage) {
+        let mut query = self
+            .app
+            .world
+            .query_filtered::<(&EnemySlot, &BygoneParts, &BygoneStage), With<Enemy>>();
+        let (_, parts, stage) = query
+            .iter(&self.app.world)
+            .find(|(enemy_slot, _, _)| enemy_slot.0 == slot)
+            .unwrap();
+        (parts.clone(), stage.clone())
+    }
+
+    /// How the default enemy's part is called in the battle log
+    pub fn part_name(&self, part: &BygonePart) -> String {
+        self.app
+            .world
+            .resource::<EnemyDefinitions>()
+            .get(DEFAULT_ENEMY)
+            .localization(self.localization.language)
+            .part_name(part)
+    }
+}
+
+/// A file in the temp dir for the test to load, removed once the test is done with it
+pub struct TempFile(PathBuf);
+
+impl TempFile {
+    /// The name only has to be unique among the tests, the process id keeps parallel runs apart
+    pub fn new(name: &str, contents: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("{}-{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        Self(path)
+    }
+
+    /// The way the file options of the command line hold it
+    pub fn path(&self) -> Option<PathBuf> {
+        Some(self.0.clone())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+pub fn part(id: &str) -> BygonePart {
+    BygonePart::from(id)
+}
+
+pub fn ongoing_games(renders: &[GameRenderPayload]) -> Vec<&OngoingGamePayload> {
+    renders
+        .iter()
+        .filter_map(|payload| match payload {
+            GameRenderPayload::OngoingGame(payload) => Some(payload.as_ref()),
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn finished_games(renders: &[GameRenderPayload]) -> Vec<FinishedGameStatus> {
+    renders
+        .iter()
+        .filter_map(|payload| match payload {
+            GameRenderPayload::FinishedGame(status) => Some(*status),
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn oneshots(renders: &[GameRenderPayload]) -> Vec<OneshotType> {
+    renders
+        .iter()
+        .filter_map(|payload| match payload {
+            GameRenderPayload::OneshotMessage(oneshot_type) => Some(oneshot_type.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Every way a battle log line could have been worded, the system picks one of them at random
+pub fn log_lines(
+    variants: &[LocalizedLine],
+    fill: impl Fn(&LocalizedLine) -> LocalizedLine,
+) -> Vec<String> {
+    variants.iter().map(|line| fill(line).0).collect()
+}
+
+pub fn has_line(payload: &OngoingGamePayload, variants: &[String]) -> bool {
+    payload
+        .battle_log_lines
+        .iter()
+        .any(|line| variants.contains(line))
+}
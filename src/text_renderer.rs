@@ -0,0 +1,166 @@
+use rand::seq::SliceRandom;
+
+use crate::{
+    events::{OneshotType, OngoingGamePayload},
+    game_helpers::FinishedGameStatus,
+    guild_config::GuildConfig,
+    localization::{Localization, RenderText},
+    scoreboard::PlayerStats,
+};
+
+const PROGRESS_BAR_SIZE: usize = 10;
+
+#[derive(Clone, Debug, Default)]
+pub struct TextRenderer;
+
+impl TextRenderer {
+    pub fn render_ongoing_game(loc: &Localization, payload: &OngoingGamePayload) -> String {
+        let mut lines = Vec::new();
+        if let Some(leader) = payload.enemies.first() {
+            lines.push(format!(
+                "=== {} ===",
+                leader.definition.localization(loc.language).title.0
+            ));
+        }
+        for enemy in payload.enemies.iter() {
+            let enemy_loc = enemy.definition.localization(loc.language);
+            if payload.enemies.len() > 1 {
+                lines.push(format!(
+                    "{}:",
+                    enemy_loc.name_in(enemy.slot, payload.enemies.len())
+                ));
+            } else {
+                lines.push(format!("{}:", loc.status_title));
+            }
+            if let (Some(wave), 0) = (payload.wave, enemy.slot.0) {
+                lines.push(format!(" • {}: {}", loc.survival.wave_title, wave));
+            }
+            lines.push(format!(" • {}", enemy.attack.render_text(loc)));
+            lines.push(format!(
+                " • {}: {}",
+                enemy_loc.stage_title.0,
+                enemy_loc.stage_name(&enemy.stage)
+            ));
+            if !enemy.effects.is_empty() {
+                lines.push(format!(
+                    " • {}: {}",
+                    loc.effects.title,
+                    loc.effects.list(&enemy.effects)
+                ));
+            }
+            if let Some(special) = &enemy.specials.telegraph {
+                lines.push(format!(
+                    " • {}: {}",
+                    loc.specials.next_title,
+                    loc.specials.get(special).name
+                ));
+            }
+            if let Some(part) = &enemy.specials.shielded {
+                lines.push(format!(
+                    " • {}: {}",
+                    loc.specials.shield_title,
+                    enemy_loc.part_name(part)
+                ));
+            }
+            // Discord titles are made of emoji, the terminal shows the key to type instead
+            for part in enemy.definition.parts.iter() {
+                if let Some(vitality) = enemy.parts.get(&part.id) {
+                    lines.push(format!(
+                        "[{}] {}: {}",
+                        part.key,
+                        enemy_loc.part_name(&part.id),
+                        vitality.render_text(loc)
+                    ));
+                }
+            }
+        }
+        lines.push(format!("{}:", loc.log_title));
+        for line in payload.battle_log_lines.iter() {
+            lines.push(format!(" • {}", line));
+        }
+        for (name, class, vitality, effects) in payload.players.iter() {
+            let mut line = format!(
+                "{} ({}): {}",
+                name.0,
+                loc.classes.name(*class),
+                vitality.health().render_text(loc)
+            );
+            if !effects.is_empty() {
+                line += &format!(" [{}]", loc.effects.list(effects));
+            }
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+
+    pub fn render_finished_game(loc: &Localization, status: FinishedGameStatus) -> String {
+        match status {
+            FinishedGameStatus::Won => loc.won.0.clone(),
+            FinishedGameStatus::Lost => loc.lost.0.clone(),
+            FinishedGameStatus::Expired => loc
+                .expired
+                .choose(&mut rand::thread_rng())
+                .unwrap()
+                .0
+                .clone(),
+            FinishedGameStatus::Survived(waves) => loc.survival.summary.insert_waves(waves).0,
+            FinishedGameStatus::Ended => loc.ending.ended.0.clone(),
+        }
+    }
+
+    pub fn render_turn_progress(loc: &Localization, progress: f32) -> String {
+        let filled_count = ((progress * PROGRESS_BAR_SIZE as f32).round().max(0.0) as usize)
+            .min(PROGRESS_BAR_SIZE);
+        format!(
+            "{}: [{}{}]",
+            loc.turn_progress_title,
+            "#".repeat(filled_count),
+            "-".repeat(PROGRESS_BAR_SIZE - filled_count)
+        )
+    }
+
+    pub fn render_leaderboard(stats: &[PlayerStats], loc: &Localization) -> String {
+        let mut lines = vec![format!("=== {} ===", loc.leaderboard_title)];
+        if stats.is_empty() {
+            lines.push(loc.leaderboard_empty.0.clone());
+        }
+        for (place, player_stats) in stats.iter().enumerate() {
+            lines.push(format!(
+                "{}. {}: {}",
+                place + 1,
+                player_stats.name.0,
+                loc.leaderboard_stats
+                    .insert_player_stats(player_stats)
+                    .0
+                    .replace('\n', " • ")
+            ));
+        }
+        lines.join("\n")
+    }
+
+    pub fn render_config(config: &GuildConfig, loc: &Localization) -> String {
+        let mut lines = vec![format!("=== {} ===", loc.config.title)];
+        for (name, value) in loc
+            .config
+            .settings(config, |channel_id| format!("#{}", channel_id))
+        {
+            lines.push(format!("{}: {}", name, value));
+        }
+        lines.join("\n")
+    }
+
+    pub fn render_oneshot(oneshot_type: OneshotType, loc: &Localization) -> String {
+        match oneshot_type {
+            OneshotType::Cooldown(duration_left) => {
+                loc.battle_cooldown.insert_duration(&duration_left).0
+            }
+            OneshotType::OtherGameInProgress => loc.other_battle_ongoing.0.clone(),
+            OneshotType::ChannelNotAllowed => loc.config.channel_not_allowed.0.clone(),
+            OneshotType::NotPermitted => loc.config.not_permitted.0.clone(),
+            OneshotType::Config(config) => Self::render_config(&config, loc),
+            OneshotType::GameEnded => loc.ending.called_off.0.clone(),
+            OneshotType::NotAllowedToEnd => loc.ending.not_allowed.0.clone(),
+            OneshotType::NoGameToEnd => loc.ending.no_battle.0.clone(),
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use twilight_model::id::{marker::GuildMarker, Id};
+
+use crate::{io::read_json, logging::format_time};
+
+/// Discord stops accepting edits of a game message once its interaction token expires
+pub const INTERACTION_TOKEN_TTL_SECS: u64 = 15 * 60;
+/// The enemy shoots this long before the turn ends, so the turn end draw shows the shot
+const ENEMY_ATTACK_LEAD: Duration = Duration::from_millis(500);
+
+/// How fast battles go. Everything but the frame and the event delay can be set per guild
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Timings {
+    pub turn_secs: u64,
+    pub progress_bar_tick_secs: u64,
+    /// How long the attacks of a player who joins by attacking wait for the player to spawn
+    pub event_delay_millis: u64,
+    pub frame_millis: u64,
+    pub max_game_duration_secs: u64,
+    /// Counted from the start of the last game in the arena
+    pub game_cooldown_secs: u64,
+    /// How long a strict turn waits for the players who haven't acted, counted from the first action
+    pub turn_timeout_secs: u64,
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self {
+            turn_secs: 10,
+            progress_bar_tick_secs: 2,
+            event_delay_millis: 500,
+            frame_millis: 100,
+            max_game_duration_secs: INTERACTION_TOKEN_TTL_SECS - 10,
+            game_cooldown_secs: INTERACTION_TOKEN_TTL_SECS - 5,
+            turn_timeout_secs: 60,
+        }
+    }
+}
+
+impl Timings {
+    pub fn turn(&self) -> Duration {
+        Duration::from_secs(self.turn_secs)
+    }
+
+    pub fn enemy_attack_delay(&self) -> Duration {
+        self.turn().saturating_sub(ENEMY_ATTACK_LEAD)
+    }
+
+    pub fn event_delay(&self) -> Duration {
+        Duration::from_millis(self.event_delay_millis)
+    }
+
+    pub fn frame(&self) -> Duration {
+        Duration::from_millis(self.frame_millis)
+    }
+
+    pub fn turn_timeout(&self) -> Duration {
+        Duration::from_secs(self.turn_timeout_secs)
+    }
+
+    /// Whole turns a battle lasts before it expires
+    pub fn max_game_turns(&self) -> usize {
+        (self.max_game_duration_secs / self.turn_secs) as usize
+    }
+
+    pub fn with(self, overrides: &TimingOverrides) -> Self {
+        Self {
+            turn_secs: overrides.turn_secs.unwrap_or(self.turn_secs),
+            progress_bar_tick_secs: overrides
+                .progress_bar_tick_secs
+                .unwrap_or(self.progress_bar_tick_secs),
+            event_delay_millis: overrides
+                .event_delay_millis
+                .unwrap_or(self.event_delay_millis),
+            frame_millis: overrides.frame_millis.unwrap_or(self.frame_millis),
+            max_game_duration_secs: overrides
+                .max_game_duration_secs
+                .unwrap_or(self.max_game_duration_secs),
+            game_cooldown_secs: overrides
+                .game_cooldown_secs
+                .unwrap_or(self.game_cooldown_secs),
+            turn_timeout_secs: overrides
+                .turn_timeout_secs
+                .unwrap_or(self.turn_timeout_secs),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let positive = [
+            ("turn_secs", self.turn_secs),
+            ("progress_bar_tick_secs", self.progress_bar_tick_secs),
+            ("frame_millis", self.frame_millis),
+            ("turn_timeout_secs", self.turn_timeout_secs),
+        ];
+        if let Some((name, _)) = positive.iter().find(|(_, value)| *value == 0) {
+            return Err(format!("{} must be above zero", name));
+        }
+        let max = Timings::default().max_game_duration_secs;
+        if self.max_game_duration_secs > max {
+            return Err(format!(
+                "max_game_duration_secs can't be above {}, the game message can't be updated after that",
+                max
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Timings to change, in the config file for a guild or on the command line for everyone.
+/// The command line ones can come from the environment too
+#[derive(Args, Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(default)]
+pub struct TimingOverrides {
+    /// Seconds in a turn
+    #[clap(long, value_parser, value_name = "SECS", env = "TURN_SECS")]
+    pub turn_secs: Option<u64>,
+    /// Seconds between the turn progress bar updates
+    #[clap(
+        long,
+        value_parser,
+        value_name = "SECS",
+        env = "PROGRESS_BAR_TICK_SECS"
+    )]
+    pub progress_bar_tick_secs: Option<u64>,
+    /// Milliseconds the attack of a joining player waits for them to spawn
+    #[clap(long, value_parser, value_name = "MILLIS", env = "EVENT_DELAY_MILLIS")]
+    pub event_delay_millis: Option<u64>,
+    /// Milliseconds in a game frame
+    #[clap(long, value_parser, value_name = "MILLIS", env = "FRAME_MILLIS")]
+    pub frame_millis: Option<u64>,
+    /// Seconds after which a battle expires, 890 at most
+    #[clap(
+        long,
+        value_parser,
+        value_name = "SECS",
+        env = "MAX_GAME_DURATION_SECS"
+    )]
+    pub max_game_duration_secs: Option<u64>,
+    /// Seconds from the start of a battle until the next one can start in the same arena
+    #[clap(long, value_parser, value_name = "SECS", env = "GAME_COOLDOWN_SECS")]
+    pub game_cooldown_secs: Option<u64>,
+    /// Seconds a strict turn waits for the players who haven't acted yet
+    #[clap(long, value_parser, value_name = "SECS", env = "TURN_TIMEOUT_SECS")]
+    pub turn_timeout_secs: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TimingConfig {
+    #[serde(flatten)]
+    pub base: Timings,
+    #[serde(default)]
+    pub guilds: HashMap<Id<GuildMarker>, TimingOverrides>,
+}
+
+impl TimingConfig {
+    /// Reads the config file if there is one and puts the command line overrides on top.
+    /// Broken timings are reported and replaced with the defaults
+    pub fn load(path: &Option<PathBuf>, overrides: &TimingOverrides) -> Self {
+        let mut config = match path {
+            Some(path) => read_json::<Self>(path),
+            None => Self::default(),
+        };
+        config.base = config.base.with(overrides);
+        if let Err(err) = config.base.validate() {
+            println!(
+                "{} - timings - FAILED to load timings, using the default ones: {}",
+                format_time(),
+                err
+            );
+            config.base = Timings::default();
+        }
+        let base = config.base;
+        config.guilds.retain(|guild_id, overrides| {
+            match base.with(overrides).validate() {
+                Ok(()) => true,
+                Err(err) => {
+                    println!(
+                        "{} - timings - FAILED to load timings of guild {}, using the common ones: {}",
+                        format_time(),
+                        guild_id,
+                        err
+                    );
+                    false
+                }
+            }
+        });
+        config
+    }
+
+    pub fn guild(&self, guild_id: Id<GuildMarker>) -> Timings {
+        match self.guilds.get(&guild_id) {
+            Some(overrides) => self.base.with(overrides),
+            None => self.base,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use clap::Parser;
+    use twilight_model::id::Id;
+
+    use super::{TimingConfig, TimingOverrides, Timings};
+    use crate::{cli::Cli, test_support::TempFile};
+
+    #[test]
+    fn command_line_fills_in_the_overrides() {
+        // Given on the command line, so whatever the environment has doesn't matter
+        let cli = Cli::try_parse_from([
+            "test_discord_bot",
+            "--timings-path",
+            "timings.json",
+            "--game-cooldown-secs",
+            "50",
+            "--turn-timeout-secs",
+            "90",
+        ])
+        .unwrap();
+        assert_eq!(cli.timings_path, Some(PathBuf::from("timings.json")));
+        assert_eq!(cli.timings.game_cooldown_secs, Some(50));
+        assert_eq!(cli.timings.turn_timeout_secs, Some(90));
+    }
+
+    #[test]
+    fn command_line_goes_over_the_file_and_guilds_over_both() {
+        let file = TempFile::new(
+            "timings-layers.json",
+            r#"{"turn_secs": 20, "game_cooldown_secs": 100, "guilds": {"7": {"turn_secs": 5}}}"#,
+        );
+        let config = TimingConfig::load(
+            &file.path(),
+            &TimingOverrides {
+                game_cooldown_secs: Some(50),
+                turn_timeout_secs: Some(90),
+                ..TimingOverrides::default()
+            },
+        );
+
+        assert_eq!(
+            config.base,
+            Timings {
+                turn_secs: 20,
+                game_cooldown_secs: 50,
+                turn_timeout_secs: 90,
+                ..Timings::default()
+            }
+        );
+        assert_eq!(
+            config.guild(Id::new(7)),
+            Timings {
+                turn_secs: 5,
+                ..config.base
+            }
+        );
+        assert_eq!(config.guild(Id::new(8)), config.base);
+    }
+
+    #[test]
+    fn broken_timings_fall_back() {
+        let file = TempFile::new(
+            "timings-broken.json",
+            r#"{"turn_secs": 0, "guilds": {"7": {"turn_secs": 5}}}"#,
+        );
+        let config = TimingConfig::load(&file.path(), &TimingOverrides::default());
+        assert_eq!(config.base, Timings::default());
+        assert_eq!(config.guild(Id::new(7)).turn_secs, 5);
+
+        let file = TempFile::new("timings-too-long.json", "{}");
+        let config = TimingConfig::load(
+            &file.path(),
+            &TimingOverrides {
+                max_game_duration_secs: Some(3600),
+                ..TimingOverrides::default()
+            },
+        );
+        assert_eq!(config.base, Timings::default());
+
+        let file = TempFile::new(
+            "timings-broken-guild.json",
+            r#"{"turn_secs": 20, "guilds": {"7": {"frame_millis": 0}, "8": {"turn_secs": 5}}}"#,
+        );
+        let config = TimingConfig::load(&file.path(), &TimingOverrides::default());
+        assert_eq!(config.guild(Id::new(7)), config.base);
+        assert_eq!(config.guild(Id::new(8)).turn_secs, 5);
+
+        let config = TimingConfig::load(
+            &TempFile::new("timings-unreadable.json", "{\"turn_secs\": ").path(),
+            &TimingOverrides::default(),
+        );
+        assert_eq!(config, TimingConfig::default());
+    }
+}
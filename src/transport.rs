@@ -0,0 +1,99 @@
+use std::error::Error;
+
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::events::{GameRenderEvent, InputEvent};
+
+/// Both ends the game loop talks through, independent of the chat platform
+pub struct TransportChannels {
+    pub inputs: Receiver<InputEvent>,
+    pub renders: Sender<GameRenderEvent>,
+}
+
+/// A chat platform frontend: it turns platform interactions into `InputEvent`s
+/// and delivers the `GameRenderEvent`s the game loop produces back to the players
+pub trait ChatTransport {
+    fn connect(self: Box<Self>) -> Result<TransportChannels, Box<dyn Error + Send + Sync>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use crossbeam_channel::{unbounded, Receiver, Sender};
+    use twilight_model::id::Id;
+
+    use super::{ChatTransport, TransportChannels};
+    use crate::{
+        components::PlayerClass,
+        enemies::{EnemyDefinitions, DEFAULT_ENEMY},
+        events::{GameRenderEvent, GameRenderPayload, GameStartEvent, InputEvent},
+        game_app,
+        game_helpers::{Clock, Difficulty, GameMode, GameScope, TurnMode},
+        localization::{Language, LanguageChoice, Localizations},
+        scoreboard::Scoreboard,
+        test_support::{TestGame, DRAW_DELAY, FRAME, TEST_SEED},
+        timings::TimingConfig,
+    };
+
+    /// A chat platform that hands over whatever channels the test gives it
+    struct FakeTransport {
+        inputs: Receiver<InputEvent>,
+        renders: Sender<GameRenderEvent>,
+    }
+
+    impl ChatTransport for FakeTransport {
+        fn connect(self: Box<Self>) -> Result<TransportChannels, Box<dyn Error + Send + Sync>> {
+            Ok(TransportChannels {
+                inputs: self.inputs,
+                renders: self.renders,
+            })
+        }
+    }
+
+    #[test]
+    fn connected_transport_gets_the_battle_drawn() {
+        let (input_sender, inputs) = unbounded();
+        let (renders, render_receiver) = unbounded();
+        let transport: Box<dyn ChatTransport> = Box::new(FakeTransport { inputs, renders });
+        // Wired up the way `main` does it
+        let TransportChannels { inputs, renders } = transport.connect().unwrap();
+        let mut app = game_app(
+            inputs,
+            renders,
+            Scoreboard::default(),
+            Clock::manual(),
+            GameScope::Channel,
+            EnemyDefinitions::default(),
+            TimingConfig::default(),
+            Some(TEST_SEED),
+        );
+
+        let (player, player_name) = TestGame::player(1);
+        input_sender
+            .send(InputEvent::GameStart(Box::new(GameStartEvent::new(
+                player,
+                player_name,
+                Some(Difficulty::Easy),
+                DEFAULT_ENEMY.to_string(),
+                PlayerClass::default(),
+                GameMode::Classic,
+                TurnMode::RealTime,
+                TestGame::arena(),
+                Id::new(1),
+                LanguageChoice::default(),
+                Localizations::new().get(Language::En).clone(),
+            ))))
+            .unwrap();
+        app.update();
+        for _ in 0..(DRAW_DELAY.as_millis() / FRAME.as_millis()) {
+            app.world.resource_mut::<Clock>().advance(FRAME);
+            app.update();
+        }
+
+        let drawn: Vec<_> = render_receiver.try_iter().collect();
+        assert!(drawn.iter().any(|ev| ev.arena == TestGame::arena()
+            && ev.interaction_id == Id::new(1)
+            && matches!(ev.payload, GameRenderPayload::OngoingGame(_))));
+    }
+}